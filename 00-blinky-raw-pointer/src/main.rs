@@ -2,6 +2,7 @@
 #![no_main]
 
 use panic_halt as _;
+use pinecil_delay::{clock::IRC8M_HZ, Delay};
 
 #[riscv_rt::entry]
 fn main() -> ! {
@@ -26,16 +27,11 @@ fn init_ports() {
     }
 }
 
-fn delay(mut n: u32) {
-    while n != 0 {
-        unsafe {
-            core::ptr::write_volatile(&mut n, n - 1);
-        }
-    }
-}
-
 // Blink LED (PA2).
 fn blink_led() {
+    // Nothing changes the clock, so the core still runs from the internal RC
+    // oscillator it starts on.
+    let delay = Delay::new(IRC8M_HZ);
     const BITMASK: u32 = 1 << 2;
     let mut bits: u32 = BITMASK;
     loop {
@@ -43,8 +39,7 @@ fn blink_led() {
             // LED on when PA2 bit is 0
             core::ptr::write_volatile(GPIOA_OCTL, bits);
         }
-        // Delay for an inexact duration
-        delay(0x4ffff);
+        delay.delay_ms(500);
         bits ^= BITMASK;
     }
 }
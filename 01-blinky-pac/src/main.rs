@@ -2,6 +2,7 @@
 #![no_main]
 
 use panic_halt as _;
+use pinecil_delay::Delay;
 
 #[riscv_rt::entry]
 fn main() -> ! {
@@ -37,21 +38,14 @@ fn init_ports(peripherals: &mut gd32vf103_pac::Peripherals) {
     });
 }
 
-fn delay(mut n: u32) {
-    while n != 0 {
-        unsafe {
-            core::ptr::write_volatile(&mut n, n - 1);
-        }
-    }
-}
-
 fn blink_led(peripherals: &mut gd32vf103_pac::Peripherals) {
+    let delay = Delay::from_rcu();
     loop {
         // Toggle LED on PA2.
         peripherals
             .GPIOA
             .octl
             .modify(|r, w| w.octl2().bit(!r.octl2().bit()));
-        delay(0x4ffff);
+        delay.delay_ms(500);
     }
 }
@@ -5,6 +5,7 @@
 use gd32vf103_pac::USART1;
 
 use panic_halt as _;
+use pinecil_delay::Delay;
 
 #[riscv_rt::entry]
 fn main() -> ! {
@@ -143,13 +144,15 @@ fn init_ports(peripherals: &mut gd32vf103_pac::Peripherals) {
 }
 
 fn send_uart(peripherals: &mut gd32vf103_pac::Peripherals) {
+    // After `init_clock`, so that it sees the 96MHz from the PLL.
+    let delay = Delay::from_rcu();
     loop {
         // Send "Hello world!\n".
         for &b in b"Hello world!\r\n" {
             send_uart_byte(&mut peripherals.USART1, b);
         }
 
-        delay(0x4ffff);
+        delay.delay_ms(500);
     }
 }
 
@@ -160,11 +163,3 @@ fn send_uart_byte(usart: &mut USART1, b: u8) {
     // Send byte.
     usart.data.write(|w| unsafe { w.data().bits(b as u16) });
 }
-
-fn delay(mut n: u32) {
-    while n != 0 {
-        unsafe {
-            core::ptr::write_volatile(&mut n, n - 1);
-        }
-    }
-}
@@ -1,23 +1,51 @@
 #![no_std]
 #![no_main]
 
-use core::{fmt::Write, iter::repeat};
+use core::{cell::RefCell, fmt::Write};
 
 use panic_halt as _;
 
-use embedded_graphics::{
-    image::{Image, ImageRaw},
-    pixelcolor::BinaryColor,
-    prelude::*,
+use embedded_hal::{
+    digital::v2::{InputPin, OutputPin},
+    serial::Read,
 };
-use embedded_hal::digital::v2::{InputPin, OutputPin};
 use gd32vf103xx_hal::{self as hal, prelude::*};
 use hal::{delay::McycleDelay, time::Bps};
+use pinecil_bsp::{
+    adc::Adc,
+    bma223::Bma223,
+    eclic::{self, Irq},
+    flash::SettingsFlash,
+    i2c::SharedI2c,
+    mtimer, stack, trap,
+};
+use pinecil_core::{
+    anim::Animation,
+    shell::{Command, Edit, LineEditor, PROMPT},
+    stack::Watch,
+    timers::Timers,
+    ui::{Buttons, Inputs, OledDemo, TICK_MS},
+};
 
 use ssd1306::{prelude::*, Builder, I2CDIBuilder};
 
+/// How often the supply voltage is measured.
+const SUPPLY_MS: u32 = 100;
+/// How often the stack usage is checked against the warning threshold.
+const STACK_MS: u32 = 1000;
+
+/// The jobs of the main loop, each on its own timer.
+#[derive(Clone, Copy, Debug)]
+enum Job {
+    Ui,
+    Supply,
+    Stack,
+}
+
 #[riscv_rt::entry]
 fn main() -> ! {
+    stack::paint();
+
     let peripherals = gd32vf103_pac::Peripherals::take().unwrap();
 
     // Use external 8MHz HXTAL and set PLL to get 96MHz system clock.
@@ -36,7 +64,7 @@ fn main() -> ! {
     let pa2_tx = pa.pa2.into_alternate_push_pull();
     let pa3_rx = pa.pa3.into_pull_up_input();
 
-    let (mut uart1_tx, _uart1_rx) = hal::serial::Serial::new(
+    let (mut uart1_tx, mut uart1_rx) = hal::serial::Serial::new(
         peripherals.USART1,
         (pa2_tx, pa3_rx),
         hal::serial::Config {
@@ -48,6 +76,20 @@ fn main() -> ! {
     )
     .split();
 
+    if let Some(crash) = trap::take_crash() {
+        let _ = write!(uart1_tx, "Crashed before the reset: {}\r\n", crash);
+    }
+
+    // The supply voltage is shown on the status line of the screens.
+    let mut adc = Adc::new(
+        peripherals.ADC0,
+        (
+            pa.pa0.into_analog(),
+            pa.pa1.into_analog(),
+            pa.pa4.into_analog(),
+        ),
+    );
+
     let pb = peripherals.GPIOB.split(&mut rcu);
     // Use PB0 as input for the '+' button (butt_B).
     let btn_b = pb.pb0.into_pull_down_input();
@@ -65,8 +107,8 @@ fn main() -> ! {
     let pb6_scl = pb.pb6.into_alternate_open_drain();
     let pb7_sda = pb.pb7.into_alternate_open_drain();
 
-    // Set up i2c.
-    let i2c0 = hal::i2c::BlockingI2c::i2c0(
+    // The OLED and the BMA223 accelerometer share I2C0.
+    let i2c0 = RefCell::new(hal::i2c::BlockingI2c::i2c0(
         peripherals.I2C0,
         (pb6_scl, pb7_sda),
         &mut afio,
@@ -79,7 +121,7 @@ fn main() -> ! {
         10,
         1000,
         1000,
-    );
+    ));
 
     // OLED datasheet recommends 100 ms delay on power up.
     delay.delay_ms(100);
@@ -91,178 +133,114 @@ fn main() -> ! {
     delay.delay_us(3);
 
     let mut disp = {
-        let interface = I2CDIBuilder::new().init(i2c0);
+        let interface = I2CDIBuilder::new().init(SharedI2c::new(&i2c0));
 
-        let mut disp_g: GraphicsMode<_, _> = Builder::new()
+        let mut disp: GraphicsMode<_, _> = Builder::new()
             .size(DisplaySize96x16)
             .with_rotation(DisplayRotation::Rotate180)
             .connect(interface)
             .into();
-        disp_g.init().unwrap_or_else(|e| {
+        disp.init().unwrap_or_else(|e| {
             write!(uart1_tx, "Error initializing OLED: {:?}\r\n", e).unwrap();
             panic!()
         });
-
-        DisplayModeEnum::Graphics(disp_g)
+        disp
     };
-
-    let mut state = 1;
-    let mut brightness = 0x0F_u8;
-
-    macro_rules! set_brightness {
-        ($disp:expr) => {{
-            let _ = $disp.set_brightness(Brightness::custom(0xF1, brightness));
-        }};
-    }
-    macro_rules! btn_check {
-        ($e:expr) => {
-            if btn_a.is_high().unwrap() {
-                state += 1;
-                $e
-            }
-            if btn_b.is_high().unwrap() {
-                brightness = brightness.wrapping_add(16);
-                $e
-            }
-        };
-    }
-    macro_rules! wait_btn_release {
-        () => {
-            while btn_a.is_high().unwrap() || btn_b.is_high().unwrap() {}
-        };
-    }
-
+    // The accelerometer screen says so if there is none.
+    let mut bma = Bma223::new(SharedI2c::new(&i2c0)).ok();
+
+    // The screens live in `pinecil_core::ui` so that they can also be run in
+    // the simulator in `tools/`.
+    // The language is the one chosen in the settings menu of demo 08.
+    let language = SettingsFlash::new(peripherals.FMC)
+        .load()
+        .map(|settings| settings.language)
+        .unwrap_or_default();
+    let anim = Animation::parse(include_bytes!("frames.anim")).unwrap();
+    let mut ui = OledDemo::new(anim, language);
+    // Last contrast sent to the display, to avoid sending it every frame.
+    let mut brightness = None;
+
+    // The UI and the supply measurement run at their own rates from the
+    // millisecond tick, and the core waits for the next tick in between.
+    eclic::init();
+    eclic::set_handler(Irq::Timer, mtimer::on_interrupt);
+    eclic::enable(Irq::Timer);
+    mtimer::start(rcu.clocks.sysclk().0);
+    let mut timers: Timers<Job, 3> = Timers::new(mtimer::now_ms());
+    timers.start_periodic(TICK_MS as u32, Job::Ui).unwrap();
+    timers.start_periodic(SUPPLY_MS, Job::Supply).unwrap();
+    timers.start_periodic(STACK_MS, Job::Stack).unwrap();
+    let mut stack_watch = Watch::default();
+    // The shell is polled between ticks, which keeps up with typing but
+    // not with pasting.
+    let mut shell = LineEditor::new();
+    let _ = write!(uart1_tx, "\r\nType help for the commands\r\n{}", PROMPT);
+    let mut supply_mv = Some(adc.read_vin_mv());
+    let mut last_ui_ms = mtimer::now_ms();
     loop {
-        match state {
-            1 => {
-                disp.use_graphics(|disp_g| {
-                    set_brightness!(disp_g);
-                    let raw_frames = {
-                        macro_rules! frame {
-                            ($s:expr) => {
-                                ImageRaw::<BinaryColor>::new(include_bytes!($s), 96, 16)
-                            };
-                        }
-                        [frame!("frame0.raw"), frame!("frame1.raw")]
-                    };
-                    for raw in repeat(&raw_frames).flatten() {
-                        let image = Image::new(raw, (0, 0).into());
-                        let _ = image.draw(disp_g);
-                        disp_g.flush().unwrap();
-
-                        for _ in 0..10 {
-                            delay.delay_ms(25);
-                            btn_check!(return);
-                        }
-                    }
-                });
-                wait_btn_release!();
+        while let Ok(byte) = uart1_rx.read() {
+            match shell.push(byte) {
+                Edit::Echo(byte) => {
+                    let _ = write!(uart1_tx, "{}", byte as char);
+                }
+                Edit::Erase => {
+                    let _ = write!(uart1_tx, "\x08 \x08");
+                }
+                Edit::Line(line) => run_command(&mut uart1_tx, line),
+                Edit::Nothing => {}
             }
-            2 => {
-                disp.use_terminal(|disp_t| {
-                    set_brightness!(disp_t);
-                    let _ = disp_t.clear();
-
-                    let _ = disp_t.write_str("Hello world!");
-                    loop {
-                        btn_check!(return);
+        }
+        let now_ms = mtimer::now_ms();
+        while let Some(job) = timers.poll(now_ms) {
+            match job {
+                Job::Supply => supply_mv = Some(adc.read_vin_mv()),
+                Job::Stack => {
+                    if let Some(usage) = stack::usage().filter(|&u| stack_watch.check(u)) {
+                        let _ = write!(uart1_tx, "\r\nWarning: stack at {}\r\n", usage);
                     }
-                });
-                wait_btn_release!();
-            }
-            3 => {
-                disp.use_terminal(|disp_t| {
-                    set_brightness!(disp_t);
-                    let _ = disp_t.clear();
-
-                    for c in repeat((b'a'..=b'z').chain(b'A'..=b'Z').chain(b'0'..=b'9')).flatten() {
-                        let _ = disp_t.print_char(c.into());
-                        for _ in 0..4 {
-                            delay.delay_ms(25);
-                            btn_check!(return);
+                }
+                Job::Ui => {
+                    let inputs = Inputs {
+                        buttons: Buttons {
+                            plus: btn_b.is_high().unwrap(),
+                            minus: btn_a.is_high().unwrap(),
+                        },
+                        accel: bma.as_mut().and_then(|bma| bma.read().ok()),
+                        supply_mv,
+                    };
+                    let elapsed_ms = now_ms.wrapping_sub(last_ui_ms).min(u16::MAX as u32) as u16;
+                    last_ui_ms = now_ms;
+                    if ui.update(inputs, elapsed_ms) {
+                        let _ = ui.draw(&mut disp);
+                        if brightness != Some(ui.brightness()) {
+                            brightness = Some(ui.brightness());
+                            let contrast = Brightness::custom(0xF1, ui.brightness());
+                            let _ = disp.set_brightness(contrast);
                         }
+                        let _ = disp.flush();
                     }
-                });
-                wait_btn_release!();
-            }
-            4 => {
-                disp.use_terminal(|disp_t| {
-                    set_brightness!(disp_t);
-                    let _ = disp_t.clear();
-
-                    let _ = write!(disp_t, "Brightness:\n--{}--", brightness);
-                    loop {
-                        btn_check!(return);
-                    }
-                });
-                wait_btn_release!();
+                }
             }
-            0 => state = 3,
-            _ => state = 1,
         }
+        unsafe { riscv::asm::wfi() };
     }
 }
 
-enum DisplayModeEnum<T, U>
-where
-    T: WriteOnlyDataCommand,
-    U: ssd1306::displaysize::DisplaySize + ssd1306::mode::terminal::TerminalDisplaySize,
-{
-    None,
-    Graphics(GraphicsMode<T, U>),
-    Terminal(TerminalMode<T, U>),
-}
-
-impl<T, U> DisplayModeEnum<T, U>
-where
-    T: WriteOnlyDataCommand,
-    U: ssd1306::displaysize::DisplaySize + ssd1306::mode::terminal::TerminalDisplaySize,
-{
-    fn into_inner(self) -> DisplayProperties<T, U> {
-        match self {
-            DisplayModeEnum::Graphics(m) => m.into_properties(),
-            DisplayModeEnum::Terminal(m) => m.into_properties(),
-            DisplayModeEnum::None => panic!(),
-        }
-    }
-
-    fn take_inner(&mut self) -> DisplayProperties<T, U> {
-        core::mem::replace(self, DisplayModeEnum::None).into_inner()
-    }
-
-    fn use_graphics(&mut self, f: impl FnOnce(&mut GraphicsMode<T, U>)) {
-        let mut m = match self {
-            DisplayModeEnum::Graphics(m) => m,
-            _ => {
-                let m: GraphicsMode<_, _> = self.take_inner().into();
-                *self = DisplayModeEnum::Graphics(m);
-                match self {
-                    DisplayModeEnum::Graphics(m) => {
-                        m.init().unwrap();
-                        m
-                    }
-                    _ => unreachable!(),
-                }
-            }
-        };
-        f(&mut m);
-    }
-
-    fn use_terminal(&mut self, f: impl FnOnce(&mut TerminalMode<T, U>)) {
-        let mut m = match self {
-            DisplayModeEnum::Terminal(m) => m,
-            _ => {
-                let m: TerminalMode<_, _> = self.take_inner().into();
-                *self = DisplayModeEnum::Terminal(m);
-                match self {
-                    DisplayModeEnum::Terminal(m) => {
-                        m.init().unwrap();
-                        m
-                    }
-                    _ => unreachable!(),
-                }
-            }
-        };
-        f(&mut m);
-    }
+/// Answers a line entered in the shell.
+fn run_command(tx: &mut impl Write, line: &str) {
+    let _ = write!(tx, "\r\n");
+    let _ = match Command::parse(line) {
+        Some(Command::Help) => Command::ALL
+            .iter()
+            .try_for_each(|c| write!(tx, "{:10}{}\r\n", c.name(), c.description())),
+        Some(Command::Stack) => match stack::usage() {
+            Some(usage) => write!(tx, "Stack: at most {}\r\n", usage),
+            None => write!(tx, "The stack has not been painted\r\n"),
+        },
+        Some(Command::Counters) => write!(tx, "This demo keeps no counters, see demo 08\r\n"),
+        None if line.trim().is_empty() => Ok(()),
+        None => write!(tx, "Unknown command, try help\r\n"),
+    };
+    let _ = write!(tx, "{}", PROMPT);
 }
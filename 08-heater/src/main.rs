@@ -0,0 +1,465 @@
+#![no_std]
+#![no_main]
+
+use core::{cell::RefCell, fmt::Write, panic::PanicInfo};
+
+use embedded_hal::{
+    blocking::i2c::{Write as I2cWrite, WriteRead},
+    digital::v2::{InputPin, OutputPin},
+    serial::Read,
+};
+use gd32vf103xx_hal::{self as hal, prelude::*};
+use hal::{delay::McycleDelay, time::Bps};
+use pinecil_bsp::{
+    adc::Adc,
+    bma223::Bma223,
+    flash::SettingsFlash,
+    fusb302::{self, Event, Fusb302},
+    heater::{self, Heater},
+    i2c::SharedI2c,
+    rtc::Rtc,
+    stack, trap,
+    watchdog::{self, Watchdog},
+};
+use pinecil_core::{
+    counters::{self, Counters, Tracker},
+    iron::{IronUi, Status},
+    pd::sink::{self, Action, Sink, State},
+    pid::{power_limit, Pid, PINECIL_CONFIG},
+    power::Supply,
+    shell::{Command, Edit, LineEditor, PROMPT},
+    sleep::{self, Sleep},
+    thermocouple,
+    tip_model::PINECIL_TIP,
+    ui::{Buttons, TICK_MS},
+};
+use riscv::register::mcycle;
+use ssd1306::{prelude::*, Builder, I2CDIBuilder};
+
+/// Time for the op-amp to settle after the heater turns off, before the tip
+/// is sampled.
+const SETTLE_US: u32 = 500;
+
+/// The main loop takes a few milliseconds, and erasing the settings page
+/// stalls it for a few more, so this only goes off if it hangs.
+const WATCHDOG_MS: u32 = 1000;
+
+/// What to ask a USB PD charger for. The tip draws 2.5 A at 20 V.
+const PD_CONFIG: sink::Config = sink::Config {
+    max_mv: 20_000,
+    max_ma: 3_000,
+};
+
+/// Turn the heater off before anything else, in case the pin was left
+/// floating or driven high across the reset.
+#[riscv_rt::pre_init]
+unsafe fn before_main() {
+    heater::force_off();
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    unsafe { heater::force_off() };
+    loop {
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Passes what the FUSB302B received to the sink, and what the sink wants to
+/// send back to the FUSB302B.
+fn run_pd<I2C, E>(
+    fusb: &mut Fusb302<I2C>,
+    sink: &mut Sink,
+    now_ms: u32,
+) -> Result<(), fusb302::Error<E>>
+where
+    I2C: I2cWrite<Error = E> + WriteRead<Error = E>,
+{
+    let action = match fusb.poll()? {
+        Some(Event::Received(message)) => sink.receive(&message, now_ms),
+        Some(Event::HardReset) => {
+            sink.hard_reset_received(now_ms);
+            None
+        }
+        None => sink.poll(now_ms),
+    };
+    match action {
+        Some(Action::Send(message)) => fusb.send(&message),
+        Some(Action::HardReset) => fusb.hard_reset(),
+        None => Ok(()),
+    }
+}
+
+/// Answers a line entered in the shell.
+fn run_command(tx: &mut impl Write, line: &str, counters: &Counters) {
+    let _ = write!(tx, "\r\n");
+    let _ = match Command::parse(line) {
+        Some(Command::Help) => Command::ALL
+            .iter()
+            .try_for_each(|c| write!(tx, "{:10}{}\r\n", c.name(), c.description())),
+        Some(Command::Stack) => match stack::usage() {
+            Some(usage) => write!(tx, "Stack: at most {}\r\n", usage),
+            None => write!(tx, "The stack has not been painted\r\n"),
+        },
+        Some(Command::Counters) => write!(tx, "Counters: {}\r\n", counters),
+        None if line.trim().is_empty() => Ok(()),
+        None => write!(tx, "Unknown command, try help\r\n"),
+    };
+    let _ = write!(tx, "{}", PROMPT);
+}
+
+#[riscv_rt::entry]
+fn main() -> ! {
+    stack::paint();
+    let peripherals = gd32vf103_pac::Peripherals::take().unwrap();
+
+    // Use external 8MHz HXTAL and set PLL to get 96MHz system clock.
+    let mut rcu = peripherals
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(96.mhz())
+        .freeze();
+
+    let mut delay = McycleDelay::new(&rcu.clocks);
+
+    let mut afio = peripherals.AFIO.constrain(&mut rcu);
+
+    let pa = peripherals.GPIOA.split(&mut rcu);
+    let pa2_tx = pa.pa2.into_alternate_push_pull();
+    let pa3_rx = pa.pa3.into_pull_up_input();
+
+    let (mut uart1_tx, mut uart1_rx) = hal::serial::Serial::new(
+        peripherals.USART1,
+        (pa2_tx, pa3_rx),
+        hal::serial::Config {
+            baudrate: Bps(2_000_000),
+            ..Default::default()
+        },
+        &mut afio,
+        &mut rcu,
+    )
+    .split();
+
+    let _ = write!(
+        uart1_tx,
+        "\r\nReset by {}\r\n",
+        watchdog::take_reset_cause()
+    );
+    if let Some(crash) = trap::take_crash() {
+        let _ = write!(uart1_tx, "Crashed before the reset: {}\r\n", crash);
+    }
+
+    let cycles_per_ms = rcu.clocks.sysclk().0 as u64 / 1000;
+    let now_ms = || (mcycle::read64() / cycles_per_ms) as u32;
+
+    let pb = peripherals.GPIOB.split(&mut rcu);
+    // Use PB0 as input for the '+' button (butt_B).
+    let btn_b = pb.pb0.into_pull_down_input();
+    // USE PB1 as input for the '-' button (butt_A), which is already pulled
+    // low externally.
+    let btn_a = pb.pb1.into_floating_input();
+
+    let mut heater = Heater::new(peripherals.TIMER2, pa.pa6, rcu.clocks.timerx());
+    let mut adc = Adc::new(
+        peripherals.ADC0,
+        (
+            pa.pa0.into_analog(),
+            pa.pa1.into_analog(),
+            pa.pa4.into_analog(),
+        ),
+    );
+    let mut supply = Supply::new();
+
+    // OLED reset: Pull low to reset.
+    let mut oled_reset = pa
+        .pa9
+        .into_push_pull_output_with_state(hal::gpio::State::Low);
+
+    // From here on, the iron resets itself if it hangs, e.g. on a stuck I2C
+    // bus while setting up the chips on it.
+    let mut watchdog = Watchdog::start(peripherals.FWDGT, WATCHDOG_MS).unwrap();
+
+    // The OLED, the FUSB302B USB PD controller and the BMA223 accelerometer
+    // share I2C0.
+    let i2c0 = RefCell::new(hal::i2c::BlockingI2c::i2c0(
+        peripherals.I2C0,
+        (
+            pb.pb6.into_alternate_open_drain(),
+            pb.pb7.into_alternate_open_drain(),
+        ),
+        &mut afio,
+        hal::i2c::Mode::Fast {
+            frequency: 400_000.hz(),
+            duty_cycle: hal::i2c::DutyCycle::Ratio2to1,
+        },
+        &mut rcu,
+        1000,
+        10,
+        1000,
+        1000,
+    ));
+
+    // OLED datasheet recommends 100 ms delay on power up, then 3 us after
+    // the reset to wait for init.
+    delay.delay_ms(100);
+    oled_reset.set_high().unwrap();
+    delay.delay_us(3);
+    let mut disp = {
+        let interface = I2CDIBuilder::new().init(SharedI2c::new(&i2c0));
+        let mut disp: GraphicsMode<_, _> = Builder::new()
+            .size(DisplaySize96x16)
+            .with_rotation(DisplayRotation::Rotate180)
+            .connect(interface)
+            .into();
+        // The iron works without a display, so carry on regardless.
+        if let Err(e) = disp.init() {
+            let _ = write!(uart1_tx, "Error initializing OLED: {:?}\r\n", e);
+        }
+        disp
+    };
+    watchdog.feed();
+
+    let mut sink = Sink::new(PD_CONFIG);
+    // The iron is powered from VBUS, so it never sees the charger go away:
+    // attaching once is enough.
+    let mut fusb = match Fusb302::new(SharedI2c::new(&i2c0)) {
+        Ok(mut fusb) => match fusb.attach() {
+            Ok(true) => {
+                sink.attach(now_ms());
+                Some(fusb)
+            }
+            _ => {
+                let _ = write!(uart1_tx, "No USB PD source, staying at 5 V\r\n");
+                None
+            }
+        },
+        Err(_) => {
+            let _ = write!(uart1_tx, "No USB PD controller, staying at 5 V\r\n");
+            None
+        }
+    };
+    let mut pd_state = sink.state();
+    watchdog.feed();
+
+    let mut bma = Bma223::new(SharedI2c::new(&i2c0)).ok();
+    if bma.is_none() {
+        let _ = write!(uart1_tx, "No accelerometer, the iron will not sleep\r\n");
+    }
+    watchdog.feed();
+    let mut sleep = Sleep::new(sleep::DEFAULT_CONFIG, now_ms());
+    let mut sleep_state = sleep.state();
+
+    // A blank page or a page from a newer firmware falls back to the
+    // defaults, which are only written once they have been changed.
+    let mut flash = SettingsFlash::new(peripherals.FMC);
+    let settings = flash.load().unwrap_or_else(|e| {
+        let _ = write!(uart1_tx, "Using default settings ({:?})\r\n", e);
+        Default::default()
+    });
+    // The counters in the backup registers are newer than those in the
+    // flash, unless the iron was unplugged since they were saved.
+    let mut rtc = Rtc::new(peripherals.RTC);
+    let mut tracker = {
+        let mut backup = [0; counters::BACKUP_WORDS];
+        rtc.read_backup(&mut backup);
+        let counters = Counters::latest(flash.load_counters(), Counters::from_backup(&backup));
+        Tracker::new(counters, rtc.now_s())
+    };
+    let mut save_counters = false;
+    let mut ui = IronUi::new(settings);
+    ui.set_counters(tracker.counters());
+    let mut last_tick_ms = now_ms();
+    let mut redraw = true;
+    let mut status = Status {
+        tip: None,
+        duty: 0,
+        supply_mv: None,
+        sleep: sleep.state(),
+    };
+
+    let mut pid = Pid::new(PINECIL_CONFIG);
+    let mut previous = (false, false);
+    // Set while the buttons which woke the iron up are still held.
+    let mut waking = false;
+    let mut tripped = false;
+    // The shell is polled once per loop, which keeps up with typing but not
+    // with pasting.
+    let mut shell = LineEditor::new();
+    let _ = write!(uart1_tx, "\r\nType help for the commands\r\n{}", PROMPT);
+    loop {
+        watchdog.feed();
+        while let Ok(byte) = uart1_rx.read() {
+            match shell.push(byte) {
+                Edit::Echo(byte) => {
+                    let _ = write!(uart1_tx, "{}", byte as char);
+                }
+                Edit::Erase => {
+                    let _ = write!(uart1_tx, "\x08 \x08");
+                }
+                Edit::Line(line) => run_command(&mut uart1_tx, line, &tracker.counters()),
+                Edit::Nothing => {}
+            }
+        }
+        if let Some(f) = fusb.as_mut() {
+            if run_pd(f, &mut sink, now_ms()).is_err() {
+                let _ = write!(uart1_tx, "\r\nFUSB302B stopped answering\r\n");
+                fusb = None;
+            }
+        }
+        if sink.state() != pd_state {
+            pd_state = sink.state();
+            match (pd_state, sink.contract()) {
+                (State::Ready, Some(contract)) => {
+                    let _ = write!(
+                        uart1_tx,
+                        "\r\nUSB PD: {}.{:02} V, {}.{:02} A\r\n",
+                        contract.mv / 1000,
+                        contract.mv % 1000 / 10,
+                        contract.ma / 1000,
+                        contract.ma % 1000 / 10
+                    );
+                    let budget_mw = contract.mv * contract.ma / 1000;
+                    pid.set_limit(power_limit(
+                        budget_mw,
+                        contract.mv,
+                        PINECIL_TIP.resistance_mohm,
+                    ));
+                }
+                (State::Failed, _) => {
+                    let _ = write!(uart1_tx, "\r\nUSB PD: no answer, staying at 5 V\r\n");
+                }
+                _ => {}
+            }
+        }
+
+        let buttons = (btn_b.is_high().unwrap(), btn_a.is_high().unwrap());
+        let pressed = (buttons.0 && !previous.0, buttons.1 && !previous.1);
+        previous = buttons;
+
+        let was_active = sleep.state() == sleep::State::Active;
+        let accel = bma.as_mut().and_then(|bma| bma.read().ok());
+        if !was_active && (pressed.0 || pressed.1) {
+            waking = true;
+        }
+        if sleep.update(accel, pressed.0 || pressed.1, now_ms()) != sleep_state {
+            sleep_state = sleep.state();
+            let _ = match sleep_state {
+                sleep::State::Active => write!(uart1_tx, "\r\nAwake\r\n"),
+                sleep::State::Standby => write!(uart1_tx, "\r\nIdle, standby\r\n"),
+                sleep::State::Off => write!(uart1_tx, "\r\nIdle, heater off\r\n"),
+            };
+            // The iron may well be unplugged next.
+            save_counters |= sleep_state == sleep::State::Off;
+        }
+
+        let now_s = rtc.now_s();
+        if tracker.update(now_s, sleep.target(ui.target()) > 0) {
+            rtc.write_backup(&tracker.counters().to_backup());
+            redraw |= ui.set_counters(tracker.counters());
+        }
+        if save_counters || tracker.save_due(now_s) {
+            save_counters = false;
+            if let Err(e) = flash.store_counters(&tracker.counters()) {
+                let _ = write!(uart1_tx, "\r\nSaving counters failed: {:?}\r\n", e);
+            }
+            // Even if it failed, so that it is only tried again at the next
+            // interval.
+            tracker.saved(now_s);
+        }
+
+        if buttons == (false, false) {
+            waking = false;
+        }
+
+        let elapsed_ms = now_ms().wrapping_sub(last_tick_ms);
+        if elapsed_ms >= TICK_MS as u32 {
+            last_tick_ms = last_tick_ms.wrapping_add(elapsed_ms);
+            // A press which wakes the iron up does not reach the UI, and
+            // neither does holding both buttons.
+            let buttons = match buttons {
+                _ if waking => Buttons::default(),
+                (true, true) => Buttons::default(),
+                (plus, minus) => Buttons { plus, minus },
+            };
+            redraw |= ui.update(buttons, elapsed_ms.min(u16::MAX as u32) as u16);
+            if let Some(settings) = ui.take_changed_settings() {
+                if let Err(e) = flash.store(&settings) {
+                    let _ = write!(uart1_tx, "\r\nSaving settings failed: {:?}\r\n", e);
+                }
+            }
+        }
+        if redraw {
+            redraw = false;
+            let _ = ui.draw(&mut disp, &status);
+            let _ = disp.flush();
+        }
+
+        // Holding both buttons plays dead: the duty is no longer updated, so
+        // the software watchdog cuts the heater.
+        if buttons != (true, true) {
+            heater.set_duty(pid.output());
+        }
+
+        // Each period starts with the heater off, which is when the
+        // thermocouple can be read.
+        if heater.poll() {
+            delay.delay_us(SETTLE_US);
+            supply.update(adc.read_vin_mv());
+            let handle = thermocouple::tmp36_temperature(adc.read_handle_uv());
+            let tip = thermocouple::tip_temperature(adc.read_tip_uv(), handle);
+            let target = sleep.target(ui.target());
+            match tip {
+                Some(tip) if target > 0 && supply.can_heat() => {
+                    pid.update(target, tip);
+                }
+                // Without a tip, with the target at 0 (or asleep) or without
+                // enough power, stay off.
+                _ => pid.reset(),
+            }
+
+            let duty = heater.duty();
+            let mv = supply.mv();
+            let _ = write!(
+                uart1_tx,
+                "{:>2}.{:02} V ({})  target {:>3} C  duty {:>3}.{}%  handle {:>4}.{} C  tip ",
+                mv / 1000,
+                mv % 1000 / 10,
+                supply.source(),
+                target / 10,
+                duty / 10,
+                duty % 10,
+                handle / 10,
+                (handle % 10).abs()
+            );
+            let _ = match tip {
+                Some(tip) => write!(uart1_tx, "{:>5}.{} C", tip / 10, (tip % 10).abs()),
+                None => write!(uart1_tx, "  none "),
+            };
+            let _ = write!(uart1_tx, "        \r");
+
+            // The display shows whole degrees, so only redraw when those
+            // change.
+            let measured = Status {
+                tip: tip.map(|tip| tip / 10 * 10),
+                duty,
+                supply_mv: Some(mv),
+                sleep: sleep.state(),
+            };
+            if measured != status {
+                status = measured;
+                redraw = true;
+            }
+        }
+        if heater.tripped() != tripped {
+            tripped = heater.tripped();
+            if tripped {
+                let _ = write!(uart1_tx, "\r\nWatchdog tripped, heater off\r\n");
+            }
+        }
+
+        // The FUSB302B has to be polled often enough to answer the charger
+        // in time.
+        delay.delay_ms(5);
+    }
+}
@@ -0,0 +1,177 @@
+#![no_std]
+#![no_main]
+
+use core::cell::Cell;
+
+use panic_halt as _;
+
+use gd32vf103xx_hal::{self as hal, prelude::*};
+use hal::time::Bps;
+use pinecil_bsp::{
+    bma223,
+    buttons::{self, Button, Buttons},
+    eclic::{self, Irq},
+    executor,
+    i2c0::{self, I2c0},
+    mtimer,
+    uart::{self, UartRx, UartTx},
+};
+use pinecil_core::{
+    executor::{Executor, Task},
+    pin_mut,
+    ui::Accel,
+};
+
+const BMA223_REG_ACCD_X_LSB: u8 = 0x02;
+const BMA223_REG_INT_OUT_CTRL: u8 = 0x20;
+/// Both interrupt pins open drain, to free up JTAG (see `notes/01-JTAG.md`).
+const BMA223_INT_OPEN_DRAIN: u8 = 0b1010;
+
+static EXECUTOR: Executor<4> = Executor::new();
+
+/// What the tasks have found out, for the report.
+#[derive(Default)]
+struct Status {
+    accel: Cell<Option<Accel>>,
+    plus_presses: Cell<u32>,
+    minus_presses: Cell<u32>,
+    received: Cell<u32>,
+}
+
+#[riscv_rt::entry]
+fn main() -> ! {
+    let peripherals = gd32vf103_pac::Peripherals::take().unwrap();
+
+    // Use external 8MHz HXTAL and set PLL to get 96MHz system clock.
+    let mut rcu = peripherals
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(96.mhz())
+        .freeze();
+
+    let mut afio = peripherals.AFIO.constrain(&mut rcu);
+
+    let pa = peripherals.GPIOA.split(&mut rcu);
+    let (uart1_tx, uart1_rx) = hal::serial::Serial::new(
+        peripherals.USART1,
+        (
+            pa.pa2.into_alternate_push_pull(),
+            pa.pa3.into_pull_up_input(),
+        ),
+        hal::serial::Config {
+            baudrate: Bps(2_000_000),
+            ..Default::default()
+        },
+        &mut afio,
+        &mut rcu,
+    )
+    .split();
+
+    let pb = peripherals.GPIOB.split(&mut rcu);
+    let buttons = Buttons::new(pb.pb0.into_pull_down_input(), pb.pb1.into_floating_input());
+    let i2c0 = I2c0::new(
+        peripherals.I2C0,
+        (
+            pb.pb6.into_alternate_open_drain(),
+            pb.pb7.into_alternate_open_drain(),
+        ),
+        rcu.clocks.pclk1().0,
+    );
+
+    // The clock wakes the sleeping tasks, so it gets the higher level and
+    // keeps time even while another handler runs.
+    eclic::init();
+    eclic::set_handler(Irq::Timer, mtimer::on_interrupt);
+    eclic::set_level(Irq::Timer, 2);
+    let handlers: [(Irq, fn()); 5] = [
+        (Irq::Usart1, uart::on_interrupt),
+        (Irq::I2c0Event, i2c0::on_interrupt),
+        (Irq::I2c0Error, i2c0::on_interrupt),
+        (Irq::Exti0, buttons::on_interrupt),
+        (Irq::Exti1, buttons::on_interrupt),
+    ];
+    for (irq, handler) in handlers {
+        eclic::set_handler(irq, handler);
+        eclic::set_level(irq, 1);
+        eclic::enable(irq);
+    }
+    eclic::enable(Irq::Timer);
+    mtimer::start(rcu.clocks.sysclk().0);
+
+    let status = Status::default();
+    let accel = read_accel(i2c0, &status);
+    let presses = count_presses(buttons, &status);
+    let received = count_received(UartRx::new(uart1_rx), &status);
+    let reports = report(UartTx::new(uart1_tx), &status);
+    pin_mut!(accel, presses, received, reports);
+    let mut tasks: [Option<Task>; 4] = [Some(accel), Some(presses), Some(received), Some(reports)];
+    executor::run(&EXECUTOR, &mut tasks);
+    unreachable!("the tasks never finish")
+}
+
+/// Samples the BMA223 every 100 ms.
+async fn read_accel(mut i2c0: I2c0, status: &Status) {
+    let _ = i2c0
+        .write(
+            bma223::ADDRESS,
+            &[BMA223_REG_INT_OUT_CTRL, BMA223_INT_OPEN_DRAIN],
+        )
+        .await;
+    loop {
+        // Each axis is an LSB and an MSB register, and the MSB alone is the
+        // 8-bit reading.
+        let mut data = [0; 6];
+        let accel = i2c0
+            .write_read(bma223::ADDRESS, &[BMA223_REG_ACCD_X_LSB], &mut data)
+            .await
+            .ok()
+            .map(|()| Accel {
+                x: data[1] as i8,
+                y: data[3] as i8,
+                z: data[5] as i8,
+            });
+        status.accel.set(accel);
+        mtimer::sleep_ms(100).await;
+    }
+}
+
+async fn count_presses(buttons: Buttons, status: &Status) {
+    loop {
+        let presses = match buttons.wait_any().await {
+            Button::Plus => &status.plus_presses,
+            Button::Minus => &status.minus_presses,
+        };
+        presses.set(presses.get() + 1);
+    }
+}
+
+async fn count_received(mut rx: UartRx, status: &Status) {
+    let mut buf = [0; 16];
+    loop {
+        if let Ok(len) = rx.read(&mut buf).await {
+            status.received.set(status.received.get() + len as u32);
+        }
+    }
+}
+
+/// Prints what the other tasks found out, twice a second.
+async fn report(mut tx: UartTx, status: &Status) {
+    tx.write_all(b"\r\nDemo 09 - async tasks\r\n").await;
+    loop {
+        match status.accel.get() {
+            Some(a) => write!(tx, "x={:+4} y={:+4} z={:+4}", a.x, a.y, a.z).await,
+            None => tx.write_all(b"no BMA223").await,
+        }
+        write!(
+            tx,
+            "  +:{} -:{}  rx:{}  {} ms\r\n",
+            status.plus_presses.get(),
+            status.minus_presses.get(),
+            status.received.get(),
+            mtimer::now_ms(),
+        )
+        .await;
+        mtimer::sleep_ms(500).await;
+    }
+}
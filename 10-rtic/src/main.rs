@@ -0,0 +1,318 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use embedded_hal::{
+    blocking::i2c::{Write, WriteRead},
+    digital::v2::OutputPin,
+    serial::{Read, Write as SerialWrite},
+};
+use gd32vf103_pac::{I2C0, USART1};
+use gd32vf103xx_hal::{
+    self as hal,
+    gpio::{
+        gpiob::{PB6, PB7},
+        Alternate, OpenDrain,
+    },
+    prelude::*,
+    serial::{Rx, Tx},
+};
+use hal::{delay::McycleDelay, time::Bps};
+use pinecil_bsp::{
+    adc::Adc,
+    bma223::Bma223,
+    buttons::{self, Button, Buttons},
+    mtimer,
+};
+use pinecil_core::{
+    anim::Animation,
+    lang::Language,
+    timers::Timers,
+    ui::{self, Inputs, OledDemo, TICK_MS},
+};
+use pinecil_rtic::{self as rtic, Irq, Resource, SoftwareTask};
+use ssd1306::{prelude::*, Builder, I2CDIBuilder};
+
+/// How often the BMA223 is sampled.
+const SAMPLE_MS: u32 = 50;
+
+// The tasks, from the highest priority down. The tick and the buttons only
+// take a few microseconds, and the UART has to keep up with the bytes
+// coming in. Sampling the BMA223 is a few short I2C transfers, and the
+// display refresh sends a whole frame.
+const TICK_PRIORITY: u8 = 3;
+const BUTTONS_PRIORITY: u8 = 3;
+const UART_PRIORITY: u8 = 2;
+const SAMPLE_PRIORITY: u8 = 2;
+const REFRESH_PRIORITY: u8 = 1;
+
+static SAMPLE: SoftwareTask = SoftwareTask::new(SAMPLE_PRIORITY, sample);
+static REFRESH: SoftwareTask = SoftwareTask::new(REFRESH_PRIORITY, refresh);
+
+type I2c = hal::i2c::BlockingI2c<I2C0, (PB6<Alternate<OpenDrain>>, PB7<Alternate<OpenDrain>>)>;
+
+/// The jobs of the tick, each on its own timer.
+#[derive(Clone, Copy, Debug)]
+enum Job {
+    Refresh,
+    Sample,
+}
+
+/// Everything the display refresh needs, which only it uses.
+struct Screen {
+    disp: GraphicsMode<I2CInterface<BusI2c>, DisplaySize96x16>,
+    /// Last contrast sent to the display, to avoid sending it every frame.
+    brightness: Option<u8>,
+    ui: OledDemo<'static>,
+    adc: Adc,
+    last_ms: u32,
+}
+
+// The OLED and the BMA223 share I2C0, so the bus has the ceiling of the
+// sampling, and the display refresh holds it back while it sends.
+static I2C_BUS: Resource<Option<I2c>> = Resource::new(SAMPLE_PRIORITY, None);
+static BMA: Resource<Option<Bma223<BusI2c>>> = Resource::new(SAMPLE_PRIORITY, None);
+static SCREEN: Resource<Option<Screen>> = Resource::new(REFRESH_PRIORITY, None);
+static UART: Resource<Option<(Tx<USART1>, Rx<USART1>)>> = Resource::new(UART_PRIORITY, None);
+static BUTTONS: Resource<Option<Buttons>> = Resource::new(BUTTONS_PRIORITY, None);
+static TIMERS: Resource<Option<Timers<Job, 2>>> = Resource::new(TICK_PRIORITY, None);
+/// The inputs for the next refresh. Presses stay in until the refresh has
+/// seen them, however short they were.
+static INPUTS: Resource<Inputs> = Resource::new(
+    BUTTONS_PRIORITY,
+    Inputs {
+        buttons: ui::Buttons {
+            plus: false,
+            minus: false,
+        },
+        accel: None,
+        supply_mv: None,
+    },
+);
+
+/// I2C0 for the drivers, which locks the bus for each transfer.
+struct BusI2c;
+
+impl Write for BusI2c {
+    type Error = nb::Error<hal::i2c::Error>;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        I2C_BUS.lock(|i2c| i2c.as_mut().unwrap().write(address, bytes))
+    }
+}
+
+impl WriteRead for BusI2c {
+    type Error = nb::Error<hal::i2c::Error>;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        I2C_BUS.lock(|i2c| i2c.as_mut().unwrap().write_read(address, bytes, buffer))
+    }
+}
+
+#[riscv_rt::entry]
+fn main() -> ! {
+    let peripherals = gd32vf103_pac::Peripherals::take().unwrap();
+
+    // Use external 8MHz HXTAL and set PLL to get 96MHz system clock.
+    let mut rcu = peripherals
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(96.mhz())
+        .freeze();
+
+    let mut delay = McycleDelay::new(&rcu.clocks);
+
+    let mut afio = peripherals.AFIO.constrain(&mut rcu);
+
+    let pa = peripherals.GPIOA.split(&mut rcu);
+    let uart = hal::serial::Serial::new(
+        peripherals.USART1,
+        (
+            pa.pa2.into_alternate_push_pull(),
+            pa.pa3.into_pull_up_input(),
+        ),
+        hal::serial::Config {
+            baudrate: Bps(2_000_000),
+            ..Default::default()
+        },
+        &mut afio,
+        &mut rcu,
+    )
+    .split();
+    // The UART task runs for each byte received.
+    unsafe { (*USART1::ptr()).ctl0.modify(|_r, w| w.rbneie().set_bit()) };
+    UART.lock(|u| *u = Some(uart));
+
+    let adc = Adc::new(
+        peripherals.ADC0,
+        (
+            pa.pa0.into_analog(),
+            pa.pa1.into_analog(),
+            pa.pa4.into_analog(),
+        ),
+    );
+
+    let pb = peripherals.GPIOB.split(&mut rcu);
+    let buttons = Buttons::new(pb.pb0.into_pull_down_input(), pb.pb1.into_floating_input());
+    BUTTONS.lock(|b| *b = Some(buttons));
+
+    // OLED reset: Pull low to reset.
+    let mut oled_reset = pa
+        .pa9
+        .into_push_pull_output_with_state(hal::gpio::State::Low);
+
+    let i2c0 = hal::i2c::BlockingI2c::i2c0(
+        peripherals.I2C0,
+        (
+            pb.pb6.into_alternate_open_drain(),
+            pb.pb7.into_alternate_open_drain(),
+        ),
+        &mut afio,
+        hal::i2c::Mode::Fast {
+            frequency: 400_000.hz(),
+            duty_cycle: hal::i2c::DutyCycle::Ratio2to1,
+        },
+        &mut rcu,
+        1000,
+        10,
+        1000,
+        1000,
+    );
+    I2C_BUS.lock(|i2c| *i2c = Some(i2c0));
+
+    // OLED datasheet recommends 100 ms delay on power up, then 3 us after
+    // the reset to wait for init.
+    delay.delay_ms(100);
+    oled_reset.set_high().unwrap();
+    delay.delay_us(3);
+    let disp = {
+        let interface = I2CDIBuilder::new().init(BusI2c);
+        let mut disp: GraphicsMode<_, _> = Builder::new()
+            .size(DisplaySize96x16)
+            .with_rotation(DisplayRotation::Rotate180)
+            .connect(interface)
+            .into();
+        let _ = disp.init();
+        disp
+    };
+    // The accelerometer screen says so if there is none.
+    BMA.lock(|bma| *bma = Bma223::new(BusI2c).ok());
+
+    let anim = Animation::parse(include_bytes!("../../06-oled/src/frames.anim")).unwrap();
+    SCREEN.lock(|screen| {
+        *screen = Some(Screen {
+            disp,
+            brightness: None,
+            ui: OledDemo::new(anim, Language::default()),
+            adc,
+            last_ms: 0,
+        })
+    });
+
+    let mut timers = Timers::new(0);
+    timers.start_periodic(TICK_MS as u32, Job::Refresh).unwrap();
+    timers.start_periodic(SAMPLE_MS, Job::Sample).unwrap();
+    TIMERS.lock(|t| *t = Some(timers));
+
+    rtic::init();
+    rtic::bind(Irq::Timer, TICK_PRIORITY, tick);
+    rtic::bind(Irq::Exti0, BUTTONS_PRIORITY, button_edge);
+    rtic::bind(Irq::Exti1, BUTTONS_PRIORITY, button_edge);
+    rtic::bind(Irq::Usart1, UART_PRIORITY, uart_received);
+    mtimer::start(rcu.clocks.sysclk().0);
+    rtic::idle()
+}
+
+/// Hardware task of the machine timer: counts the milliseconds and spawns
+/// the jobs which are due.
+fn tick() {
+    mtimer::on_interrupt();
+    let now_ms = mtimer::now_ms();
+    TIMERS.lock(|timers| {
+        let timers = timers.as_mut().unwrap();
+        while let Some(job) = timers.poll(now_ms) {
+            // A task still waiting from last time only runs once.
+            let _ = match job {
+                Job::Refresh => REFRESH.spawn(),
+                Job::Sample => SAMPLE.spawn(),
+            };
+        }
+    });
+}
+
+/// Hardware task of EXTI lines 0 and 1: keeps the buttons which went down.
+fn button_edge() {
+    buttons::on_interrupt();
+    let held = BUTTONS.lock(|buttons| read_buttons(buttons.as_ref().unwrap()));
+    INPUTS.lock(|inputs| {
+        inputs.buttons.plus |= held.plus;
+        inputs.buttons.minus |= held.minus;
+    });
+}
+
+fn read_buttons(buttons: &Buttons) -> ui::Buttons {
+    ui::Buttons {
+        plus: buttons.is_pressed(Button::Plus),
+        minus: buttons.is_pressed(Button::Minus),
+    }
+}
+
+/// Hardware task of USART1: echoes what it receives, and takes '+' and '-'
+/// as presses of the buttons.
+fn uart_received() {
+    UART.lock(|uart| {
+        let (tx, rx) = uart.as_mut().unwrap();
+        while let Ok(byte) = rx.read() {
+            let _ = tx.write(byte);
+            match byte {
+                b'+' => INPUTS.lock(|inputs| inputs.buttons.plus = true),
+                b'-' => INPUTS.lock(|inputs| inputs.buttons.minus = true),
+                _ => {}
+            }
+        }
+    });
+}
+
+/// Software task: reads the BMA223.
+fn sample() {
+    let accel = BMA.lock(|bma| bma.as_mut().and_then(|bma| bma.read().ok()));
+    INPUTS.lock(|inputs| inputs.accel = accel);
+}
+
+/// Software task: updates the screens of demo 06 and redraws the display.
+fn refresh() {
+    let held = BUTTONS.lock(|buttons| read_buttons(buttons.as_ref().unwrap()));
+    let inputs = INPUTS.lock(|inputs| {
+        let taken = *inputs;
+        inputs.buttons = held;
+        taken
+    });
+    SCREEN.lock(|screen| {
+        let screen = screen.as_mut().unwrap();
+        let now_ms = mtimer::now_ms();
+        let elapsed_ms = now_ms.wrapping_sub(screen.last_ms).min(u16::MAX as u32) as u16;
+        screen.last_ms = now_ms;
+        let inputs = Inputs {
+            supply_mv: Some(screen.adc.read_vin_mv()),
+            ..inputs
+        };
+        if screen.ui.update(inputs, elapsed_ms) {
+            let _ = screen.ui.draw(&mut screen.disp);
+            let brightness = screen.ui.brightness();
+            if screen.brightness != Some(brightness) {
+                screen.brightness = Some(brightness);
+                let _ = screen
+                    .disp
+                    .set_brightness(Brightness::custom(0xF1, brightness));
+            }
+            let _ = screen.disp.flush();
+        }
+    });
+}
@@ -0,0 +1,134 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use panic_halt as _;
+
+use embedded_hal::{digital::v2::InputPin, serial::Write as _};
+use gd32vf103xx_hal::{self as hal, prelude::*};
+use hal::time::Bps;
+use pinecil_bsp::{
+    bma223::Bma223,
+    eclic,
+    power::{self, Wake},
+    rtc::Rtc,
+};
+
+/// How long to stay in deep-sleep, unless something else wakes the chip.
+const SLEEP_S: u32 = 5;
+/// How long to stay in standby.
+const STANDBY_S: u32 = 10;
+/// How hard the iron has to be moved to wake it, in steps of about 4 mg.
+const MOTION_THRESHOLD: u8 = 20;
+
+#[riscv_rt::entry]
+fn main() -> ! {
+    let peripherals = gd32vf103_pac::Peripherals::take().unwrap();
+
+    // Use external 8MHz HXTAL and set PLL to get 96MHz system clock.
+    let mut rcu = peripherals
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(96.mhz())
+        .freeze();
+
+    let mut afio = peripherals.AFIO.constrain(&mut rcu);
+
+    let pa = peripherals.GPIOA.split(&mut rcu);
+    let pa2_tx = pa.pa2.into_alternate_push_pull();
+    let pa3_rx = pa.pa3.into_pull_up_input();
+
+    let (mut uart1_tx, _uart1_rx) = hal::serial::Serial::new(
+        peripherals.USART1,
+        (pa2_tx, pa3_rx),
+        hal::serial::Config {
+            baudrate: Bps(2_000_000),
+            ..Default::default()
+        },
+        &mut afio,
+        &mut rcu,
+    )
+    .split();
+
+    if power::woke_from_standby() {
+        let _ = write!(uart1_tx, "\r\nWoke up from standby\r\n");
+    } else {
+        let _ = write!(uart1_tx, "\r\nStarted\r\n");
+    }
+
+    let pb = peripherals.GPIOB.split(&mut rcu);
+    // Use PB0 as input for the '+' button (butt_B).
+    let _btn_b = pb.pb0.into_pull_down_input();
+    // USE PB1 as input for the '-' button (butt_A), which is already pulled
+    // low externally.
+    let btn_a = pb.pb1.into_floating_input();
+
+    let i2c0 = hal::i2c::BlockingI2c::i2c0(
+        peripherals.I2C0,
+        (
+            pb.pb6.into_alternate_open_drain(),
+            pb.pb7.into_alternate_open_drain(),
+        ),
+        &mut afio,
+        hal::i2c::Mode::Fast {
+            frequency: 400_000.hz(),
+            duty_cycle: hal::i2c::DutyCycle::Ratio2to1,
+        },
+        &mut rcu,
+        1000,
+        10,
+        1000,
+        1000,
+    );
+    let mut wake = Wake::BUTTONS | Wake::RTC_ALARM;
+    match Bma223::new(i2c0).and_then(|mut bma| {
+        bma.enable_motion_interrupt(MOTION_THRESHOLD)?;
+        Ok(bma)
+    }) {
+        Ok(_bma) => wake = wake | Wake::MOTION,
+        Err(e) => {
+            let _ = write!(uart1_tx, "BMA223 not found: {:?}\r\n", e);
+        }
+    }
+
+    let mut rtc = Rtc::new(peripherals.RTC);
+
+    eclic::init();
+    power::enable_wake(wake);
+    unsafe { riscv::interrupt::enable() };
+
+    loop {
+        if btn_a.is_high().unwrap_or(false) {
+            let _ = write!(uart1_tx, "Standby for {} s\r\n", STANDBY_S);
+            let _ = nb::block!(uart1_tx.flush());
+            rtc.set_alarm_in(STANDBY_S);
+            power::standby();
+        }
+
+        let _ = write!(
+            uart1_tx,
+            "{} s: deep-sleep for {} s, or until a button is pressed or the iron is moved\r\n",
+            rtc.now_s(),
+            SLEEP_S,
+        );
+        let _ = nb::block!(uart1_tx.flush());
+        rtc.set_alarm_in(SLEEP_S);
+        power::deep_sleep();
+
+        let woken = power::take_woken();
+        let _ = write!(uart1_tx, "{} s: woken by", rtc.now_s());
+        for &(source, name) in &[
+            (Wake::BUTTONS, "button"),
+            (Wake::MOTION, "motion"),
+            (Wake::RTC_ALARM, "alarm"),
+        ] {
+            if woken.intersects(source) {
+                let _ = write!(uart1_tx, " {}", name);
+            }
+        }
+        let _ = write!(uart1_tx, "\r\n");
+        rtc.clear_alarm();
+    }
+}
@@ -0,0 +1,7 @@
+/* Keep the top of RAM free for the DFU request word set by the application
+   (`pinecil_core::image::DFU_REQUEST_ADDR`). */
+_stack_start = ORIGIN(RAM) + LENGTH(RAM) - 16;
+
+/* Must match `pinecil_core::image::BOOTLOADER_SIZE`. */
+ASSERT(LOADADDR(.data) + SIZEOF(.data) <= ORIGIN(FLASH) + 16K,
+  "ERROR(bootloader): the bootloader does not fit in the first 16K of flash");
@@ -0,0 +1,12 @@
+use std::env;
+
+fn main() {
+    // `bootloader.x` is passed in addition to the workspace `memory.x`. It has
+    // a unique name so that the linker finds it through the search path.
+    println!(
+        "cargo:rustc-link-search={}",
+        env::var("CARGO_MANIFEST_DIR").unwrap()
+    );
+    println!("cargo:rustc-link-arg-bins=-Tbootloader.x");
+    println!("cargo:rerun-if-changed=bootloader.x");
+}
@@ -0,0 +1,162 @@
+//! Flash programming through the FMC, exposed to the DFU class.
+
+use gd32vf103_pac::FMC;
+use pinecil_core::image::{self, APP_BASE, FLASH_BASE, FLASH_PAGE_SIZE, FLASH_SIZE, SETTINGS_ADDR};
+use usbd_dfu::{DFUManifestationError, DFUMemError, DFUMemIO};
+
+const FMC_UNLOCK_KEY0: u32 = 0x4567_0123;
+const FMC_UNLOCK_KEY1: u32 = 0xcdef_89ab;
+
+const TRANSFER_SIZE: usize = 1024;
+
+pub struct FlashMemory {
+    fmc: FMC,
+    buffer: [u8; TRANSFER_SIZE],
+    buffer_len: usize,
+}
+
+impl FlashMemory {
+    pub fn new(fmc: FMC) -> Self {
+        Self {
+            fmc,
+            buffer: [0; TRANSFER_SIZE],
+            buffer_len: 0,
+        }
+    }
+
+    fn unlock(&mut self) {
+        if self.fmc.ctl0.read().lk().bit_is_set() {
+            self.fmc.key0.write(|w| unsafe { w.bits(FMC_UNLOCK_KEY0) });
+            self.fmc.key0.write(|w| unsafe { w.bits(FMC_UNLOCK_KEY1) });
+        }
+    }
+
+    fn lock(&mut self) {
+        self.fmc.ctl0.modify(|_r, w| w.lk().set_bit());
+    }
+
+    /// Waits for the current operation and returns whether it succeeded.
+    fn wait_ready(&mut self) -> bool {
+        while self.fmc.stat0.read().busy().bit_is_set() {}
+        let stat = self.fmc.stat0.read();
+        let ok = !stat.pgerr().bit_is_set() && !stat.wperr().bit_is_set();
+        // Clear the flags by writing 1.
+        self.fmc
+            .stat0
+            .write(|w| w.endf().set_bit().pgerr().set_bit().wperr().set_bit());
+        ok
+    }
+
+    fn erase_page(&mut self, address: u32) -> Result<(), DFUMemError> {
+        self.unlock();
+        self.fmc.ctl0.modify(|_r, w| w.per().set_bit());
+        self.fmc.addr0.write(|w| unsafe { w.bits(address) });
+        self.fmc.ctl0.modify(|_r, w| w.start().set_bit());
+        let ok = self.wait_ready();
+        self.fmc.ctl0.modify(|_r, w| w.per().clear_bit());
+        self.lock();
+        if ok {
+            Ok(())
+        } else {
+            Err(DFUMemError::Erase)
+        }
+    }
+
+    fn program_words(&mut self, address: u32, data: &[u8]) -> Result<(), DFUMemError> {
+        self.unlock();
+        self.fmc.ctl0.modify(|_r, w| w.pg().set_bit());
+        let mut result = Ok(());
+        for (i, chunk) in data.chunks(4).enumerate() {
+            // Pad the last word with the erased value.
+            let mut word = [0xff; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            let p = (address + i as u32 * 4) as *mut u32;
+            unsafe { core::ptr::write_volatile(p, u32::from_le_bytes(word)) };
+            if !self.wait_ready() {
+                result = Err(DFUMemError::Prog);
+                break;
+            }
+        }
+        self.fmc.ctl0.modify(|_r, w| w.pg().clear_bit());
+        self.lock();
+        result
+    }
+}
+
+/// The settings page after the application is left alone, so that the
+/// settings survive updates.
+fn in_app_region(address: u32, length: u32) -> bool {
+    address >= APP_BASE
+        && address % 4 == 0
+        && address
+            .checked_add(length)
+            .map_or(false, |end| end <= SETTINGS_ADDR)
+}
+
+impl DFUMemIO for FlashMemory {
+    const INITIAL_ADDRESS_POINTER: u32 = APP_BASE;
+    // 111 pages of 1K, readable, erasable and writable.
+    const MEM_INFO_STRING: &'static str = "@Flash/0x08004000/111*001Kg";
+    const HAS_DOWNLOAD: bool = true;
+    const HAS_UPLOAD: bool = true;
+    const MANIFESTATION_TOLERANT: bool = false;
+    const PAGE_PROGRAM_TIME_MS: u32 = 8;
+    const PAGE_ERASE_TIME_MS: u32 = 50;
+    const FULL_ERASE_TIME_MS: u32 = 111 * 50;
+    const TRANSFER_SIZE: u16 = TRANSFER_SIZE as u16;
+
+    fn read_block(&mut self, address: u32, length: usize) -> Result<&[u8], DFUMemError> {
+        if address < FLASH_BASE || address.saturating_add(length as u32) > FLASH_BASE + FLASH_SIZE {
+            return Err(DFUMemError::Address);
+        }
+        Ok(unsafe { core::slice::from_raw_parts(address as *const u8, length) })
+    }
+
+    fn erase_block(&mut self, address: u32) -> Result<(), DFUMemError> {
+        if !in_app_region(address, FLASH_PAGE_SIZE) || address % FLASH_PAGE_SIZE != 0 {
+            return Err(DFUMemError::Address);
+        }
+        self.erase_page(address)
+    }
+
+    fn erase_all_blocks(&mut self) -> Result<(), DFUMemError> {
+        // Only the application region, never the bootloader itself or the
+        // settings.
+        for address in (APP_BASE..SETTINGS_ADDR).step_by(FLASH_PAGE_SIZE as usize) {
+            self.erase_page(address)?;
+        }
+        Ok(())
+    }
+
+    fn store_write_buffer(&mut self, src: &[u8]) -> Result<(), ()> {
+        if src.len() > self.buffer.len() {
+            return Err(());
+        }
+        self.buffer[..src.len()].copy_from_slice(src);
+        self.buffer_len = src.len();
+        Ok(())
+    }
+
+    fn program_block(&mut self, address: u32, length: usize) -> Result<(), DFUMemError> {
+        if length > self.buffer_len || !in_app_region(address, length as u32) {
+            return Err(DFUMemError::Address);
+        }
+        let buffer = self.buffer;
+        self.program_words(address, &buffer[..length])?;
+
+        let written = unsafe { core::slice::from_raw_parts(address as *const u8, length) };
+        if written != &buffer[..length] {
+            return Err(DFUMemError::Verify);
+        }
+        Ok(())
+    }
+
+    fn manifestation(&mut self) -> Result<(), DFUManifestationError> {
+        // Only leave DFU mode if the new image is complete. The bootloader
+        // checks it again after the reset and then starts it.
+        match unsafe { image::check_flash_image() } {
+            Ok(_) => unsafe { image::software_reset() },
+            Err(_) => Err(DFUManifestationError::Firmware),
+        }
+    }
+}
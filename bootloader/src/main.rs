@@ -0,0 +1,123 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use gd32vf103xx_hal::prelude::*;
+use pinecil_core::image::{self, DFU_PID, DFU_REQUEST_ADDR, DFU_REQUEST_MAGIC, DFU_VID};
+use riscv::register::mcycle;
+use synopsys_usb_otg::UsbBus;
+use usb_device::prelude::*;
+use usbd_dfu::DFUClass;
+
+mod flash;
+mod usb;
+
+static mut EP_MEMORY: [u32; 1024] = [0; 1024];
+
+#[riscv_rt::entry]
+fn main() -> ! {
+    let peripherals = gd32vf103_pac::Peripherals::take().unwrap();
+
+    let dfu_requested = take_dfu_request();
+    let button_held = read_plus_button(&peripherals);
+
+    if !dfu_requested && !button_held && unsafe { image::check_flash_image() }.is_ok() {
+        unsafe { jump_to_app() }
+    }
+
+    run_dfu(peripherals)
+}
+
+/// Reads and clears the DFU request word left by the application.
+fn take_dfu_request() -> bool {
+    unsafe {
+        let p = DFU_REQUEST_ADDR as *mut u32;
+        let requested = core::ptr::read_volatile(p) == DFU_REQUEST_MAGIC;
+        core::ptr::write_volatile(p, 0);
+        requested
+    }
+}
+
+const GPIO_MD_INPUT: u8 = 0b00;
+const GPIO_CTL_INPUT_PULLUP_PULLDOWN: u8 = 0b10;
+
+/// Checks whether the '+' button (PB0) is held.
+///
+/// We cannot use the '-' button here because it is wired to BOOT0, so holding
+/// it at power on starts the ROM bootloader instead of us.
+///
+/// GPIOB is put back into its reset state afterwards so that the application
+/// starts with the same peripheral state as without the bootloader.
+fn read_plus_button(peripherals: &gd32vf103_pac::Peripherals) -> bool {
+    peripherals.RCU.apb2en.modify(|_r, w| w.pben().set_bit());
+    // Set PB0 to input with pull-down.
+    peripherals.GPIOB.ctl0.modify(|_r, w| unsafe {
+        w.md0()
+            .bits(GPIO_MD_INPUT)
+            .ctl0()
+            .bits(GPIO_CTL_INPUT_PULLUP_PULLDOWN)
+    });
+    peripherals.GPIOB.octl.modify(|_r, w| w.octl0().clear_bit());
+
+    // Give the pull-down some time to settle.
+    let start = mcycle::read();
+    while mcycle::read().wrapping_sub(start) < 1000 {}
+    let held = peripherals.GPIOB.istat.read().istat0().bit_is_set();
+
+    peripherals.RCU.apb2rst.modify(|_r, w| w.pbrst().set_bit());
+    peripherals
+        .RCU
+        .apb2rst
+        .modify(|_r, w| w.pbrst().clear_bit());
+    peripherals.RCU.apb2en.modify(|_r, w| w.pben().clear_bit());
+    held
+}
+
+/// Jumps to the reset handler of the application.
+///
+/// Nothing apart from GPIOB has been touched at this point, and the
+/// application's `riscv-rt` startup code sets up its own stack pointer, trap
+/// vector and RAM.
+unsafe fn jump_to_app() -> ! {
+    let entry: extern "C" fn() -> ! = core::mem::transmute(image::APP_ENTRY as usize);
+    entry()
+}
+
+fn run_dfu(peripherals: gd32vf103_pac::Peripherals) -> ! {
+    // Use external 8MHz HXTAL and set PLL to get 96MHz system clock. The USB
+    // clock is derived from it by a /2 prescaler.
+    let mut rcu = peripherals
+        .RCU
+        .configure()
+        .ext_hf_clock(8.mhz())
+        .sysclk(96.mhz())
+        .freeze();
+    assert!(rcu.clocks.usbclk_valid());
+
+    let pa = peripherals.GPIOA.split(&mut rcu);
+    let usb = usb::UsbFs {
+        _pins: (
+            pa.pa11.into_alternate_push_pull(),
+            pa.pa12.into_alternate_push_pull(),
+        ),
+        hclk: rcu.clocks.hclk().0,
+    };
+    let usb_bus = UsbBus::new(usb, unsafe { &mut *core::ptr::addr_of_mut!(EP_MEMORY) });
+
+    let mut dfu = DFUClass::new(&usb_bus, flash::FlashMemory::new(peripherals.FMC));
+
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(DFU_VID, DFU_PID))
+        .manufacturer("Pinecil")
+        .product("Pinecil bootloader")
+        .serial_number("0")
+        .device_release(0x0200)
+        .self_powered(false)
+        .max_power(100)
+        .max_packet_size_0(64)
+        .build();
+
+    loop {
+        usb_dev.poll(&mut [&mut dfu]);
+    }
+}
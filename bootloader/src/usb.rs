@@ -0,0 +1,43 @@
+//! Glue between the GD32VF103 USBFS peripheral and `synopsys-usb-otg`.
+//!
+//! The USBFS peripheral is a Synopsys DWC2 OTG core, the same one found on the
+//! STM32F105/107.
+
+use gd32vf103_pac::{RCU, USBFS_GLOBAL};
+use gd32vf103xx_hal::gpio::{gpioa, Alternate, PushPull};
+use synopsys_usb_otg::UsbPeripheral;
+
+pub struct UsbFs {
+    pub _pins: (
+        gpioa::PA11<Alternate<PushPull>>,
+        gpioa::PA12<Alternate<PushPull>>,
+    ),
+    pub hclk: u32,
+}
+
+unsafe impl Sync for UsbFs {}
+
+unsafe impl UsbPeripheral for UsbFs {
+    const REGISTERS: *const () = USBFS_GLOBAL::ptr() as *const ();
+
+    const HIGH_SPEED: bool = false;
+    const FIFO_DEPTH_WORDS: usize = 320;
+    const ENDPOINT_COUNT: usize = 4;
+
+    fn enable() {
+        riscv::interrupt::free(|_| unsafe {
+            let rcu = &*RCU::ptr();
+
+            // Enable clock to USBFS.
+            rcu.ahben.modify(|_r, w| w.usbfsen().set_bit());
+
+            // Reset USBFS.
+            rcu.ahbrst.modify(|_r, w| w.usbfsrst().set_bit());
+            rcu.ahbrst.modify(|_r, w| w.usbfsrst().clear_bit());
+        });
+    }
+
+    fn ahb_frequency_hz(&self) -> u32 {
+        self.hclk
+    }
+}
@@ -1,7 +1,15 @@
+/* The last 1K page of the flash keeps the settings, see
+   `pinecil_core::image::SETTINGS_ADDR`.
+
+   The top of the RAM is left alone by the startup code, and by the
+   bootloader, so that it keeps what was in it across a reset: `.uninit`,
+   with the crash record of `pinecil_bsp::trap`, and the word at the very
+   top (`pinecil_core::image::DFU_REQUEST_ADDR`). */
 MEMORY
 {
-    FLASH (rx)      : ORIGIN = 0x08000000, LENGTH = 128k 
-    RAM (xrw)       : ORIGIN = 0x20000000, LENGTH = 32k
+    FLASH (rx)      : ORIGIN = 0x08000000, LENGTH = 127k
+    RAM (xrw)       : ORIGIN = 0x20000000, LENGTH = 32k - 64
+    UNINIT (rw)     : ORIGIN = 0x20000000 + 32k - 64, LENGTH = 60
 }
 
 REGION_ALIAS("REGION_TEXT", FLASH);
@@ -10,3 +18,11 @@ REGION_ALIAS("REGION_DATA", RAM);
 REGION_ALIAS("REGION_BSS", RAM);
 REGION_ALIAS("REGION_HEAP", RAM);
 REGION_ALIAS("REGION_STACK", RAM);
+
+SECTIONS
+{
+    .uninit (NOLOAD) : ALIGN(4)
+    {
+        *(.uninit .uninit.*);
+    } > UNINIT
+}
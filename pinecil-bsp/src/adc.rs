@@ -0,0 +1,131 @@
+//! Analog inputs on ADC0.
+//!
+//! - PA0 (ADC_IN0): input supply, through a voltage divider.
+//! - PA1 (ADC_IN1): tip thermocouple, after the op-amp.
+//! - PA4 (ADC_IN4): TMP36 handle temperature sensor, used as the cold
+//!   junction of the thermocouple.
+//!
+//! The thermocouple only reads correctly while the heater is off, so the tip
+//! should be sampled right after [`Heater::poll`](crate::heater::Heater::poll)
+//! starts a new period, which begins with the heater off.
+//!
+//! Conversions are started by software and waited for, which takes about
+//! 20 µs each with the long sample time used here.
+
+use gd32vf103_pac::{ADC0, RCU};
+use gd32vf103xx_hal::gpio::{
+    gpioa::{PA0, PA1, PA4},
+    Analog,
+};
+use riscv::register::mcycle;
+
+/// The ADC reference is the 3.3 V supply.
+pub const VREF_UV: u32 = 3_300_000;
+const FULL_SCALE: u32 = 1 << 12;
+
+/// Gain of the tip thermocouple op-amp, 1 + 750k / 2.37k, as in the IronOS
+/// sources for the Pinecil. Individual irons differ by a few percent, which
+/// calibration will have to take care of.
+pub const TIP_GAIN_X100: u32 = 100 + 750_000 * 100 / 2370;
+
+/// Ratio of the divider in front of PA0, times 100, which lets through up
+/// to 28 V. This is the nominal value; resistor tolerances make the reading
+/// off by up to a few percent.
+pub const VIN_DIVIDER_X100: u32 = 850;
+
+const CHANNEL_VIN: u8 = 0;
+const CHANNEL_TIP: u8 = 1;
+const CHANNEL_HANDLE: u8 = 4;
+
+/// 239.5 ADC clock cycles, the longest there is. The op-amp output and the
+/// TMP36 are both fairly high impedance.
+const SAMPLE_TIME_239_5: u8 = 0b111;
+/// Software trigger for the regular channels.
+const TRIGGER_SWRCST: u8 = 0b111;
+/// ADC clock is APB2 / 8, 12 MHz at the usual 96 MHz. It must not exceed
+/// 14 MHz.
+const RCU_ADCPSC_DIV8: u8 = 0b11;
+
+pub struct Adc {
+    adc: ADC0,
+    _pins: (PA0<Analog>, PA1<Analog>, PA4<Analog>),
+}
+
+impl Adc {
+    /// Sets up and calibrates ADC0. This assumes that APB2 runs at no more
+    /// than 112 MHz.
+    pub fn new(adc: ADC0, pins: (PA0<Analog>, PA1<Analog>, PA4<Analog>)) -> Self {
+        riscv::interrupt::free(|_| unsafe {
+            let rcu = &*RCU::ptr();
+            rcu.cfg0.modify(|_r, w| {
+                w.adcpsc_1_0().bits(RCU_ADCPSC_DIV8);
+                w.adcpsc_2().clear_bit()
+            });
+            rcu.apb2en.modify(|_r, w| w.adc0en().set_bit());
+            rcu.apb2rst.modify(|_r, w| w.adc0rst().set_bit());
+            rcu.apb2rst.modify(|_r, w| w.adc0rst().clear_bit());
+        });
+
+        adc.sampt1.modify(|_r, w| unsafe {
+            w.spt0().bits(SAMPLE_TIME_239_5);
+            w.spt1().bits(SAMPLE_TIME_239_5);
+            w.spt4().bits(SAMPLE_TIME_239_5)
+        });
+        // One regular channel per conversion, chosen in `read`.
+        adc.rsq0.modify(|_r, w| unsafe { w.rl().bits(0) });
+        adc.ctl1.modify(|_r, w| unsafe {
+            w.eterc().set_bit();
+            w.etsrc().bits(TRIGGER_SWRCST)
+        });
+
+        adc.ctl1.modify(|_r, w| w.adcon().set_bit());
+        // The ADC needs 14 ADC clock cycles to power up before calibration.
+        // The ADC clock is PCLK2 / 8, and the core runs at least as fast as
+        // PCLK2, so that is at most 14 * 8 core cycles.
+        let start = mcycle::read();
+        while mcycle::read().wrapping_sub(start) < 14 * 8 {}
+        adc.ctl1.modify(|_r, w| w.rstclb().set_bit());
+        while adc.ctl1.read().rstclb().bit_is_set() {}
+        adc.ctl1.modify(|_r, w| w.clb().set_bit());
+        while adc.ctl1.read().clb().bit_is_set() {}
+
+        Adc { adc, _pins: pins }
+    }
+
+    /// Converts one channel and returns the raw 12-bit value.
+    fn read(&mut self, channel: u8) -> u16 {
+        self.adc
+            .rsq2
+            .modify(|_r, w| unsafe { w.rsq0().bits(channel) });
+        self.adc.stat.modify(|_r, w| w.eoc().clear_bit());
+        self.adc.ctl1.modify(|_r, w| w.swrcst().set_bit());
+        while self.adc.stat.read().eoc().bit_is_clear() {}
+        self.adc.rdata.read().rdata().bits()
+    }
+
+    fn read_uv(&mut self, channel: u8) -> u32 {
+        (self.read(channel) as u64 * VREF_UV as u64 / FULL_SCALE as u64) as u32
+    }
+
+    /// Reads the thermocouple voltage in microvolts, i.e. the difference
+    /// between the tip and the cold junction. Only meaningful while the
+    /// heater is off.
+    pub fn read_tip_uv(&mut self) -> i32 {
+        (self.read_uv(CHANNEL_TIP) * 100 / TIP_GAIN_X100) as i32
+    }
+
+    /// Reads the input supply voltage in millivolts. The heater draws enough
+    /// current to pull the supply down a little, so this is best read while
+    /// it is off, like the tip.
+    pub fn read_vin_mv(&mut self) -> u32 {
+        // Average a few conversions, as the supply is noisy.
+        let sum: u32 = (0..4).map(|_| self.read_uv(CHANNEL_VIN)).sum();
+        sum / 4 / 1000 * VIN_DIVIDER_X100 / 100
+    }
+
+    /// Reads the output of the TMP36 in microvolts, see
+    /// `pinecil_core::thermocouple::tmp36_temperature`.
+    pub fn read_handle_uv(&mut self) -> i32 {
+        self.read_uv(CHANNEL_HANDLE) as i32
+    }
+}
@@ -0,0 +1,111 @@
+//! BMA223 accelerometer, on I2C0 at address 0x18.
+//!
+//! Only the 8-bit readings are used, at the default range of ±2 g, which
+//! gives 64 counts per g. The interrupt pins are wired to JTAG and are set
+//! to open drain so that they do not hold it down (see `notes/01-JTAG.md`).
+//! With JTAG turned off, they can tell the GD32VF103 that the iron moved,
+//! see `power`.
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use pinecil_core::ui::Accel;
+
+pub const ADDRESS: u8 = 0x18;
+
+const CHIP_ID: u8 = 0xf8;
+
+mod reg {
+    pub const BGW_CHIPID: u8 = 0x00;
+    pub const ACCD_X_LSB: u8 = 0x02;
+    pub const PMU_BW: u8 = 0x10;
+    pub const INT_EN_0: u8 = 0x16;
+    pub const INT_MAP_0: u8 = 0x19;
+    pub const INT_MAP_2: u8 = 0x1b;
+    pub const INT_OUT_CTRL: u8 = 0x20;
+    pub const INT_RST_LATCH: u8 = 0x21;
+    pub const INT_5: u8 = 0x27;
+    pub const INT_6: u8 = 0x28;
+}
+
+// PMU_BW: 62.5 Hz, enough for telling whether the iron is moving, and less
+// noisy than the default.
+const BW_62_5_HZ: u8 = 0x0b;
+// INT_OUT_CTRL: both interrupt pins open drain, active low.
+const INT_OPEN_DRAIN: u8 = 0b1010;
+// INT_EN_0: the slope interrupt on all three axes.
+const SLOPE_EN_XYZ: u8 = 0b111;
+// INT_MAP_0 and INT_MAP_2: the slope interrupt on INT1 and INT2.
+const INT_SLOPE: u8 = 1 << 2;
+// INT_RST_LATCH: the pins stay active for 250 ms.
+const LATCH_250_MS: u8 = 0b0001;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    I2c(E),
+    /// Something else answered at the address of the BMA223.
+    WrongDevice(u8),
+}
+
+pub struct Bma223<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C, E> Bma223<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    pub fn new(i2c: I2C) -> Result<Self, Error<E>> {
+        let mut bma = Bma223 { i2c };
+        // Free up JTAG first, whatever else answers at this address.
+        bma.write(reg::INT_OUT_CTRL, INT_OPEN_DRAIN)?;
+        let mut id = [0];
+        bma.i2c
+            .write_read(ADDRESS, &[reg::BGW_CHIPID], &mut id)
+            .map_err(Error::I2c)?;
+        if id[0] != CHIP_ID {
+            return Err(Error::WrongDevice(id[0]));
+        }
+        bma.write(reg::PMU_BW, BW_62_5_HZ)?;
+        Ok(bma)
+    }
+
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Sets the interrupt pins off, both of them, when the acceleration
+    /// changes by more than `threshold` between two samples, in steps of
+    /// about 4 mg. They stay active for 250 ms, so each movement is one
+    /// edge.
+    pub fn enable_motion_interrupt(&mut self, threshold: u8) -> Result<(), Error<E>> {
+        self.write(reg::INT_6, threshold)?;
+        // The slope has to be there for one sample only.
+        self.write(reg::INT_5, 0)?;
+        self.write(reg::INT_RST_LATCH, LATCH_250_MS)?;
+        self.write(reg::INT_MAP_0, INT_SLOPE)?;
+        self.write(reg::INT_MAP_2, INT_SLOPE)?;
+        self.write(reg::INT_EN_0, SLOPE_EN_XYZ)
+    }
+
+    pub fn disable_motion_interrupt(&mut self) -> Result<(), Error<E>> {
+        self.write(reg::INT_EN_0, 0)
+    }
+
+    /// Reads the latest acceleration.
+    pub fn read(&mut self) -> Result<Accel, Error<E>> {
+        // Each axis is an LSB and an MSB register, and the MSB alone is the
+        // 8-bit reading.
+        let mut data = [0; 6];
+        self.i2c
+            .write_read(ADDRESS, &[reg::ACCD_X_LSB], &mut data)
+            .map_err(Error::I2c)?;
+        Ok(Accel {
+            x: data[1] as i8,
+            y: data[3] as i8,
+            z: data[5] as i8,
+        })
+    }
+
+    fn write(&mut self, reg: u8, value: u8) -> Result<(), Error<E>> {
+        self.i2c.write(ADDRESS, &[reg, value]).map_err(Error::I2c)
+    }
+}
@@ -0,0 +1,144 @@
+//! The '+' and '-' buttons, for `async` tasks.
+//!
+//! - PB0: '+', pulled down inside the chip.
+//! - PB1: '-', pulled down on the board, as it is also BOOT0.
+//!
+//! Both read high while pressed. Each press is caught by EXTI lines 0 and 1,
+//! on both edges: a press is a rising edge after the button has been still
+//! for a while, so that the contacts bouncing on press or on release are not
+//! taken for more presses. The handler has to be set up by the firmware, and
+//! needs `mtimer` running for the timing:
+//!
+//! ```ignore
+//! eclic::set_handler(Irq::Exti0, buttons::on_interrupt);
+//! eclic::set_handler(Irq::Exti1, buttons::on_interrupt);
+//! eclic::enable(Irq::Exti0);
+//! eclic::enable(Irq::Exti1);
+//! ```
+
+use core::{
+    sync::atomic::{AtomicU32, Ordering},
+    task::Poll,
+};
+
+use embedded_hal::digital::v2::InputPin;
+use gd32vf103_pac::{AFIO, EXTI, GPIOB};
+use gd32vf103xx_hal::gpio::{
+    gpiob::{PB0, PB1},
+    Floating, Input, PullDown,
+};
+use pinecil_core::executor::poll_fn;
+
+use crate::{executor::WakerCell, mtimer};
+
+/// Edges this soon after another one are the contacts bouncing.
+const DEBOUNCE_MS: u32 = 20;
+
+/// EXTISS0 value with lines 0 and 1 on port B.
+const EXTISS_PB0_PB1: u32 = 0x11;
+const EXTISS_LINES_0_1_MASK: u32 = 0xff;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Plus,
+    Minus,
+}
+
+impl Button {
+    /// Also the EXTI line.
+    fn index(self) -> usize {
+        match self {
+            Button::Plus => 0,
+            Button::Minus => 1,
+        }
+    }
+}
+
+static WAKERS: [WakerCell; 2] = [WakerCell::new(), WakerCell::new()];
+/// Number of presses so far, which a waiting task compares against.
+static PRESSES: [AtomicU32; 2] = [AtomicU32::new(0), AtomicU32::new(0)];
+static LAST_EDGE_MS: [AtomicU32; 2] = [AtomicU32::new(0), AtomicU32::new(0)];
+
+pub struct Buttons {
+    plus: PB0<Input<PullDown>>,
+    minus: PB1<Input<Floating>>,
+}
+
+impl Buttons {
+    /// Routes PB0 and PB1 to their EXTI lines. The AFIO clock has to be on,
+    /// which `gd32vf103xx-hal` does when it sets up the AFIO.
+    pub fn new(plus: PB0<Input<PullDown>>, minus: PB1<Input<Floating>>) -> Self {
+        riscv::interrupt::free(|_| unsafe {
+            let afio = &*AFIO::ptr();
+            afio.extiss0
+                .modify(|r, w| w.bits(r.bits() & !EXTISS_LINES_0_1_MASK | EXTISS_PB0_PB1));
+            let exti = &*EXTI::ptr();
+            let lines = 0b11;
+            exti.rten.modify(|r, w| w.bits(r.bits() | lines));
+            exti.ften.modify(|r, w| w.bits(r.bits() | lines));
+            exti.pd.write(|w| w.bits(lines));
+            exti.inten.modify(|r, w| w.bits(r.bits() | lines));
+        });
+        Buttons { plus, minus }
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        match button {
+            Button::Plus => self.plus.is_high().unwrap(),
+            Button::Minus => self.minus.is_high().unwrap(),
+        }
+    }
+
+    /// Waits for the next press of `button`.
+    pub async fn wait_press(&self, button: Button) {
+        let i = button.index();
+        let presses = PRESSES[i].load(Ordering::Acquire);
+        poll_fn(|cx| {
+            WAKERS[i].register(cx.waker());
+            if PRESSES[i].load(Ordering::Acquire) != presses {
+                return Poll::Ready(());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Waits for the next press of either button, and returns which.
+    pub async fn wait_any(&self) -> Button {
+        let presses = [
+            PRESSES[0].load(Ordering::Acquire),
+            PRESSES[1].load(Ordering::Acquire),
+        ];
+        poll_fn(|cx| {
+            for button in [Button::Plus, Button::Minus] {
+                let i = button.index();
+                WAKERS[i].register(cx.waker());
+                if PRESSES[i].load(Ordering::Acquire) != presses[i] {
+                    return Poll::Ready(button);
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// The handler of EXTI lines 0 and 1. Counts the presses and wakes the
+/// tasks waiting for them.
+pub fn on_interrupt() {
+    let exti = unsafe { &*EXTI::ptr() };
+    let pending = exti.pd.read().bits() & 0b11;
+    exti.pd.write(|w| unsafe { w.bits(pending) });
+    let levels = unsafe { (*GPIOB::ptr()).istat.read().bits() };
+    let now_ms = mtimer::now_ms();
+    for i in 0..2 {
+        if pending & 1 << i == 0 {
+            continue;
+        }
+        let since_ms = now_ms.wrapping_sub(LAST_EDGE_MS[i].swap(now_ms, Ordering::Relaxed));
+        if levels & 1 << i != 0 && since_ms >= DEBOUNCE_MS {
+            PRESSES[i].fetch_add(1, Ordering::Release);
+            WAKERS[i].wake();
+        }
+    }
+}
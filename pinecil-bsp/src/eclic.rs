@@ -0,0 +1,347 @@
+//! The Nuclei ECLIC interrupt controller of the Bumblebee core, which the
+//! GD32VF103 has instead of a PLIC.
+//!
+//! Each interrupt source has its own enable and pending bit, a trigger, and a
+//! level and priority. An interrupt of a higher level preempts a lower one,
+//! while the priority only picks between pending interrupts of the same
+//! level. How the 4 control bits of the GD32VF103 are split between the two
+//! is set with [`set_level_bits`]. Interrupts at or below the threshold
+//! ([`set_threshold`]) are held back.
+//!
+//! [`init`] switches the core from the CLINT compatible mode `riscv-rt` leaves
+//! it in to the ECLIC mode. The interrupts are then taken non-vectored: they
+//! all go through the trap entry of `riscv-rt`, which saves the registers
+//! and calls `DefaultHandler`. That is defined here, and calls the handler
+//! set with [`set_handler`] with interrupts enabled again, so that a higher
+//! level can preempt it. A source without a handler is disabled when it
+//! fires, rather than firing again forever.
+//!
+//! A source can also be made vectored with [`set_vector`], to jump straight
+//! to its own entry point without going through `riscv-rt`. That entry point
+//! has to save and restore every register itself and return with `mret`, so
+//! it has to be written in assembly on stable Rust.
+//!
+//! In ECLIC mode the machine timer is an ECLIC source as well ([`Irq::Timer`])
+//! and `mie` no longer matters, so `mtimer::on_interrupt` is set as the
+//! handler of it instead of being called from `MachineTimer`.
+//!
+//! The `critical-section` crate is implemented by turning interrupts off
+//! altogether, which works the same in both modes. [`with_threshold`] only
+//! holds back interrupts up to a level instead.
+
+use core::arch::{asm, global_asm};
+
+use riscv::register::{mepc, mstatus};
+
+const BASE: usize = 0xd200_0000;
+const CLICCFG: *mut u8 = BASE as *mut u8;
+const MTH: *mut u8 = (BASE + 0xb) as *mut u8;
+/// The registers of source `n` start at `CLICINT + 4 * n`.
+const CLICINT: usize = BASE + 0x1000;
+const IP: usize = 0;
+const IE: usize = 1;
+const ATTR: usize = 2;
+const CTL: usize = 3;
+
+const ATTR_SHV: u8 = 1 << 0;
+const ATTR_TRIG_MASK: u8 = 0b11 << 1;
+const ATTR_TRIG_RISING: u8 = 0b01 << 1;
+const ATTR_TRIG_FALLING: u8 = 0b11 << 1;
+
+/// Number of sources, including the reserved ones.
+pub const SOURCES: usize = 87;
+/// Width of the level and priority field of each source.
+pub const CTL_BITS: u8 = 4;
+
+/// The interrupt sources the Pinecil firmware uses, with their ECLIC
+/// numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Irq {
+    /// The software interrupt of the core, `msip`.
+    Software = 3,
+    /// The machine timer of the core, see `mtimer`.
+    Timer = 7,
+    Rtc = 22,
+    Exti0 = 25,
+    Exti1 = 26,
+    Exti2 = 27,
+    Exti3 = 28,
+    Exti4 = 29,
+    Adc0_1 = 37,
+    Exti5_9 = 42,
+    Timer0Up = 44,
+    Timer1 = 47,
+    Timer2 = 48,
+    Timer3 = 49,
+    I2c0Event = 50,
+    I2c0Error = 51,
+    Usart0 = 56,
+    Usart1 = 57,
+    Exti10_15 = 59,
+    RtcAlarm = 60,
+    Timer4 = 69,
+    /// Not on the Pinecil, which leaves it free to be pended by software.
+    Spi2 = 70,
+    /// Not on the Pinecil, see [`Irq::Spi2`].
+    Uart3 = 71,
+    /// Not on the Pinecil, see [`Irq::Spi2`].
+    Uart4 = 72,
+    Timer5 = 73,
+    Timer6 = 74,
+}
+
+impl Irq {
+    fn reg(self, offset: usize) -> *mut u8 {
+        (CLICINT + 4 * self as usize + offset) as *mut u8
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// Pending for as long as the peripheral asks, which is what the
+    /// peripherals of the GD32VF103 need.
+    Level,
+    RisingEdge,
+    FallingEdge,
+}
+
+static mut HANDLERS: [Option<fn()>; SOURCES] = [None; SOURCES];
+
+/// The table for vectored sources, which `mtvt` points to. It has to be
+/// aligned to its size rounded up to a power of two.
+#[repr(align(512))]
+struct VectorTable([usize; SOURCES]);
+
+static mut VECTORS: VectorTable = VectorTable([0; SOURCES]);
+
+// In ECLIC mode, traps go to `mtvec` with the low 6 bits cleared, so the
+// entry has to be aligned to 64 bytes, which the one of `riscv-rt` is not.
+global_asm!(
+    ".section .text.eclic_trap, \"ax\"",
+    ".balign 64",
+    ".global _eclic_trap",
+    "_eclic_trap:",
+    "j _start_trap",
+);
+
+extern "C" {
+    fn _eclic_trap();
+}
+
+unsafe fn modify(reg: *mut u8, f: impl FnOnce(u8) -> u8) {
+    reg.write_volatile(f(reg.read_volatile()));
+}
+
+/// Switches to ECLIC mode, with all sources disabled, all 4 control bits
+/// used for the level, and the threshold at 0.
+pub fn init() {
+    riscv::interrupt::free(|_| unsafe {
+        for n in 0..SOURCES {
+            let reg = |offset| (CLICINT + 4 * n + offset) as *mut u8;
+            reg(IE).write_volatile(0);
+            reg(IP).write_volatile(0);
+            reg(ATTR).write_volatile(0);
+            reg(CTL).write_volatile(0);
+        }
+        MTH.write_volatile(0);
+        CLICCFG.write_volatile(CTL_BITS << 1);
+        let vectors = core::ptr::addr_of!(VECTORS) as usize;
+        // mtvt
+        asm!("csrw 0x307, {}", in(reg) vectors);
+        // Mode 0b11 is ECLIC mode, and non-vectored interrupts go to mtvec
+        // as well while bit 0 of mtvt2 is clear.
+        asm!("csrw 0x7ec, zero");
+        asm!("csrw mtvec, {}", in(reg) _eclic_trap as *const () as usize | 0b11);
+    });
+}
+
+/// Sets how many of the [`CTL_BITS`] are the level, the rest being the
+/// priority.
+pub fn set_level_bits(bits: u8) {
+    unsafe { CLICCFG.write_volatile(bits.min(CTL_BITS) << 1) };
+}
+
+fn level_bits() -> u8 {
+    unsafe { (CLICCFG.read_volatile() >> 1) & 0xf }.min(CTL_BITS)
+}
+
+/// The 8-bit level the core compares, for a level counting from 0: the
+/// unused low bits read as 1.
+fn encode_level(level: u8) -> u8 {
+    let bits = level_bits();
+    if bits == 0 {
+        return 0xff;
+    }
+    let level = level.min((1 << bits) - 1);
+    ((level as u16) << (8 - bits)) as u8 | (0xff >> bits)
+}
+
+/// Sets the level, from 0 up to `2^level_bits - 1`. Higher levels preempt
+/// lower ones.
+pub fn set_level(irq: Irq, level: u8) {
+    let bits = level_bits();
+    let shift = 8 - bits;
+    let mask = !(0xffu16 << shift) as u8;
+    let level = encode_level(level) & !mask;
+    unsafe { modify(irq.reg(CTL), |ctl| (ctl & mask) | level) };
+}
+
+/// Sets the priority within the level, from 0 up to
+/// `2^(CTL_BITS - level_bits) - 1`.
+pub fn set_priority(irq: Irq, priority: u8) {
+    let bits = CTL_BITS - level_bits();
+    if bits == 0 {
+        return;
+    }
+    let shift = 8 - CTL_BITS;
+    let mask = ((1u8 << bits) - 1) << shift;
+    unsafe {
+        modify(irq.reg(CTL), |ctl| {
+            (ctl & !mask) | ((priority << shift) & mask)
+        })
+    };
+}
+
+pub fn set_trigger(irq: Irq, trigger: Trigger) {
+    let trig = match trigger {
+        Trigger::Level => 0,
+        Trigger::RisingEdge => ATTR_TRIG_RISING,
+        Trigger::FallingEdge => ATTR_TRIG_FALLING,
+    };
+    unsafe { modify(irq.reg(ATTR), |attr| (attr & !ATTR_TRIG_MASK) | trig) };
+}
+
+/// Sets the function called for `irq` in non-vectored mode.
+pub fn set_handler(irq: Irq, handler: fn()) {
+    riscv::interrupt::free(|_| unsafe { HANDLERS[irq as usize] = Some(handler) });
+}
+
+/// Makes `irq` vectored, jumping straight to `entry`.
+///
+/// # Safety
+///
+/// `entry` is jumped to in the middle of whatever was running, so it has to
+/// save every register it touches and return with `mret`.
+pub unsafe fn set_vector(irq: Irq, entry: unsafe extern "C" fn()) {
+    riscv::interrupt::free(|_| {
+        VECTORS.0[irq as usize] = entry as usize;
+        modify(irq.reg(ATTR), |attr| attr | ATTR_SHV);
+    });
+}
+
+/// Makes `irq` non-vectored again, going to its handler.
+pub fn clear_vector(irq: Irq) {
+    unsafe { modify(irq.reg(ATTR), |attr| attr & !ATTR_SHV) };
+}
+
+pub fn enable(irq: Irq) {
+    unsafe { irq.reg(IE).write_volatile(1) };
+}
+
+pub fn disable(irq: Irq) {
+    unsafe { irq.reg(IE).write_volatile(0) };
+}
+
+pub fn is_enabled(irq: Irq) -> bool {
+    unsafe { irq.reg(IE).read_volatile() & 1 != 0 }
+}
+
+pub fn is_pending(irq: Irq) -> bool {
+    unsafe { irq.reg(IP).read_volatile() & 1 != 0 }
+}
+
+/// Makes an edge-triggered source pending from software. Level-triggered
+/// sources follow their peripheral instead.
+pub fn pend(irq: Irq) {
+    unsafe { irq.reg(IP).write_volatile(1) };
+}
+
+pub fn unpend(irq: Irq) {
+    unsafe { irq.reg(IP).write_volatile(0) };
+}
+
+/// Holds back interrupts at `level` and below. 0 lets everything through
+/// but the lowest level.
+pub fn set_threshold(level: u8) {
+    unsafe { MTH.write_volatile(encode_level(level)) };
+}
+
+/// The level of the interrupt being handled, counting from 0 as in
+/// [`set_level`], or 0 outside of interrupts.
+pub fn current_level() -> u8 {
+    let mintstatus: usize;
+    // mintstatus, with the 8-bit level of the current interrupt in the top
+    // byte.
+    unsafe { asm!("csrr {}, 0x346", out(reg) mintstatus) };
+    let level = (mintstatus >> 24) as u8;
+    match level_bits() {
+        0 => 0,
+        bits => level >> (8 - bits),
+    }
+}
+
+/// Runs `f` with interrupts at `level` and below held back, and those above
+/// still let through. A higher threshold already in place is kept.
+pub fn with_threshold<R>(level: u8, f: impl FnOnce() -> R) -> R {
+    let previous = unsafe { MTH.read_volatile() };
+    let threshold = encode_level(level).max(previous);
+    unsafe { MTH.write_volatile(threshold) };
+    let result = f();
+    unsafe { MTH.write_volatile(previous) };
+    result
+}
+
+/// The non-vectored interrupts, called by `riscv-rt` for every interrupt
+/// code it has no handler of its own for. In ECLIC mode that is all of
+/// them, as `mcause` has more than the code in it.
+#[export_name = "DefaultHandler"]
+fn dispatch() {
+    let mcause: usize;
+    unsafe { asm!("csrr {}, mcause", out(reg) mcause) };
+    let n = mcause & 0xfff;
+    if n >= SOURCES {
+        return;
+    }
+    let reg = |offset| (CLICINT + 4 * n + offset) as *mut u8;
+    unsafe {
+        // Edge-triggered sources are only cleared by hardware when they are
+        // vectored.
+        if reg(ATTR).read_volatile() & ATTR_TRIG_MASK != 0 {
+            reg(IP).write_volatile(0);
+        }
+        let handler = match HANDLERS[n] {
+            Some(handler) => handler,
+            None => {
+                reg(IE).write_volatile(0);
+                return;
+            }
+        };
+
+        // A nested interrupt overwrites these, and `mcause` holds the level
+        // and interrupt enable to go back to on `mret`.
+        let epc = mepc::read();
+        mstatus::set_mie();
+        handler();
+        mstatus::clear_mie();
+        mepc::write(epc);
+        asm!("csrw mcause, {}", in(reg) mcause);
+    }
+}
+
+struct CriticalSection;
+critical_section::set_impl!(CriticalSection);
+
+unsafe impl critical_section::Impl for CriticalSection {
+    unsafe fn acquire() -> critical_section::RawRestoreState {
+        let enabled = mstatus::read().mie();
+        mstatus::clear_mie();
+        enabled
+    }
+
+    unsafe fn release(enabled: critical_section::RawRestoreState) {
+        if enabled {
+            mstatus::set_mie();
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Running the tasks of `pinecil_core::executor` on the board, and waking
+//! them from interrupts.
+//!
+//! The drivers with `async` functions each keep a [`WakerCell`] per thing to
+//! wait for. A future which has to wait enables the interrupt it waits for
+//! and stores its waker, and the interrupt handler disables the interrupt
+//! again and wakes it. The interrupt handlers have to be set up with
+//! `eclic` by the firmware, as each driver documents.
+
+use core::cell::RefCell;
+use core::task::Waker;
+
+use critical_section::Mutex;
+use pinecil_core::executor::{Executor, Task};
+
+/// The waker of the task waiting for something, shared with an interrupt
+/// handler.
+pub struct WakerCell {
+    waker: Mutex<RefCell<Option<Waker>>>,
+}
+
+impl WakerCell {
+    pub const fn new() -> Self {
+        WakerCell {
+            waker: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Stores the waker of the task to wake, replacing the previous one.
+    pub fn register(&self, waker: &Waker) {
+        critical_section::with(|cs| {
+            let mut cell = self.waker.borrow_ref_mut(cs);
+            match &*cell {
+                Some(stored) if stored.will_wake(waker) => {}
+                _ => *cell = Some(waker.clone()),
+            }
+        });
+    }
+
+    /// Wakes the task which registered last, if any.
+    pub fn wake(&self) {
+        if let Some(waker) = critical_section::with(|cs| self.waker.borrow_ref_mut(cs).take()) {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for WakerCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs the tasks until they have all finished, sleeping in `wfi` whenever
+/// none of them has been woken.
+pub fn run<const N: usize>(executor: &'static Executor<N>, tasks: &mut [Option<Task<'_>>]) {
+    while executor.poll(tasks) {
+        // With interrupts off, an interrupt which comes in after the check
+        // still ends the `wfi`, and is only taken after it.
+        riscv::interrupt::free(|_| {
+            if !executor.is_woken() {
+                unsafe { riscv::asm::wfi() };
+            }
+        });
+    }
+}
@@ -0,0 +1,174 @@
+//! The settings page at the end of the flash, programmed through the FMC.
+//!
+//! The layout of the settings is in `pinecil_core::settings`, and the records
+//! of the usage counters follow them, see `pinecil_core::counters`. Erasing
+//! the page stalls the CPU for a few milliseconds, as the code runs from the
+//! same flash, and wears it out, so it is only erased when the settings have
+//! changed or there is no room left for another record of the counters.
+//! Either way, the settings and the newest counters are written back.
+
+use gd32vf103_pac::FMC;
+use pinecil_core::{
+    counters::{self, Counters},
+    image::SETTINGS_ADDR,
+    settings::{self, Settings},
+};
+
+const FMC_UNLOCK_KEY0: u32 = 0x4567_0123;
+const FMC_UNLOCK_KEY1: u32 = 0xcdef_89ab;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    Erase,
+    Program,
+    /// The page does not read back as written.
+    Verify,
+}
+
+pub struct SettingsFlash {
+    fmc: FMC,
+}
+
+impl SettingsFlash {
+    pub fn new(fmc: FMC) -> Self {
+        SettingsFlash { fmc }
+    }
+
+    pub fn release(self) -> FMC {
+        self.fmc
+    }
+
+    fn stored_settings(&self) -> &[u8; settings::LEN] {
+        unsafe { &*(SETTINGS_ADDR as *const [u8; settings::LEN]) }
+    }
+
+    fn stored_records(&self) -> &[u8; counters::SLOTS * counters::LEN] {
+        let addr = SETTINGS_ADDR + counters::OFFSET as u32;
+        unsafe { &*(addr as *const [u8; counters::SLOTS * counters::LEN]) }
+    }
+
+    /// Reads the stored settings.
+    pub fn load(&self) -> Result<Settings, settings::Error> {
+        Settings::parse(self.stored_settings())
+    }
+
+    /// Reads the newest stored counters, `None` if none were stored yet.
+    pub fn load_counters(&self) -> Option<Counters> {
+        counters::newest(self.stored_records())
+    }
+
+    /// Writes `settings` over the stored ones, unless they are the same.
+    pub fn store(&mut self, settings: &Settings) -> Result<(), Error> {
+        let bytes = settings.to_bytes();
+        if *self.stored_settings() == bytes {
+            return Ok(());
+        }
+        // Left erased if there are none.
+        let counters = self
+            .load_counters()
+            .map_or([0xff; counters::LEN], |c| c.to_bytes());
+        self.write_page(&bytes, &counters)
+    }
+
+    /// Adds a record of `counters` after the stored ones, unless they are the
+    /// same. The page is only erased once it is full.
+    pub fn store_counters(&mut self, counters: &Counters) -> Result<(), Error> {
+        if self.load_counters() == Some(*counters) {
+            return Ok(());
+        }
+        let bytes = counters.to_bytes();
+        let slot = match counters::next_slot(self.stored_records()) {
+            Some(slot) => slot,
+            None => {
+                let settings = *self.stored_settings();
+                return self.write_page(&settings, &bytes);
+            }
+        };
+        let offset = counters::OFFSET + slot * counters::LEN;
+        self.unlock();
+        let result = self.program(offset, &bytes);
+        self.lock();
+        result?;
+        if self.stored_records()[slot * counters::LEN..][..counters::LEN] != bytes[..] {
+            return Err(Error::Verify);
+        }
+        Ok(())
+    }
+
+    fn write_page(
+        &mut self,
+        settings: &[u8; settings::LEN],
+        counters: &[u8; counters::LEN],
+    ) -> Result<(), Error> {
+        self.unlock();
+        let result = self
+            .erase()
+            .and_then(|()| self.program(0, settings))
+            .and_then(|()| self.program(counters::OFFSET, counters));
+        self.lock();
+        result?;
+        if self.stored_settings() != settings
+            || self.stored_records()[..counters::LEN] != counters[..]
+        {
+            return Err(Error::Verify);
+        }
+        Ok(())
+    }
+
+    fn unlock(&mut self) {
+        if self.fmc.ctl0.read().lk().bit_is_set() {
+            self.fmc.key0.write(|w| unsafe { w.bits(FMC_UNLOCK_KEY0) });
+            self.fmc.key0.write(|w| unsafe { w.bits(FMC_UNLOCK_KEY1) });
+        }
+    }
+
+    fn lock(&mut self) {
+        self.fmc.ctl0.modify(|_r, w| w.lk().set_bit());
+    }
+
+    /// Waits for the current operation and returns whether it succeeded.
+    fn wait_ready(&mut self) -> bool {
+        while self.fmc.stat0.read().busy().bit_is_set() {}
+        let stat = self.fmc.stat0.read();
+        let ok = !stat.pgerr().bit_is_set() && !stat.wperr().bit_is_set();
+        // Clear the flags by writing 1.
+        self.fmc
+            .stat0
+            .write(|w| w.endf().set_bit().pgerr().set_bit().wperr().set_bit());
+        ok
+    }
+
+    fn erase(&mut self) -> Result<(), Error> {
+        self.fmc.ctl0.modify(|_r, w| w.per().set_bit());
+        self.fmc.addr0.write(|w| unsafe { w.bits(SETTINGS_ADDR) });
+        self.fmc.ctl0.modify(|_r, w| w.start().set_bit());
+        let ok = self.wait_ready();
+        self.fmc.ctl0.modify(|_r, w| w.per().clear_bit());
+        if ok {
+            Ok(())
+        } else {
+            Err(Error::Erase)
+        }
+    }
+
+    /// Programs `bytes` at `offset` in the page. Words which are still
+    /// erased, as they were when nothing was stored yet, are skipped.
+    fn program(&mut self, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+        self.fmc.ctl0.modify(|_r, w| w.pg().set_bit());
+        let mut result = Ok(());
+        for (i, word) in bytes.chunks_exact(4).enumerate() {
+            let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+            if word == u32::MAX {
+                continue;
+            }
+            let p = (SETTINGS_ADDR + (offset + i * 4) as u32) as *mut u32;
+            unsafe { core::ptr::write_volatile(p, word) };
+            if !self.wait_ready() {
+                result = Err(Error::Program);
+                break;
+            }
+        }
+        self.fmc.ctl0.modify(|_r, w| w.pg().clear_bit());
+        result
+    }
+}
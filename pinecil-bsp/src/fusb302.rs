@@ -0,0 +1,215 @@
+//! FUSB302B USB PD controller, on I2C0 at address 0x22.
+//!
+//! The FUSB302B does the physical layer of USB PD: it encodes and decodes
+//! the messages on the CC line, adds and checks the CRC, and answers each
+//! message with a GoodCRC by itself (and retries its own messages until it
+//! gets one). The protocol is left to `pinecil_core::pd::sink`.
+//!
+//! Its interrupt pin is not used, so [`Fusb302::poll`] has to be called
+//! regularly, at least every 10 ms or so while a negotiation is going on.
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use pinecil_core::pd::message::{Message, MAX_LEN};
+
+pub const ADDRESS: u8 = 0x22;
+
+mod reg {
+    pub const DEVICE_ID: u8 = 0x01;
+    pub const SWITCHES0: u8 = 0x02;
+    pub const SWITCHES1: u8 = 0x03;
+    pub const CONTROL0: u8 = 0x06;
+    pub const CONTROL1: u8 = 0x07;
+    pub const CONTROL3: u8 = 0x09;
+    pub const POWER: u8 = 0x0b;
+    pub const RESET: u8 = 0x0c;
+    pub const INTERRUPTA: u8 = 0x3e;
+    pub const STATUS0: u8 = 0x40;
+    pub const STATUS1: u8 = 0x41;
+    pub const FIFOS: u8 = 0x43;
+}
+
+// SWITCHES0
+const PDWN1: u8 = 1 << 0;
+const PDWN2: u8 = 1 << 1;
+const MEAS_CC1: u8 = 1 << 2;
+const MEAS_CC2: u8 = 1 << 3;
+// SWITCHES1
+const TXCC1: u8 = 1 << 0;
+const TXCC2: u8 = 1 << 1;
+const AUTO_CRC: u8 = 1 << 2;
+const SPECREV_30: u8 = 0b10 << 5;
+// CONTROL0
+const TX_FLUSH: u8 = 1 << 6;
+// CONTROL1
+const RX_FLUSH: u8 = 1 << 2;
+// CONTROL3
+const AUTO_RETRY: u8 = 1 << 0;
+const N_RETRIES_3: u8 = 3 << 1;
+const SEND_HARD_RESET: u8 = 1 << 6;
+// POWER
+const POWER_ALL: u8 = 0x0f;
+// RESET
+const SW_RES: u8 = 1 << 0;
+const PD_RESET: u8 = 1 << 1;
+// INTERRUPTA
+const I_HARDRST: u8 = 1 << 0;
+// STATUS0
+const BC_LVL: u8 = 0b11;
+const VBUSOK: u8 = 1 << 7;
+// STATUS1
+const RX_EMPTY: u8 = 1 << 5;
+
+// FIFO tokens.
+const TX_SOP1: u8 = 0x12;
+const TX_SOP2: u8 = 0x13;
+const TX_PACKSYM: u8 = 0x80;
+const TX_JAM_CRC: u8 = 0xff;
+const TX_EOP: u8 = 0x14;
+const TX_TXOFF: u8 = 0xfe;
+const TX_TXON: u8 = 0xa1;
+const RX_TOKEN_MASK: u8 = 0xe0;
+const RX_SOP: u8 = 0xe0;
+const CRC_LEN: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    I2c(E),
+    /// Something else answered at the address of the FUSB302B.
+    WrongDevice(u8),
+}
+
+/// What [`Fusb302::poll`] found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A message from the source.
+    Received(Message),
+    /// The source signalled a hard reset.
+    HardReset,
+}
+
+pub struct Fusb302<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C, E> Fusb302<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Resets the FUSB302B and powers it up, without attaching yet.
+    pub fn new(i2c: I2C) -> Result<Self, Error<E>> {
+        let mut fusb = Fusb302 { i2c };
+        fusb.write(reg::RESET, SW_RES)?;
+        let id = fusb.read(reg::DEVICE_ID)?;
+        // Version 0b1000 and up is the FUSB302B.
+        if id >> 4 < 0b1000 {
+            return Err(Error::WrongDevice(id));
+        }
+        fusb.write(reg::POWER, POWER_ALL)?;
+        fusb.write(reg::CONTROL3, AUTO_RETRY | N_RETRIES_3)?;
+        Ok(fusb)
+    }
+
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Whether a source is supplying VBUS.
+    pub fn vbus_ok(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read(reg::STATUS0)? & VBUSOK != 0)
+    }
+
+    /// Finds out which CC pin the source is on and starts listening there.
+    /// Returns `false` if neither has a source on it.
+    pub fn attach(&mut self) -> Result<bool, Error<E>> {
+        let mut levels = [0; 2];
+        for (level, &meas) in levels.iter_mut().zip(&[MEAS_CC1, MEAS_CC2]) {
+            self.write(reg::SWITCHES0, PDWN1 | PDWN2 | meas)?;
+            // The measurement takes a few hundred microseconds to settle,
+            // which is about as long as a few reads over I2C.
+            for _ in 0..4 {
+                *level = self.read(reg::STATUS0)? & BC_LVL;
+            }
+        }
+        let (meas, txcc) = match levels {
+            [0, 0] => return Ok(false),
+            [cc1, cc2] if cc1 >= cc2 => (MEAS_CC1, TXCC1),
+            _ => (MEAS_CC2, TXCC2),
+        };
+        self.write(reg::SWITCHES0, PDWN1 | PDWN2 | meas)?;
+        // Sink, UFP, with GoodCRC sent by the FUSB302B.
+        self.write(reg::SWITCHES1, SPECREV_30 | AUTO_CRC | txcc)?;
+        self.write(reg::RESET, PD_RESET)?;
+        self.write(reg::CONTROL0, TX_FLUSH)?;
+        self.write(reg::CONTROL1, RX_FLUSH)?;
+        Ok(true)
+    }
+
+    /// Checks for a hard reset and for received messages.
+    pub fn poll(&mut self) -> Result<Option<Event>, Error<E>> {
+        if self.read(reg::INTERRUPTA)? & I_HARDRST != 0 {
+            self.write(reg::RESET, PD_RESET)?;
+            return Ok(Some(Event::HardReset));
+        }
+        while self.read(reg::STATUS1)? & RX_EMPTY == 0 {
+            let token = self.read(reg::FIFOS)?;
+            let mut header = [0; 2];
+            self.read_into(reg::FIFOS, &mut header)?;
+            let count = (u16::from_le_bytes(header) >> 12 & 7) as usize;
+            let mut buf = [0; MAX_LEN + CRC_LEN];
+            buf[..2].copy_from_slice(&header);
+            let len = 2 + 4 * count;
+            self.read_into(reg::FIFOS, &mut buf[2..len + CRC_LEN])?;
+            // Only SOP messages are for us, not those for the cable.
+            if token & RX_TOKEN_MASK != RX_SOP {
+                continue;
+            }
+            if let Ok(message) = Message::parse(&buf[..len]) {
+                return Ok(Some(Event::Received(message)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Sends a message. The FUSB302B retries until the source answers with
+    /// a GoodCRC.
+    pub fn send(&mut self, message: &Message) -> Result<(), Error<E>> {
+        let mut data = [0; MAX_LEN];
+        let data = message.encode(&mut data);
+        let mut buf = [0; 1 + 5 + MAX_LEN + 4];
+        buf[0] = reg::FIFOS;
+        buf[1..6].copy_from_slice(&[
+            TX_SOP1,
+            TX_SOP1,
+            TX_SOP1,
+            TX_SOP2,
+            TX_PACKSYM | data.len() as u8,
+        ]);
+        buf[6..6 + data.len()].copy_from_slice(data);
+        let end = 6 + data.len();
+        buf[end..end + 4].copy_from_slice(&[TX_JAM_CRC, TX_EOP, TX_TXOFF, TX_TXON]);
+        self.i2c.write(ADDRESS, &buf[..end + 4]).map_err(Error::I2c)
+    }
+
+    /// Signals a hard reset to the source.
+    pub fn hard_reset(&mut self) -> Result<(), Error<E>> {
+        let control3 = self.read(reg::CONTROL3)?;
+        self.write(reg::CONTROL3, control3 | SEND_HARD_RESET)?;
+        self.write(reg::RESET, PD_RESET)
+    }
+
+    fn read(&mut self, reg: u8) -> Result<u8, Error<E>> {
+        let mut value = [0];
+        self.read_into(reg, &mut value)?;
+        Ok(value[0])
+    }
+
+    fn read_into(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Error<E>> {
+        self.i2c
+            .write_read(ADDRESS, &[reg], buf)
+            .map_err(Error::I2c)
+    }
+
+    fn write(&mut self, reg: u8, value: u8) -> Result<(), Error<E>> {
+        self.i2c.write(ADDRESS, &[reg, value]).map_err(Error::I2c)
+    }
+}
@@ -0,0 +1,201 @@
+//! Tip heater output.
+//!
+//! The gate of the heater MOSFET is driven from PA6, which is channel 0 of
+//! TIMER2. The tip is powered for `duty` out of every PWM period of
+//! [`PERIOD_MS`], at the end of the period.
+//!
+//! A stuck-on heater will melt things, so there are several layers keeping
+//! it off unless the firmware is alive and asking for heat:
+//!
+//! - The duty is capped at [`MAX_DUTY`], so the tip is unpowered for part of
+//!   every period. That is also when the thermocouple can be read.
+//! - The timer runs in single pulse mode: it stops by itself, with the output
+//!   off, after one period. [`Heater::poll`] starts the next one, so if the
+//!   firmware hangs the heater goes off within one period.
+//! - [`Heater::set_duty`] only grants heat for [`LEASE_PERIODS`] periods. If
+//!   the control loop stops updating the duty while the rest of the firmware
+//!   keeps polling, the software watchdog turns the heater off.
+//! - [`force_off`] drives the pin low without going through the timer, for
+//!   the panic and exception handlers and for `pre_init` right after reset.
+
+use gd32vf103_pac::{GPIOA, RCU, TIMER2};
+use gd32vf103xx_hal::{gpio::gpioa::PA6, time::Hertz};
+
+/// Length of one PWM period.
+pub const PERIOD_MS: u32 = 100;
+/// Duty cycles are given in per mille.
+pub const FULL_DUTY: u16 = 1000;
+/// Hard limit on the duty cycle, leaving the tip unpowered for at least
+/// 10 ms of every period.
+pub const MAX_DUTY: u16 = 900;
+/// Number of periods the heater keeps going after the last `set_duty`.
+pub const LEASE_PERIODS: u8 = 3;
+
+/// The timer counts at 100 kHz, so that the prescaler fits in 16 bits for
+/// any timer clock the GD32VF103 can run at.
+const COUNTER_HZ: u32 = 100_000;
+const PERIOD_TICKS: u32 = COUNTER_HZ / 1000 * PERIOD_MS;
+
+const GPIO_MD_OUTPUT_2MHZ: u8 = 0b10;
+const GPIO_CTL_OUTPUT_PUSH_PULL: u8 = 0b00;
+const GPIO_CTL_AFIO_PUSH_PULL: u8 = 0b10;
+const TIMER_CHCTL_PWM0: u8 = 0b110;
+
+/// Turns the heater off by driving PA6 low as a plain GPIO and stopping the
+/// timer.
+///
+/// # Safety
+///
+/// This takes over PA6 and TIMER2 behind the back of whoever owns them. It is
+/// meant for the panic and exception handlers and for `pre_init`, where
+/// nothing else runs.
+pub unsafe fn force_off() {
+    let rcu = &*RCU::ptr();
+    let gpioa = &*GPIOA::ptr();
+    let timer = &*TIMER2::ptr();
+
+    // GPIOA might not be clocked yet right after reset.
+    rcu.apb2en.modify(|_r, w| w.paen().set_bit());
+    gpioa.octl.modify(|_r, w| w.octl6().clear_bit());
+    gpioa.ctl0.modify(|_r, w| {
+        w.md6()
+            .bits(GPIO_MD_OUTPUT_2MHZ)
+            .ctl6()
+            .bits(GPIO_CTL_OUTPUT_PUSH_PULL)
+    });
+
+    timer.ctl0.modify(|_r, w| w.cen().clear_bit());
+    timer.ch0cv.write(|w| w.ch0val().bits(0));
+}
+
+pub struct Heater<MODE> {
+    timer: TIMER2,
+    _pin: PA6<MODE>,
+    duty: u16,
+    lease: u8,
+}
+
+impl<MODE> Heater<MODE> {
+    /// Sets up TIMER2 for the heater, which starts off.
+    ///
+    /// `timer_clock` is the clock of the timers on APB1, i.e.
+    /// `rcu.clocks.timerx()`.
+    pub fn new(timer: TIMER2, pin: PA6<MODE>, timer_clock: Hertz) -> Self {
+        unsafe { force_off() };
+
+        riscv::interrupt::free(|_| unsafe {
+            let rcu = &*RCU::ptr();
+            rcu.apb1en.modify(|_r, w| w.timer2en().set_bit());
+            rcu.apb1rst.modify(|_r, w| w.timer2rst().set_bit());
+            rcu.apb1rst.modify(|_r, w| w.timer2rst().clear_bit());
+        });
+
+        let prescaler = timer_clock.0 / COUNTER_HZ - 1;
+        timer
+            .psc
+            .write(|w| unsafe { w.psc().bits(prescaler as u16) });
+        timer
+            .car
+            .write(|w| unsafe { w.carl().bits((PERIOD_TICKS - 1) as u16) });
+        // Count down in single pulse mode. The counter stops at the reload
+        // value, where the output is off for any duty below 100%. The
+        // compare value is written directly rather than at the next update,
+        // as each period is started by hand anyway.
+        timer.chctl0_output().modify(|_r, w| unsafe {
+            w.ch0ms().bits(0b00);
+            w.ch0comctl().bits(TIMER_CHCTL_PWM0)
+        });
+        timer.ch0cv.write(|w| unsafe { w.ch0val().bits(0) });
+        // The output is only enabled for periods with some duty, see `poll`.
+        timer
+            .chctl2
+            .modify(|_r, w| w.ch0p().clear_bit().ch0en().clear_bit());
+        timer.ctl0.modify(|_r, w| w.dir().set_bit().spm().set_bit());
+        // Load the prescaler and the counter.
+        timer.swevg.write(|w| w.upg().set_bit());
+
+        // Only now hand the pin over to the timer, whose output is low.
+        unsafe {
+            let gpioa = &*GPIOA::ptr();
+            gpioa.ctl0.modify(|_r, w| {
+                w.md6()
+                    .bits(GPIO_MD_OUTPUT_2MHZ)
+                    .ctl6()
+                    .bits(GPIO_CTL_AFIO_PUSH_PULL)
+            });
+        }
+
+        Heater {
+            timer,
+            _pin: pin,
+            duty: 0,
+            lease: 0,
+        }
+    }
+
+    /// Sets the duty cycle, in per mille, for the next [`LEASE_PERIODS`]
+    /// periods. It is clamped to [`MAX_DUTY`].
+    pub fn set_duty(&mut self, duty: u16) {
+        self.duty = duty.min(MAX_DUTY);
+        self.lease = LEASE_PERIODS;
+    }
+
+    /// The duty cycle of the current period, which is 0 once the lease has
+    /// run out.
+    pub fn duty(&self) -> u16 {
+        if self.is_cut_off() {
+            0
+        } else {
+            self.duty
+        }
+    }
+
+    /// Whether the software watchdog has turned the heater off because
+    /// `set_duty` was not called in time.
+    pub fn tripped(&self) -> bool {
+        self.is_cut_off() && self.duty != 0
+    }
+
+    /// Turns the heater off at once.
+    pub fn off(&mut self) {
+        self.timer.ctl0.modify(|_r, w| w.cen().clear_bit());
+        self.timer.chctl2.modify(|_r, w| w.ch0en().clear_bit());
+        self.timer.ch0cv.write(|w| unsafe { w.ch0val().bits(0) });
+        self.timer.swevg.write(|w| w.upg().set_bit());
+        self.duty = 0;
+        self.lease = 0;
+    }
+
+    fn is_running(&self) -> bool {
+        self.timer.ctl0.read().cen().bit_is_set()
+    }
+
+    /// The lease has run out and the last period is over.
+    fn is_cut_off(&self) -> bool {
+        self.lease == 0 && !self.is_running()
+    }
+
+    /// Starts the next period once the current one is over. This needs to be
+    /// called more often than every [`PERIOD_MS`], or the heater gets less
+    /// power than asked for.
+    ///
+    /// Returns whether a new period was started. The tip is unpowered at the
+    /// start of each period, so that is the time to read the thermocouple.
+    pub fn poll(&mut self) -> bool {
+        if self.is_running() || self.lease == 0 {
+            return false;
+        }
+        self.lease -= 1;
+        let compare = self.duty as u32 * PERIOD_TICKS / FULL_DUTY as u32;
+        self.timer
+            .ch0cv
+            .write(|w| unsafe { w.ch0val().bits(compare as u16) });
+        // A compare value of 0 still gives a pulse of one tick, so the output
+        // is turned off for a duty of 0. The period runs all the same.
+        self.timer
+            .chctl2
+            .modify(|_r, w| w.ch0en().bit(compare != 0));
+        self.timer.ctl0.modify(|_r, w| w.cen().set_bit());
+        true
+    }
+}
@@ -0,0 +1,41 @@
+//! Sharing I2C0 between drivers.
+//!
+//! The OLED, the BMA223 and the FUSB302B all sit on I2C0, and each driver
+//! wants to own its bus. The bus is kept in a `RefCell` instead, and each
+//! driver gets a [`SharedI2c`] handle to it. Nothing runs on I2C0 from an
+//! interrupt, so the borrow can't fail.
+
+use core::cell::RefCell;
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+pub struct SharedI2c<'a, I2C> {
+    bus: &'a RefCell<I2C>,
+}
+
+impl<'a, I2C> SharedI2c<'a, I2C> {
+    pub fn new(bus: &'a RefCell<I2C>) -> Self {
+        SharedI2c { bus }
+    }
+}
+
+impl<I2C: Write> Write for SharedI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().write(address, bytes)
+    }
+}
+
+impl<I2C: WriteRead> WriteRead for SharedI2c<'_, I2C> {
+    type Error = I2C::Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().write_read(address, bytes, buffer)
+    }
+}
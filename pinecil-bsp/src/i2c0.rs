@@ -0,0 +1,256 @@
+//! I2C0 driven by its interrupts, for `async` tasks.
+//!
+//! The blocking driver of `gd32vf103xx-hal` busy-waits for each event of a
+//! transaction. This one goes through the same steps as a master, but waits
+//! for each event with the event and error interrupts of I2C0 enabled, so
+//! that the other tasks run in the meantime. The bus holds the clock low
+//! while it waits for the firmware, so being late only slows it down. The
+//! handler of both interrupts has to be set up by the firmware:
+//!
+//! ```ignore
+//! eclic::set_handler(Irq::I2c0Event, i2c0::on_interrupt);
+//! eclic::set_handler(Irq::I2c0Error, i2c0::on_interrupt);
+//! eclic::enable(Irq::I2c0Event);
+//! eclic::enable(Irq::I2c0Error);
+//! ```
+//!
+//! The bus runs at 400 kHz, as in the demos.
+
+use core::task::Poll;
+
+use gd32vf103_pac::{i2c0::stat0, I2C0, RCU};
+use gd32vf103xx_hal::gpio::{
+    gpiob::{PB6, PB7},
+    Alternate, OpenDrain,
+};
+use pinecil_core::executor::poll_fn;
+
+use crate::executor::WakerCell;
+
+static WAKER: WakerCell = WakerCell::new();
+
+const BUS_HZ: u32 = 400_000;
+/// Most SCL rise time of fast mode, in ns.
+const RISE_TIME_NS: u32 = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Nothing answered at the address, or it refused a byte.
+    Nack,
+    /// Another master took over the bus.
+    ArbitrationLost,
+    /// A start or stop where there should be none.
+    Bus,
+}
+
+/// SCL and SDA.
+pub type Pins = (PB6<Alternate<OpenDrain>>, PB7<Alternate<OpenDrain>>);
+
+pub struct I2c0 {
+    i2c: I2C0,
+    pins: Pins,
+}
+
+impl I2c0 {
+    /// Sets up I2C0 as a master. `apb1_hz` is the clock of APB1, which has
+    /// to be a whole number of MHz from 2 to 54.
+    pub fn new(i2c: I2C0, pins: Pins, apb1_hz: u32) -> Self {
+        riscv::interrupt::free(|_| unsafe {
+            let rcu = &*RCU::ptr();
+            rcu.apb1en.modify(|_r, w| w.i2c0en().set_bit());
+            rcu.apb1rst.modify(|_r, w| w.i2c0rst().set_bit());
+            rcu.apb1rst.modify(|_r, w| w.i2c0rst().clear_bit());
+        });
+
+        let mhz = apb1_hz / 1_000_000;
+        i2c.ctl1.write(|w| unsafe { w.i2cclk().bits(mhz as u8) });
+        // Fast mode with a 2:1 low to high ratio, so a period is 3 counts.
+        let count = (apb1_hz / (BUS_HZ * 3)).max(1);
+        i2c.ckcfg.write(|w| unsafe {
+            w.fast().set_bit();
+            w.dtcy().clear_bit();
+            w.clkc().bits(count as u16)
+        });
+        i2c.rt
+            .write(|w| unsafe { w.risetime().bits((mhz * RISE_TIME_NS / 1000 + 1) as u8) });
+        i2c.ctl0.write(|w| w.i2cen().set_bit());
+
+        I2c0 { i2c, pins }
+    }
+
+    pub fn release(self) -> (I2C0, Pins) {
+        self.i2c.ctl0.write(|w| w.i2cen().clear_bit());
+        (self.i2c, self.pins)
+    }
+
+    pub async fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        let result = self.send(address, bytes).await;
+        self.stop();
+        result
+    }
+
+    pub async fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        let result = self.receive(address, buffer).await;
+        if result.is_err() {
+            self.stop();
+        }
+        result
+    }
+
+    /// Writes `bytes`, then reads into `buffer` after a repeated start, as
+    /// for reading registers.
+    pub async fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        let mut result = self.send(address, bytes).await;
+        if result.is_ok() {
+            result = self.receive(address, buffer).await;
+        }
+        if result.is_err() {
+            self.stop();
+        }
+        result
+    }
+
+    /// Sends a start, the address and `bytes`, and leaves the bus held
+    /// after the last byte.
+    async fn send(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.start(address << 1).await?;
+        self.clear_addsend();
+        for &byte in bytes {
+            self.wait(|s| s.tbe().bit_is_set()).await?;
+            self.i2c.data.write(|w| unsafe { w.trb().bits(byte) });
+        }
+        self.wait(|s| s.btc().bit_is_set()).await
+    }
+
+    /// Sends a start and the address, and reads `buffer`, ending with a
+    /// stop. Which bytes get no acknowledge and when the stop is sent
+    /// depends on how many there are, as in the user manual. Reading
+    /// nothing only sends the stop.
+    async fn receive(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        let n = buffer.len();
+        match n {
+            0 => self.stop(),
+            1 => {
+                self.i2c
+                    .ctl0
+                    .modify(|_r, w| w.acken().clear_bit().poap().clear_bit());
+                self.start(address << 1 | 1).await?;
+                self.clear_addsend();
+                self.stop();
+                self.wait(|s| s.rbne().bit_is_set()).await?;
+                buffer[0] = self.read_data();
+            }
+            2 => {
+                self.i2c
+                    .ctl0
+                    .modify(|_r, w| w.acken().set_bit().poap().set_bit());
+                self.start(address << 1 | 1).await?;
+                self.i2c.ctl0.modify(|_r, w| w.acken().clear_bit());
+                self.clear_addsend();
+                self.wait(|s| s.btc().bit_is_set()).await?;
+                self.stop();
+                buffer[0] = self.read_data();
+                buffer[1] = self.read_data();
+                self.i2c.ctl0.modify(|_r, w| w.poap().clear_bit());
+            }
+            _ => {
+                self.i2c
+                    .ctl0
+                    .modify(|_r, w| w.acken().set_bit().poap().clear_bit());
+                self.start(address << 1 | 1).await?;
+                self.clear_addsend();
+                for byte in &mut buffer[..n - 3] {
+                    self.wait(|s| s.rbne().bit_is_set()).await?;
+                    *byte = self.read_data();
+                }
+                // The third last byte is in the data register and the second
+                // last in the shift register.
+                self.wait(|s| s.btc().bit_is_set()).await?;
+                self.i2c.ctl0.modify(|_r, w| w.acken().clear_bit());
+                buffer[n - 3] = self.read_data();
+                self.wait(|s| s.btc().bit_is_set()).await?;
+                self.stop();
+                buffer[n - 2] = self.read_data();
+                self.wait(|s| s.rbne().bit_is_set()).await?;
+                buffer[n - 1] = self.read_data();
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a start, or a repeated start, and the address byte, and waits
+    /// for the address to be acknowledged.
+    async fn start(&mut self, address_byte: u8) -> Result<(), Error> {
+        // The stop of the last transaction may not be out yet.
+        while self.i2c.ctl0.read().stop().bit_is_set() {}
+        self.i2c.ctl0.modify(|_r, w| w.start().set_bit());
+        self.wait(|s| s.sbsend().bit_is_set()).await?;
+        self.i2c
+            .data
+            .write(|w| unsafe { w.trb().bits(address_byte) });
+        self.wait(|s| s.addsend().bit_is_set()).await
+    }
+
+    /// Reading STAT1 after STAT0 clears ADDSEND, which lets the transfer go
+    /// on.
+    fn clear_addsend(&mut self) {
+        let _ = self.i2c.stat0.read();
+        let _ = self.i2c.stat1.read();
+    }
+
+    fn stop(&mut self) {
+        self.i2c.ctl0.modify(|_r, w| w.stop().set_bit());
+    }
+
+    fn read_data(&mut self) -> u8 {
+        self.i2c.data.read().trb().bits()
+    }
+
+    /// Waits until `ready` is true of STAT0, or an error comes up.
+    async fn wait(&mut self, ready: impl Fn(&stat0::R) -> bool) -> Result<(), Error> {
+        let i2c = &self.i2c;
+        poll_fn(|cx| {
+            let stat0 = i2c.stat0.read();
+            let error = if stat0.aerr().bit_is_set() {
+                Some(Error::Nack)
+            } else if stat0.lostarb().bit_is_set() {
+                Some(Error::ArbitrationLost)
+            } else if stat0.berr().bit_is_set() {
+                Some(Error::Bus)
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                i2c.stat0.modify(|_r, w| {
+                    w.aerr().clear_bit();
+                    w.lostarb().clear_bit();
+                    w.berr().clear_bit()
+                });
+                return Poll::Ready(Err(error));
+            }
+            if ready(&stat0) {
+                return Poll::Ready(Ok(()));
+            }
+            WAKER.register(cx.waker());
+            i2c.ctl1
+                .modify(|_r, w| w.evie().set_bit().bufie().set_bit().errie().set_bit());
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// The handler of both I2C0 interrupts. Turns them off, as they stay on
+/// until the firmware has dealt with the event, and wakes the task waiting
+/// for it.
+pub fn on_interrupt() {
+    let i2c = unsafe { &*I2C0::ptr() };
+    i2c.ctl1
+        .modify(|_r, w| w.evie().clear_bit().bufie().clear_bit().errie().clear_bit());
+    WAKER.wake();
+}
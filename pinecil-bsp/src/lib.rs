@@ -0,0 +1,25 @@
+//! Drivers for the parts of the Pinecil which are not covered by
+//! `gd32vf103xx-hal`.
+//!
+//! Anything which does not need the hardware belongs in `pinecil-core`
+//! instead, where it can be tested on the host.
+
+#![no_std]
+
+pub mod adc;
+pub mod bma223;
+pub mod buttons;
+pub mod eclic;
+pub mod executor;
+pub mod flash;
+pub mod fusb302;
+pub mod heater;
+pub mod i2c;
+pub mod i2c0;
+pub mod mtimer;
+pub mod power;
+pub mod rtc;
+pub mod stack;
+pub mod trap;
+pub mod uart;
+pub mod watchdog;
@@ -0,0 +1,157 @@
+//! A monotonic millisecond clock, ticked by the interrupt of the machine
+//! timer of the core.
+//!
+//! The Bumblebee core has the usual RISC-V `mtime` and `mtimecmp`, memory
+//! mapped at `0xD100_0000`. `mtime` counts at a quarter of the core clock, and
+//! the interrupt fires once it reaches `mtimecmp`. Each interrupt moves
+//! `mtimecmp` on by exactly one millisecond from where it was, so the clock
+//! does not drift however late the interrupt is taken, and ticks missed
+//! while interrupts were off are counted when it is.
+//!
+//! With the ECLIC in charge (see `eclic`), the timer is one of its sources:
+//!
+//! ```ignore
+//! eclic::init();
+//! eclic::set_handler(Irq::Timer, mtimer::on_interrupt);
+//! eclic::enable(Irq::Timer);
+//! mtimer::start(core_hz);
+//! ```
+//!
+//! In the CLINT compatible mode `riscv-rt` leaves the core in, the interrupt
+//! is enabled by `mie.MTIE` instead, which [`start`] sets as well, and
+//! handled by a function named `MachineTimer`:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! extern "C" fn MachineTimer() {
+//!     mtimer::on_interrupt();
+//! }
+//! ```
+//!
+//! The software timers of `pinecil_core::timers` run on top of
+//! [`now_ms`], and `async` tasks wait with [`sleep_ms`], which the interrupt
+//! wakes them from.
+
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Poll, Waker},
+};
+
+use critical_section::Mutex;
+use pinecil_core::executor::poll_fn;
+use riscv::register::{mie, mstatus};
+
+const MTIME_LO: *mut u32 = 0xd100_0000 as *mut u32;
+const MTIME_HI: *mut u32 = 0xd100_0004 as *mut u32;
+const MTIMECMP_LO: *mut u32 = 0xd100_0008 as *mut u32;
+const MTIMECMP_HI: *mut u32 = 0xd100_000c as *mut u32;
+
+/// `mtime` counts at the core clock divided by this.
+const MTIME_DIVIDER: u32 = 4;
+
+static TICKS_PER_MS: AtomicU32 = AtomicU32::new(0);
+static NOW_MS: AtomicU32 = AtomicU32::new(0);
+
+/// How many tasks can sleep at the same time. Any more are polled again
+/// straight away until there is room.
+const SLEEPERS: usize = 8;
+
+/// The deadline and waker of each sleeping task.
+type Sleepers = [Option<(u32, Waker)>; SLEEPERS];
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_SLEEPER: Option<(u32, Waker)> = None;
+static SLEEPING: Mutex<RefCell<Sleepers>> = Mutex::new(RefCell::new([NO_SLEEPER; SLEEPERS]));
+
+/// Reads a 64-bit register in two halves, again if the low half wrapped
+/// around in between.
+unsafe fn read64(lo: *const u32, hi: *const u32) -> u64 {
+    loop {
+        let high = hi.read_volatile();
+        let low = lo.read_volatile();
+        if hi.read_volatile() == high {
+            return (high as u64) << 32 | low as u64;
+        }
+    }
+}
+
+/// Writes `mtimecmp` in two halves without the half-written value firing an
+/// interrupt: the high half goes to the top first.
+unsafe fn write_mtimecmp(value: u64) {
+    MTIMECMP_HI.write_volatile(u32::MAX);
+    MTIMECMP_LO.write_volatile(value as u32);
+    MTIMECMP_HI.write_volatile((value >> 32) as u32);
+}
+
+/// Starts the clock at 0 and enables the timer interrupt, along with
+/// interrupts in general.
+pub fn start(core_hz: u32) {
+    let ticks = core_hz / MTIME_DIVIDER / 1000;
+    TICKS_PER_MS.store(ticks, Ordering::Relaxed);
+    NOW_MS.store(0, Ordering::Relaxed);
+    unsafe {
+        let now = read64(MTIME_LO, MTIME_HI);
+        write_mtimecmp(now + ticks as u64);
+        mie::set_mtimer();
+        mstatus::set_mie();
+    }
+}
+
+/// Milliseconds since [`start`], wrapping around after 49 days.
+pub fn now_ms() -> u32 {
+    NOW_MS.load(Ordering::Relaxed)
+}
+
+/// Whether `deadline_ms` has come at `now_ms`, allowing for wrap-around.
+fn due(deadline_ms: u32, now_ms: u32) -> bool {
+    (now_ms.wrapping_sub(deadline_ms) as i32) >= 0
+}
+
+/// Waits for at least `ms` milliseconds, for `async` tasks.
+pub async fn sleep_ms(ms: u32) {
+    // Rounded up, as the current millisecond has partly gone by.
+    let deadline_ms = now_ms().wrapping_add(ms).wrapping_add(1);
+    poll_fn(|cx| {
+        if due(deadline_ms, now_ms()) {
+            return Poll::Ready(());
+        }
+        let waiting = critical_section::with(|cs| {
+            let mut sleepers = SLEEPING.borrow_ref_mut(cs);
+            let slot = sleepers
+                .iter()
+                .position(|s| matches!(s, Some((_, waker)) if waker.will_wake(cx.waker())))
+                .or_else(|| sleepers.iter().position(Option::is_none));
+            slot.map(|i| sleepers[i] = Some((deadline_ms, cx.waker().clone())))
+        });
+        if waiting.is_none() {
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// The handler of the timer interrupt.
+pub fn on_interrupt() {
+    let ticks = TICKS_PER_MS.load(Ordering::Relaxed) as u64;
+    let mut ms = NOW_MS.load(Ordering::Relaxed);
+    unsafe {
+        let mut compare = read64(MTIMECMP_LO, MTIMECMP_HI);
+        let now = read64(MTIME_LO, MTIME_HI);
+        while compare <= now {
+            compare += ticks;
+            ms = ms.wrapping_add(1);
+        }
+        write_mtimecmp(compare);
+    }
+    NOW_MS.store(ms, Ordering::Relaxed);
+
+    critical_section::with(|cs| {
+        for sleeper in SLEEPING.borrow_ref_mut(cs).iter_mut() {
+            if matches!(sleeper, Some((deadline_ms, _)) if due(*deadline_ms, ms)) {
+                sleeper.take().unwrap().1.wake();
+            }
+        }
+    });
+}
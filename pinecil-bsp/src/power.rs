@@ -0,0 +1,253 @@
+//! Saving power: sleep, deep-sleep and standby, and what wakes the chip up.
+//!
+//! - [`sleep`] stops the core until the next interrupt, while everything
+//!   else carries on. This is what the main loops do between ticks.
+//! - [`deep_sleep`] stops all the clocks but the IRC40K, with the LDO in its
+//!   low power mode. Only the EXTI lines set up with [`enable_wake`] wake
+//!   the chip: the buttons, the BMA223 or the RTC alarm. The chip wakes up
+//!   on the 8 MHz IRC8M, and [`deep_sleep`] brings back the 96 MHz from the
+//!   PLL before it returns. The RAM and the peripherals keep their state,
+//!   but the machine timer stands still, so the clock of `mtimer` misses the
+//!   time asleep.
+//! - [`standby`] turns off everything but the backup domain, and only the
+//!   RTC alarm wakes the chip, which then starts over from reset:
+//!   [`woke_from_standby`] tells after that. The buttons can't, as the only
+//!   wake up pin is PA0, which is the input voltage on the Pinecil.
+//!
+//! The FWDGT keeps counting in both deep-sleep and standby, so with the
+//! watchdog running, the RTC alarm has to wake the chip in time to feed it.
+
+use core::{
+    arch::asm,
+    ops::BitOr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use gd32vf103_pac::{AFIO, EXTI, GPIOA, PMU, RCU};
+
+use crate::{
+    eclic::{self, Irq},
+    rtc,
+};
+
+/// The CSR of the Bumblebee core which picks what `wfi` does.
+const CSR_SLEEPVALUE: usize = 0x811;
+const SLEEPVALUE_SLEEP: usize = 0;
+const SLEEPVALUE_DEEP: usize = 1;
+
+// RCU_APB1EN
+const APB1EN_PMUEN: u32 = 1 << 28;
+// PMU_CTL
+const PMU_CTL_LDOLP: u32 = 1 << 0;
+const PMU_CTL_STBMOD: u32 = 1 << 1;
+const PMU_CTL_WURST: u32 = 1 << 2;
+const PMU_CTL_STBRST: u32 = 1 << 3;
+// PMU_CS
+const PMU_CS_STBF: u32 = 1 << 1;
+
+/// EXTISS0 value with lines 0 and 1 on port B, as in `buttons`.
+const EXTISS_PB0_PB1: u32 = 0x11;
+const EXTISS_LINES_0_1_MASK: u32 = 0xff;
+/// Line 13 in EXTISS3, which is port A at 0.
+const EXTISS_LINE_13_MASK: u32 = 0xf << 4;
+/// AFIO_PCF0 with JTAG turned off, which frees PA13.
+const PCF0_SWJ_CFG_MASK: u32 = 0b111 << 24;
+const PCF0_SWJ_CFG_NO_JTAG: u32 = 0b100 << 24;
+/// GPIOA_CTL1 bits of PA13, set to an input with a pull-up or down.
+const CTL1_PA13_MASK: u32 = 0xf << 20;
+const CTL1_PA13_INPUT_PULL: u32 = 0b1000 << 20;
+const PA13: u32 = 1 << 13;
+
+/// EXTI lines which wake the chip from deep-sleep, combined with `|`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Wake(u32);
+
+impl Wake {
+    /// The '+' and '-' buttons, PB0 and PB1, as they are pressed.
+    pub const BUTTONS: Wake = Wake(0b11);
+    /// The BMA223 seeing the iron move, on PA13 (see
+    /// `Bma223::enable_motion_interrupt`). PA13 is also JTMS, so this turns
+    /// JTAG off until the next reset.
+    pub const MOTION: Wake = Wake(1 << 13);
+    /// The alarm of `rtc`, which is EXTI line 17.
+    pub const RTC_ALARM: Wake = Wake(1 << 17);
+
+    /// Whether any of `other` is in here.
+    pub fn intersects(self, other: Wake) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Wake {
+    type Output = Wake;
+
+    fn bitor(self, other: Wake) -> Wake {
+        Wake(self.0 | other.0)
+    }
+}
+
+static ENABLED: AtomicU32 = AtomicU32::new(0);
+static WOKEN: AtomicU32 = AtomicU32::new(0);
+
+/// Makes `wake` wake the chip from deep-sleep, with their EXTI interrupts
+/// set up with the ECLIC and handled by [`on_interrupt`]. The ECLIC has to
+/// be initialized, and the AFIO clocked, which `gd32vf103xx-hal` does when
+/// it sets up the AFIO. The buttons have to be set up as inputs already, as
+/// in `buttons`.
+pub fn enable_wake(wake: Wake) {
+    riscv::interrupt::free(|_| unsafe {
+        let afio = &*AFIO::ptr();
+        if wake.intersects(Wake::BUTTONS) {
+            afio.extiss0
+                .modify(|r, w| w.bits(r.bits() & !EXTISS_LINES_0_1_MASK | EXTISS_PB0_PB1));
+        }
+        if wake.intersects(Wake::MOTION) {
+            afio.pcf0
+                .modify(|r, w| w.bits(r.bits() & !PCF0_SWJ_CFG_MASK | PCF0_SWJ_CFG_NO_JTAG));
+            afio.extiss3
+                .modify(|r, w| w.bits(r.bits() & !EXTISS_LINE_13_MASK));
+            // The pins of the BMA223 are open drain, and active low.
+            let rcu = &*RCU::ptr();
+            rcu.apb2en.modify(|_r, w| w.paen().set_bit());
+            let gpioa = &*GPIOA::ptr();
+            gpioa.octl.modify(|r, w| w.bits(r.bits() | PA13));
+            gpioa
+                .ctl1
+                .modify(|r, w| w.bits(r.bits() & !CTL1_PA13_MASK | CTL1_PA13_INPUT_PULL));
+        }
+        let exti = &*EXTI::ptr();
+        let rising = wake.0 & (Wake::BUTTONS.0 | Wake::RTC_ALARM.0);
+        let falling = wake.0 & Wake::MOTION.0;
+        exti.rten.modify(|r, w| w.bits(r.bits() | rising));
+        exti.ften.modify(|r, w| w.bits(r.bits() | falling));
+        exti.pd.write(|w| w.bits(wake.0));
+        exti.inten.modify(|r, w| w.bits(r.bits() | wake.0));
+    });
+    ENABLED.fetch_or(wake.0, Ordering::Relaxed);
+
+    let mut irqs: [Option<Irq>; 4] = [None; 4];
+    if wake.intersects(Wake::BUTTONS) {
+        irqs[0] = Some(Irq::Exti0);
+        irqs[1] = Some(Irq::Exti1);
+    }
+    if wake.intersects(Wake::MOTION) {
+        irqs[2] = Some(Irq::Exti10_15);
+    }
+    if wake.intersects(Wake::RTC_ALARM) {
+        irqs[3] = Some(Irq::RtcAlarm);
+    }
+    for irq in irqs.iter().flatten() {
+        eclic::set_handler(*irq, on_interrupt);
+        eclic::enable(*irq);
+    }
+}
+
+/// The handler of the EXTI lines set up with [`enable_wake`]. Notes down
+/// which of them went off, for [`take_woken`].
+pub fn on_interrupt() {
+    let exti = unsafe { &*EXTI::ptr() };
+    let pending = exti.pd.read().bits() & ENABLED.load(Ordering::Relaxed);
+    exti.pd.write(|w| unsafe { w.bits(pending) });
+    if pending & Wake::RTC_ALARM.0 != 0 {
+        // The line only goes off again once the flag has been cleared.
+        rtc::clear_alarm_flag();
+    }
+    WOKEN.fetch_or(pending, Ordering::Relaxed);
+}
+
+/// What went off since the last call, which is what woke the chip if it
+/// was asleep.
+pub fn take_woken() -> Wake {
+    Wake(WOKEN.swap(0, Ordering::Relaxed))
+}
+
+/// Stops the core until the next interrupt.
+pub fn sleep() {
+    unsafe { riscv::asm::wfi() };
+}
+
+/// Stops the clocks until one of the wake sources goes off, then brings
+/// back the 96 MHz clock. The interrupt which woke the chip is taken after
+/// that, if interrupts are enabled.
+///
+/// The UART has to be done sending first, or the rest is cut off.
+pub fn deep_sleep() {
+    riscv::interrupt::free(|_| unsafe {
+        let pmu = enable_pmu();
+        pmu.ctl
+            .modify(|r, w| w.bits(r.bits() & !PMU_CTL_STBMOD | PMU_CTL_LDOLP));
+        set_sleep_value(SLEEPVALUE_DEEP);
+        // With interrupts off, an interrupt wakes the core without being
+        // taken.
+        riscv::asm::wfi();
+        set_sleep_value(SLEEPVALUE_SLEEP);
+        restore_clock();
+    });
+}
+
+/// Turns everything off until the RTC alarm, which has to be set, goes
+/// off and resets the chip.
+pub fn standby() -> ! {
+    unsafe {
+        riscv::interrupt::disable();
+        let pmu = enable_pmu();
+        // A wake up flag from before would wake the chip straight away.
+        pmu.ctl
+            .modify(|r, w| w.bits(r.bits() | PMU_CTL_STBMOD | PMU_CTL_WURST));
+        set_sleep_value(SLEEPVALUE_DEEP);
+    }
+    loop {
+        unsafe { riscv::asm::wfi() };
+    }
+}
+
+/// Whether the chip was reset by waking up from standby. Only tells once.
+pub fn woke_from_standby() -> bool {
+    unsafe {
+        let pmu = enable_pmu();
+        let standby = pmu.cs.read().bits() & PMU_CS_STBF != 0;
+        pmu.ctl.modify(|r, w| w.bits(r.bits() | PMU_CTL_STBRST));
+        standby
+    }
+}
+
+/// Sets up the clocks as the demos do: 96 MHz from the PLL, fed by the
+/// 8 MHz HXTAL, with APB1 at 48 MHz. This is `init_clock` of demo 04, which
+/// `gd32vf103xx-hal` does as well.
+///
+/// # Safety
+///
+/// This changes the clocks behind the back of the HAL, so they have to be
+/// what the HAL set up in the first place.
+pub unsafe fn restore_clock() {
+    let rcu = &*RCU::ptr();
+
+    // Enable HXTAL and wait for it to be stable.
+    rcu.ctl.modify(|_r, w| w.hxtalen().set_bit());
+    while !rcu.ctl.read().hxtalstb().bit_is_set() {}
+    rcu.ctl.modify(|_r, w| w.ckmen().set_bit());
+
+    // PLL from HXTAL, x12 from 8 MHz to 96 MHz.
+    rcu.cfg0.modify(|_r, w| w.pllsel().set_bit());
+    rcu.cfg0
+        .modify(|_r, w| w.pllmf_4().clear_bit().pllmf_3_0().bits(0b1010));
+    // APB1 must not go above 60 MHz.
+    rcu.cfg0.modify(|_r, w| w.apb1psc().bits(0b100));
+
+    rcu.ctl.modify(|_r, w| w.pllen().set_bit());
+    while !rcu.ctl.read().pllstb().bit_is_set() {}
+
+    // Switch the system clock over to the PLL.
+    rcu.cfg0.modify(|_r, w| w.scs().bits(0b10));
+    while rcu.cfg0.read().scss().bits() != 0b10 {}
+}
+
+unsafe fn enable_pmu() -> &'static gd32vf103_pac::pmu::RegisterBlock {
+    let rcu = &*RCU::ptr();
+    rcu.apb1en.modify(|r, w| w.bits(r.bits() | APB1EN_PMUEN));
+    &*PMU::ptr()
+}
+
+unsafe fn set_sleep_value(value: usize) {
+    asm!("csrw {csr}, {0}", in(reg) value, csr = const CSR_SLEEPVALUE);
+}
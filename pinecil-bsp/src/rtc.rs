@@ -0,0 +1,167 @@
+//! The RTC, counting seconds in the backup domain.
+//!
+//! The Pinecil has no 32.768 kHz crystal, so the RTC runs from the IRC40K,
+//! which is only good to a few percent, but keeps going in deep-sleep and
+//! standby. The count and the alarm survive a reset, as long as the power
+//! stays on; [`Rtc::new`] only sets the RTC up if it is not running yet.
+//!
+//! The alarm is EXTI line 17, which `power` sets up to wake the chip.
+//!
+//! The backup data registers are in the same domain, and kept the same way.
+//! They hold the `pinecil_core::counters` between resets.
+
+use gd32vf103_pac::{rtc::RegisterBlock, PMU, RCU, RTC};
+
+/// Number of backup data registers, of 16 bits each, which can be read and
+/// written through [`Rtc`]. There are more after a gap, which are not used.
+pub const BACKUP_REGS: usize = 10;
+/// BKP_DATA0, with the next ones every 4 bytes.
+const BKP_DATA0: *mut u32 = 0x4000_6c04 as *mut u32;
+
+/// The RTC counts at the clock divided by this.
+const PRESCALER: u32 = 40_000;
+
+// RCU_APB1EN
+const APB1EN_BKPIEN: u32 = 1 << 27;
+const APB1EN_PMUEN: u32 = 1 << 28;
+// RCU_RSTSCK
+const RSTSCK_IRC40KEN: u32 = 1 << 0;
+const RSTSCK_IRC40KSTB: u32 = 1 << 1;
+// RCU_BDCTL
+const BDCTL_RTCSRC_MASK: u32 = 0b11 << 8;
+const BDCTL_RTCSRC_IRC40K: u32 = 0b10 << 8;
+const BDCTL_RTCEN: u32 = 1 << 15;
+// PMU_CTL
+const PMU_CTL_BKPWEN: u32 = 1 << 8;
+// RTC_INTEN
+const INTEN_ALRMIE: u32 = 1 << 1;
+// RTC_CTL
+const CTL_SCIF: u32 = 1 << 0;
+const CTL_ALRMIF: u32 = 1 << 1;
+const CTL_OVIF: u32 = 1 << 2;
+const CTL_RSYNF: u32 = 1 << 3;
+const CTL_CMF: u32 = 1 << 4;
+const CTL_LWOFF: u32 = 1 << 5;
+/// The flags of RTC_CTL, which are cleared by writing 0, and left as they
+/// are by writing 1.
+const CTL_FLAGS: u32 = CTL_SCIF | CTL_ALRMIF | CTL_OVIF | CTL_RSYNF;
+
+pub struct Rtc {
+    rtc: RTC,
+}
+
+impl Rtc {
+    /// Starts the RTC at 0 seconds, or carries on where it is if it is
+    /// running from before a reset.
+    pub fn new(rtc: RTC) -> Self {
+        riscv::interrupt::free(|_| unsafe {
+            let rcu = &*RCU::ptr();
+            rcu.apb1en
+                .modify(|r, w| w.bits(r.bits() | APB1EN_BKPIEN | APB1EN_PMUEN));
+            let pmu = &*PMU::ptr();
+            pmu.ctl.modify(|r, w| w.bits(r.bits() | PMU_CTL_BKPWEN));
+            // The IRC40K is off after a reset, even if the RTC is running.
+            rcu.rstsck.modify(|r, w| w.bits(r.bits() | RSTSCK_IRC40KEN));
+            while rcu.rstsck.read().bits() & RSTSCK_IRC40KSTB == 0 {}
+        });
+        let mut this = Rtc { rtc };
+        let rcu = unsafe { &*RCU::ptr() };
+        let bdctl = rcu.bdctl.read().bits();
+        if bdctl & BDCTL_RTCEN == 0 || bdctl & BDCTL_RTCSRC_MASK != BDCTL_RTCSRC_IRC40K {
+            rcu.bdctl.modify(|r, w| unsafe {
+                w.bits(r.bits() & !BDCTL_RTCSRC_MASK | BDCTL_RTCSRC_IRC40K | BDCTL_RTCEN)
+            });
+            this.configure(|rtc| unsafe {
+                rtc.psch.write(|w| w.bits((PRESCALER - 1) >> 16));
+                rtc.pscl.write(|w| w.bits((PRESCALER - 1) & 0xffff));
+                rtc.cnth.write(|w| w.bits(0));
+                rtc.cntl.write(|w| w.bits(0));
+            });
+        }
+        // The registers read as they were before the reset until the RTC
+        // has caught up.
+        modify_ctl(&this.rtc, |ctl| ctl & !CTL_RSYNF);
+        while this.rtc.ctl.read().bits() & CTL_RSYNF == 0 {}
+        this
+    }
+
+    /// Seconds since the RTC was started.
+    pub fn now_s(&self) -> u32 {
+        // Again if the low half wrapped around in between.
+        loop {
+            let high = self.rtc.cnth.read().bits();
+            let low = self.rtc.cntl.read().bits();
+            if self.rtc.cnth.read().bits() == high {
+                return high << 16 | low;
+            }
+        }
+    }
+
+    /// Sets off the alarm once [`now_s`](Rtc::now_s) gets to `at_s`.
+    pub fn set_alarm(&mut self, at_s: u32) {
+        self.clear_alarm();
+        self.configure(|rtc| unsafe {
+            rtc.alrmh.write(|w| w.bits(at_s >> 16));
+            rtc.alrml.write(|w| w.bits(at_s & 0xffff));
+        });
+        self.rtc
+            .inten
+            .modify(|r, w| unsafe { w.bits(r.bits() | INTEN_ALRMIE) });
+    }
+
+    /// Sets off the alarm `s` seconds from now.
+    pub fn set_alarm_in(&mut self, s: u32) {
+        let at_s = self.now_s().wrapping_add(s);
+        self.set_alarm(at_s);
+    }
+
+    /// Whether the alarm has gone off since it was set or cleared.
+    pub fn alarm_fired(&self) -> bool {
+        self.rtc.ctl.read().bits() & CTL_ALRMIF != 0
+    }
+
+    pub fn clear_alarm(&mut self) {
+        self.rtc
+            .inten
+            .modify(|r, w| unsafe { w.bits(r.bits() & !INTEN_ALRMIE) });
+        modify_ctl(&self.rtc, |ctl| ctl & !CTL_ALRMIF);
+    }
+
+    /// Reads the backup data registers into `words`, from the first one on.
+    pub fn read_backup(&self, words: &mut [u16]) {
+        assert!(words.len() <= BACKUP_REGS);
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = unsafe { BKP_DATA0.add(i).read_volatile() } as u16;
+        }
+    }
+
+    /// Writes `words` to the backup data registers, from the first one on.
+    pub fn write_backup(&mut self, words: &[u16]) {
+        assert!(words.len() <= BACKUP_REGS);
+        for (i, &word) in words.iter().enumerate() {
+            unsafe { BKP_DATA0.add(i).write_volatile(word as u32) };
+        }
+    }
+
+    /// Writes to the registers of the RTC which can only be written in
+    /// configuration mode, one write at a time.
+    fn configure(&mut self, f: impl FnOnce(&RTC)) {
+        modify_ctl(&self.rtc, |ctl| ctl | CTL_CMF);
+        f(&self.rtc);
+        modify_ctl(&self.rtc, |ctl| ctl & !CTL_CMF);
+        while self.rtc.ctl.read().bits() & CTL_LWOFF == 0 {}
+    }
+}
+
+/// Changes RTC_CTL once the last write has gone through, without clearing
+/// any flag `f` does not.
+fn modify_ctl(rtc: &RegisterBlock, f: impl FnOnce(u32) -> u32) {
+    while rtc.ctl.read().bits() & CTL_LWOFF == 0 {}
+    let ctl = rtc.ctl.read().bits() | CTL_FLAGS;
+    rtc.ctl.write(|w| unsafe { w.bits(f(ctl)) });
+}
+
+/// Clears the alarm flag, for the interrupt handler.
+pub(crate) fn clear_alarm_flag() {
+    modify_ctl(unsafe { &*RTC::ptr() }, |ctl| ctl & !CTL_ALRMIF);
+}
@@ -0,0 +1,77 @@
+//! Painting the stack, to measure how much of it is used (see
+//! `pinecil_core::stack`).
+//!
+//! The stack is what `riscv-rt` leaves of the RAM in `memory.x` after the
+//! data, the BSS and the heap, from `_sheap + _heap_size` up to
+//! `_stack_start`. [`paint`] has to be called first thing in `main`. Not
+//! from `pre_init`, as the BSS is only cleared after that, and with it the
+//! flag which says that the stack has been painted.
+
+use core::{
+    arch::asm,
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use pinecil_core::stack::{Usage, PAINT};
+
+extern "C" {
+    static _sheap: u32;
+    /// Only the address means something.
+    static _heap_size: u8;
+    static _stack_start: u32;
+}
+
+static PAINTED: AtomicBool = AtomicBool::new(false);
+
+/// The lowest address of the stack.
+pub fn bottom() -> usize {
+    let heap_end = ptr::addr_of!(_sheap) as usize + ptr::addr_of!(_heap_size) as usize;
+    (heap_end + 3) & !3
+}
+
+/// Just above the highest address of the stack.
+pub fn top() -> usize {
+    ptr::addr_of!(_stack_start) as usize
+}
+
+fn sp() -> usize {
+    let sp: usize;
+    unsafe { asm!("mv {}, sp", out(reg) sp) };
+    sp
+}
+
+/// Room left below the stack pointer for the functions the loop in
+/// [`paint`] calls, which are only calls in a debug build.
+const PAINT_MARGIN: usize = 256;
+
+/// Fills the stack with the paint, from the bottom up to where it is now.
+pub fn paint() {
+    let end = (sp() - PAINT_MARGIN) & !3;
+    let mut word = bottom() as *mut u32;
+    while (word as usize) < end {
+        unsafe {
+            ptr::write_volatile(word, PAINT);
+            word = word.add(1);
+        }
+    }
+    PAINTED.store(true, Ordering::Release);
+}
+
+/// How much of the stack has been used at most, if it has been painted.
+pub fn usage() -> Option<Usage> {
+    if !PAINTED.load(Ordering::Acquire) {
+        return None;
+    }
+    let bottom = bottom() as *const u32;
+    let words = (top() - bottom as usize) / 4;
+    // Read word by word, as the part in use changes under our feet.
+    let stack = (0..words).map(|i| unsafe { ptr::read_volatile(bottom.add(i)) });
+    Some(Usage::measure(stack))
+}
+
+/// Whether the stack has gone past its bottom, going by the stack pointer
+/// `sp` and by the guard words if it has been painted.
+pub fn overflowed(sp: usize) -> bool {
+    sp < bottom() || usage().map_or(false, |usage| usage.overflowed())
+}
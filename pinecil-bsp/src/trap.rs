@@ -0,0 +1,99 @@
+//! The exception handler, instead of the endless loop of `riscv-rt`.
+//!
+//! A bus fault, an illegal instruction or any other exception turns the
+//! heater off, keeps a [`CrashRecord`] of where it happened and of whether
+//! the stack had overflowed (see `stack`), prints it over USART1 if the
+//! firmware has set that up, and halts. The record is kept in the `.uninit`
+//! section, which `memory.x` puts out of the way of both the startup code
+//! and the bootloader, so it survives the reset which gets the iron going
+//! again. The next boot reports it:
+//!
+//! ```ignore
+//! if let Some(crash) = trap::take_crash() {
+//!     let _ = write!(uart1_tx, "Crashed before the reset: {}\r\n", crash);
+//! }
+//! ```
+
+use core::{
+    fmt::{self, Write},
+    mem::{size_of, MaybeUninit},
+    ptr,
+};
+
+use gd32vf103_pac::{RCU, USART1};
+use pinecil_core::crash::{self, CrashRecord};
+use riscv::register::{mcause, mepc, mtval};
+
+use crate::{heater, stack};
+
+#[link_section = ".uninit.CRASH"]
+static mut CRASH: MaybeUninit<[u32; crash::WORDS]> = MaybeUninit::uninit();
+
+/// The registers `riscv-rt` saves on the stack before it calls the handler.
+#[repr(C)]
+struct TrapFrame {
+    ra: usize,
+    /// t0 to t6 and a0 to a7.
+    _others: [usize; 15],
+}
+
+/// The crash before the last reset, if there was one. It is only reported
+/// once.
+pub fn take_crash() -> Option<CrashRecord> {
+    unsafe {
+        let words = ptr::addr_of_mut!(CRASH) as *mut [u32; crash::WORDS];
+        let record = CrashRecord::parse(&ptr::read_volatile(words));
+        ptr::write_volatile(words, [0; crash::WORDS]);
+        record
+    }
+}
+
+#[export_name = "ExceptionHandler"]
+fn exception_handler(frame: &TrapFrame) -> ! {
+    unsafe { heater::force_off() };
+    // The frame was pushed right below where the stack was.
+    let sp = frame as *const TrapFrame as usize + size_of::<TrapFrame>();
+    let record = CrashRecord {
+        mcause: mcause::read().bits() as u32,
+        mepc: mepc::read() as u32,
+        mtval: mtval::read() as u32,
+        sp: sp as u32,
+        ra: frame.ra as u32,
+        stack_overflow: stack::overflowed(sp),
+    };
+    unsafe {
+        let words = ptr::addr_of_mut!(CRASH) as *mut [u32; crash::WORDS];
+        ptr::write_volatile(words, record.to_words());
+    }
+    if PolledUart::is_enabled() {
+        let _ = write!(PolledUart, "\r\n*** {}\r\n", record);
+    }
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+/// USART1 written to without interrupts, whoever owns it.
+struct PolledUart;
+
+impl PolledUart {
+    fn is_enabled() -> bool {
+        unsafe {
+            (*RCU::ptr()).apb1en.read().usart1en().bit_is_set() && {
+                let ctl0 = (*USART1::ptr()).ctl0.read();
+                ctl0.uen().bit_is_set() && ctl0.ten().bit_is_set()
+            }
+        }
+    }
+}
+
+impl Write for PolledUart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let usart = unsafe { &*USART1::ptr() };
+        for &b in s.as_bytes() {
+            while usart.stat.read().tbe().bit_is_clear() {}
+            usart.data.write(|w| unsafe { w.data().bits(b as u16) });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+//! USART1, the UART on the breakout board, for `async` tasks.
+//!
+//! The UART is set up by `gd32vf103xx-hal` as in the other demos, and the
+//! halves it splits into are handed over to [`UartTx`] and [`UartRx`]. While
+//! a half waits for room or for data, it enables the matching interrupt of
+//! USART1, whose handler has to be set up by the firmware:
+//!
+//! ```ignore
+//! eclic::set_handler(Irq::Usart1, uart::on_interrupt);
+//! eclic::enable(Irq::Usart1);
+//! ```
+
+use core::{fmt, task::Poll};
+
+use embedded_hal::serial::{Read, Write};
+use gd32vf103_pac::USART1;
+use gd32vf103xx_hal::serial::{Error, Rx, Tx};
+use pinecil_core::executor::poll_fn;
+
+use crate::executor::WakerCell;
+
+static TX_WAKER: WakerCell = WakerCell::new();
+static RX_WAKER: WakerCell = WakerCell::new();
+
+/// Longest text [`UartTx::write_fmt`] writes, the rest is cut off between
+/// characters.
+pub const FMT_LEN: usize = 80;
+
+pub struct UartTx {
+    tx: Tx<USART1>,
+}
+
+impl UartTx {
+    pub fn new(tx: Tx<USART1>) -> Self {
+        UartTx { tx }
+    }
+
+    pub fn release(self) -> Tx<USART1> {
+        self.tx
+    }
+
+    /// Waits until the byte has been handed to the UART.
+    pub async fn write_byte(&mut self, byte: u8) {
+        poll_fn(|cx| match self.tx.write(byte) {
+            Ok(()) => Poll::Ready(()),
+            Err(nb::Error::WouldBlock) => {
+                TX_WAKER.register(cx.waker());
+                riscv::interrupt::free(|_| unsafe {
+                    (*USART1::ptr()).ctl0.modify(|_r, w| w.tbeie().set_bit());
+                });
+                Poll::Pending
+            }
+            Err(nb::Error::Other(e)) => match e {},
+        })
+        .await
+    }
+
+    pub async fn write_all(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_byte(byte).await;
+        }
+    }
+
+    /// Formats into a buffer on the stack first, so that `write!` works:
+    /// `write!(tx, "{}", x).await`.
+    pub async fn write_fmt(&mut self, args: fmt::Arguments<'_>) {
+        let mut buf = FmtBuf {
+            buf: [0; FMT_LEN],
+            len: 0,
+        };
+        let _ = fmt::Write::write_fmt(&mut buf, args);
+        let len = buf.len;
+        self.write_all(&buf.buf[..len]).await;
+    }
+}
+
+struct FmtBuf {
+    buf: [u8; FMT_LEN],
+    len: usize,
+}
+
+impl fmt::Write for FmtBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(FMT_LEN - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+pub struct UartRx {
+    rx: Rx<USART1>,
+}
+
+impl UartRx {
+    pub fn new(rx: Rx<USART1>) -> Self {
+        UartRx { rx }
+    }
+
+    pub fn release(self) -> Rx<USART1> {
+        self.rx
+    }
+
+    /// Waits for the next byte.
+    pub async fn read_byte(&mut self) -> Result<u8, Error> {
+        poll_fn(|cx| match self.rx.read() {
+            Ok(byte) => Poll::Ready(Ok(byte)),
+            Err(nb::Error::WouldBlock) => {
+                RX_WAKER.register(cx.waker());
+                riscv::interrupt::free(|_| unsafe {
+                    (*USART1::ptr()).ctl0.modify(|_r, w| w.rbneie().set_bit());
+                });
+                Poll::Pending
+            }
+            Err(nb::Error::Other(e)) => Poll::Ready(Err(e)),
+        })
+        .await
+    }
+
+    /// Waits for at least one byte, and reads as many more as have already
+    /// arrived, up to the length of `buf`. Returns how many were read.
+    pub async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let first = match buf.first_mut() {
+            Some(first) => first,
+            None => return Ok(0),
+        };
+        *first = self.read_byte().await?;
+        let mut len = 1;
+        for byte in &mut buf[1..] {
+            match self.rx.read() {
+                Ok(b) => *byte = b,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+            len += 1;
+        }
+        Ok(len)
+    }
+}
+
+/// The handler of the USART1 interrupt. Turns off the interrupts which
+/// fired, and wakes the tasks waiting for them.
+pub fn on_interrupt() {
+    let usart = unsafe { &*USART1::ptr() };
+    let stat = usart.stat.read();
+    let ctl0 = usart.ctl0.read();
+    let tx = ctl0.tbeie().bit_is_set() && stat.tbe().bit_is_set();
+    // An overrun has the data interrupt fire as well.
+    let rx = ctl0.rbneie().bit_is_set() && (stat.rbne().bit_is_set() || stat.orerr().bit_is_set());
+    usart.ctl0.modify(|_r, w| {
+        if tx {
+            w.tbeie().clear_bit();
+        }
+        if rx {
+            w.rbneie().clear_bit();
+        }
+        w
+    });
+    if tx {
+        TX_WAKER.wake();
+    }
+    if rx {
+        RX_WAKER.wake();
+    }
+}
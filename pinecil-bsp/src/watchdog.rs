@@ -0,0 +1,83 @@
+//! The free watchdog timer (FWDGT), which resets the chip unless it is fed
+//! in time, and the flags in the RCU saying what reset the chip last.
+//!
+//! Once started, the FWDGT can't be stopped other than by a reset. It runs
+//! from its own oscillator, so it still resets the chip when the firmware
+//! hangs with the interrupts off, or waiting on an I2C bus which is stuck.
+//! It is held while the core is halted by a debugger.
+//!
+//! ```ignore
+//! let cause = watchdog::take_reset_cause();
+//! let mut watchdog = Watchdog::start(peripherals.FWDGT, 1000).unwrap();
+//! loop {
+//!     watchdog.feed();
+//!     // ...
+//! }
+//! ```
+
+use gd32vf103_pac::{FWDGT, RCU};
+use pinecil_core::watchdog::{BadTimeout, Prescale, ResetCause};
+
+const CMD_UNLOCK: u32 = 0x5555;
+const CMD_RELOAD: u32 = 0xaaaa;
+const CMD_START: u32 = 0xcccc;
+/// Set in FWDGT_STAT while a write of the prescaler is on its way.
+const STAT_PUD: u32 = 1 << 0;
+/// Set in FWDGT_STAT while a write of the reload value is on its way.
+const STAT_RUD: u32 = 1 << 1;
+
+/// Clears the reset flags of RCU_RSTSCK.
+const RSTSCK_RSTFC: u32 = 1 << 24;
+
+/// DBG_CTL, with the bit holding the FWDGT while the core is halted.
+const DBG_CTL: *mut u32 = 0xe004_2004 as *mut u32;
+const DBG_CTL_FWDGT_HOLD: u32 = 1 << 8;
+
+pub struct Watchdog {
+    fwdgt: FWDGT,
+    prescale: Prescale,
+}
+
+impl Watchdog {
+    /// Starts the FWDGT, to reset the chip unless it is fed within
+    /// `timeout_ms` (see `pinecil_core::watchdog` for how close that is).
+    pub fn start(fwdgt: FWDGT, timeout_ms: u32) -> Result<Self, BadTimeout> {
+        let prescale = Prescale::for_timeout(timeout_ms)?;
+        unsafe {
+            DBG_CTL.write_volatile(DBG_CTL.read_volatile() | DBG_CTL_FWDGT_HOLD);
+            // Starting it first turns on the IRC40K, which the registers
+            // below need to be written.
+            fwdgt.ctl.write(|w| w.bits(CMD_START));
+            fwdgt.ctl.write(|w| w.bits(CMD_UNLOCK));
+            while fwdgt.stat.read().bits() & STAT_PUD != 0 {}
+            fwdgt.psc.write(|w| w.bits(prescale.psc as u32));
+            while fwdgt.stat.read().bits() & STAT_RUD != 0 {}
+            fwdgt.rld.write(|w| w.bits(prescale.reload as u32));
+            // Waits for both to be taken, then starts over from the new
+            // reload value, which also locks the registers again.
+            while fwdgt.stat.read().bits() & (STAT_PUD | STAT_RUD) != 0 {}
+            fwdgt.ctl.write(|w| w.bits(CMD_RELOAD));
+        }
+        Ok(Watchdog { fwdgt, prescale })
+    }
+
+    /// Starts the timeout over.
+    pub fn feed(&mut self) {
+        self.fwdgt.ctl.write(|w| unsafe { w.bits(CMD_RELOAD) });
+    }
+
+    /// The timeout, rounded down to what the FWDGT can count.
+    pub fn timeout_ms(&self) -> u32 {
+        self.prescale.timeout_ms()
+    }
+}
+
+/// What reset the chip last. The flags are cleared, so that the next boot
+/// only sees the next reset.
+pub fn take_reset_cause() -> ResetCause {
+    let rcu = unsafe { &*RCU::ptr() };
+    let cause = ResetCause::from_flags(rcu.rstsck.read().bits());
+    rcu.rstsck
+        .modify(|r, w| unsafe { w.bits(r.bits() | RSTSCK_RSTFC) });
+    cause
+}
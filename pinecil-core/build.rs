@@ -0,0 +1,236 @@
+//! Generates the glyphs of `large_font` from `fonts/6x10.bdf`, and the
+//! string tables of `lang` from `lang/*.txt`.
+//!
+//! For `large_font`, only the few characters the main screen needs are
+//! converted. They are cropped to the rows and columns which any of them
+//! use, doubled in size, and centred vertically in the 16 rows of the
+//! display. Each column becomes a `u16` with the top pixel in the least
+//! significant bit, like the pages of `display`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fmt::Write,
+    fs,
+    path::Path,
+};
+
+const FONT: &str = "fonts/6x10.bdf";
+const CHARS: &str = "0123456789-FO";
+const SCALE: usize = 2;
+const HEIGHT: usize = 16;
+
+/// The languages of `lang/`, by file name and the name of their variant of
+/// `Language`. The position of each language is stored in the settings, so
+/// new ones go at the end.
+const LANGUAGES: &[(&str, &str)] = &[("en", "English"), ("de", "German"), ("fr", "French")];
+
+/// Rows of a glyph, most significant bit on the left.
+struct Glyph {
+    rows: Vec<u32>,
+    width: usize,
+}
+
+fn parse_bdf(bdf: &str) -> HashMap<u32, Glyph> {
+    let mut glyphs = HashMap::new();
+    let mut lines = bdf.lines();
+    let mut encoding = None;
+    let mut width = 0;
+    while let Some(line) = lines.next() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("ENCODING") => encoding = words.next().and_then(|e| e.parse().ok()),
+            Some("BBX") => width = words.next().and_then(|w| w.parse().ok()).unwrap(),
+            Some("BITMAP") => {
+                let mut rows = Vec::new();
+                for line in &mut lines {
+                    if line == "ENDCHAR" {
+                        break;
+                    }
+                    // Each row is padded to whole bytes.
+                    let bits = line.len() * 4;
+                    let row = u32::from_str_radix(line, 16).unwrap();
+                    rows.push(row >> (bits - width));
+                }
+                if let Some(encoding) = encoding.take() {
+                    glyphs.insert(encoding, Glyph { rows, width });
+                }
+            }
+            _ => {}
+        }
+    }
+    glyphs
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    large_font();
+    lang();
+}
+
+fn large_font() {
+    println!("cargo:rerun-if-changed={}", FONT);
+
+    let bdf = fs::read_to_string(FONT).unwrap();
+    let glyphs = parse_bdf(&bdf);
+    let chars: Vec<&Glyph> = CHARS.chars().map(|c| &glyphs[&(c as u32)]).collect();
+
+    // The ink of all the characters, so that they line up with each other.
+    let width = chars[0].width;
+    let mut used_rows = Vec::new();
+    let mut used_columns = 0;
+    for glyph in &chars {
+        for (y, &row) in glyph.rows.iter().enumerate() {
+            if row != 0 {
+                used_rows.push(y);
+                used_columns |= row;
+            }
+        }
+    }
+    let top = *used_rows.iter().min().unwrap();
+    let bottom = *used_rows.iter().max().unwrap();
+    let left = used_columns.leading_zeros() as usize - (32 - width);
+    let right = width - 1 - used_columns.trailing_zeros() as usize;
+    let ink_height = (bottom - top + 1) * SCALE;
+    assert!(ink_height <= HEIGHT, "the glyphs are too tall");
+    let y_offset = (HEIGHT - ink_height) / 2;
+    let out_width = (right - left + 1) * SCALE;
+
+    let mut out = String::new();
+    writeln!(out, "/// Width of each glyph, in pixels.").unwrap();
+    writeln!(out, "pub const WIDTH: usize = {};", out_width).unwrap();
+    writeln!(out, "const CHARS: &str = {:?};", CHARS).unwrap();
+    writeln!(out, "const GLYPHS: [[u16; WIDTH]; {}] = [", chars.len()).unwrap();
+    for (c, glyph) in CHARS.chars().zip(&chars) {
+        let columns: Vec<String> = (0..out_width)
+            .map(|x| {
+                let bit = width - 1 - (left + x / SCALE);
+                let mut column = 0u16;
+                for y in 0..ink_height {
+                    if glyph.rows[top + y / SCALE] & (1 << bit) != 0 {
+                        column |= 1 << (y_offset + y);
+                    }
+                }
+                format!("0x{:04x}", column)
+            })
+            .collect();
+        writeln!(out, "    [{}], // {:?}", columns.join(", "), c).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("large_font.rs");
+    fs::write(path, out).unwrap();
+}
+
+/// Reads `lang/<code>.txt`: one `key = text` per line, with `#` starting a
+/// comment line.
+fn parse_lang(code: &str) -> Vec<(String, String)> {
+    let path = format!("lang/{}.txt", code);
+    println!("cargo:rerun-if-changed={}", path);
+    let file = fs::read_to_string(&path).unwrap();
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for (i, line) in file.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, text) = line
+            .split_once('=')
+            .unwrap_or_else(|| panic!("{}:{}: expected `key = text`", path, i + 1));
+        let key = key.trim();
+        if !seen.insert(key.to_owned()) {
+            panic!("{}:{}: `{}` is there twice", path, i + 1, key);
+        }
+        entries.push((key.to_owned(), text.trim().to_owned()));
+    }
+    entries
+}
+
+fn camel_case(key: &str) -> String {
+    key.split('_')
+        .map(|word| word[..1].to_uppercase() + &word[1..])
+        .collect()
+}
+
+/// English defines the keys. Every other language has to list all of them,
+/// but may leave the text empty to fall back to English.
+fn lang() {
+    let english = parse_lang(LANGUAGES[0].0);
+    let mut tables = Vec::new();
+    for &(code, _) in LANGUAGES {
+        let mut entries: HashMap<String, String> = parse_lang(code).into_iter().collect();
+        let mut table = Vec::new();
+        for (key, english_text) in &english {
+            let text = match entries.remove(key) {
+                Some(text) if text.is_empty() => english_text.clone(),
+                Some(text) => text,
+                None => panic!("lang/{}.txt: `{}` is missing", code, key),
+            };
+            if text.is_empty() {
+                panic!("lang/{}.txt: `{}` is empty", code, key);
+            }
+            table.push(text);
+        }
+        if let Some(key) = entries.keys().next() {
+            panic!("lang/{}.txt: `{}` is not in English", code, key);
+        }
+        tables.push(table);
+    }
+
+    let mut out = String::new();
+    writeln!(out, "/// The strings of the UI, see `lang/en.txt`.").unwrap();
+    writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq)]").unwrap();
+    writeln!(out, "pub enum Text {{").unwrap();
+    for (key, text) in &english {
+        writeln!(out, "    /// {:?}", text).unwrap();
+        writeln!(out, "    {},", camel_case(key)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq)]").unwrap();
+    writeln!(out, "pub enum Language {{").unwrap();
+    for (_, name) in LANGUAGES {
+        writeln!(out, "    {},", name).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out, "impl Default for Language {{").unwrap();
+    writeln!(out, "    fn default() -> Self {{").unwrap();
+    writeln!(out, "        Language::{}", LANGUAGES[0].1).unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out, "impl Language {{").unwrap();
+    writeln!(out, "    /// In the order they are stored in the settings.").unwrap();
+    writeln!(
+        out,
+        "    pub const ALL: [Language; {}] = [",
+        LANGUAGES.len()
+    )
+    .unwrap();
+    for (_, name) in LANGUAGES {
+        writeln!(out, "        Language::{},", name).unwrap();
+    }
+    writeln!(out, "    ];").unwrap();
+    writeln!(out, "}}").unwrap();
+    let codes: Vec<&str> = LANGUAGES.iter().map(|l| l.0).collect();
+    writeln!(
+        out,
+        "const CODES: [&str; {}] = {:?};",
+        LANGUAGES.len(),
+        codes
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "const TABLES: [[&str; {}]; {}] = [",
+        english.len(),
+        LANGUAGES.len()
+    )
+    .unwrap();
+    for table in &tables {
+        writeln!(out, "    {:?},", table).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("lang.rs");
+    fs::write(path, out).unwrap();
+}
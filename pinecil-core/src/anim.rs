@@ -0,0 +1,206 @@
+//! Compressed animations for the OLED.
+//!
+//! Frames are stored in the native layout of the SSD1306 display RAM, which
+//! [`display::draw_pages`](crate::display::draw_pages) draws: the display is
+//! split into pages of 8 rows, and each byte holds one column of a page with
+//! the top pixel in the least significant bit.
+//!
+//! File layout (all numbers little-endian):
+//!
+//! ```text
+//! "PANI"  magic
+//! u16     width
+//! u16     height, a multiple of 8
+//! u16     number of frames
+//! frames:
+//!     u8  kind: 0 = key frame, 1 = XOR against the previous frame
+//!     u16 delay in milliseconds
+//!     u16 length of the compressed data
+//!     ... compressed data
+//! ```
+//!
+//! The data of each frame is run-length encoded. A control byte `c` below
+//! 0x80 is followed by `c + 1` literal bytes, otherwise the next byte is
+//! repeated `(c & 0x7f) + 2` times. XOR frames mostly consist of zeroes, which
+//! compress very well.
+//!
+//! The first frame is always a key frame. The encoder lives in `imgtool`.
+
+pub const MAGIC: &[u8; 4] = b"PANI";
+pub const HEADER_LEN: usize = 10;
+pub const FRAME_HEADER_LEN: usize = 5;
+
+pub const MAX_LITERAL: usize = 0x80;
+pub const MIN_RUN: usize = 2;
+pub const MAX_RUN: usize = 0x7f + MIN_RUN;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    BadMagic,
+    BadSize,
+    Truncated,
+    /// The compressed data does not add up to exactly one frame.
+    BadData,
+    /// The first frame is not a key frame.
+    NoKeyFrame,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    Key,
+    Xor,
+}
+
+impl FrameKind {
+    pub fn to_byte(self) -> u8 {
+        match self {
+            FrameKind::Key => 0,
+            FrameKind::Xor => 1,
+        }
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, Error> {
+    match data.get(offset..offset + 2) {
+        Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+        None => Err(Error::Truncated),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Animation<'a> {
+    width: u16,
+    height: u16,
+    frame_count: u16,
+    frames: &'a [u8],
+}
+
+impl<'a> Animation<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if data.get(0..4) != Some(&MAGIC[..]) {
+            return Err(Error::BadMagic);
+        }
+        let anim = Animation {
+            width: u16_at(data, 4)?,
+            height: u16_at(data, 6)?,
+            frame_count: u16_at(data, 8)?,
+            frames: &data[HEADER_LEN..],
+        };
+        if anim.width == 0 || anim.height == 0 || anim.height % 8 != 0 {
+            return Err(Error::BadSize);
+        }
+        match anim.frames().next() {
+            Some(Ok(frame)) if frame.kind == FrameKind::Key => Ok(anim),
+            Some(Ok(_)) => Err(Error::NoKeyFrame),
+            Some(Err(e)) => Err(e),
+            None => Err(Error::Truncated),
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn frame_count(&self) -> u16 {
+        self.frame_count
+    }
+
+    /// Size of the decoded frame buffer in bytes.
+    pub fn frame_len(&self) -> usize {
+        self.width as usize * self.height as usize / 8
+    }
+
+    pub fn frames(&self) -> Frames<'a> {
+        Frames {
+            remaining: self.frame_count,
+            data: self.frames,
+        }
+    }
+}
+
+pub struct Frames<'a> {
+    remaining: u16,
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Result<Frame<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(self.next_frame())
+    }
+}
+
+impl<'a> Frames<'a> {
+    fn next_frame(&mut self) -> Result<Frame<'a>, Error> {
+        let kind = match self.data.first() {
+            Some(0) => FrameKind::Key,
+            Some(1) => FrameKind::Xor,
+            Some(_) => return Err(Error::BadData),
+            None => return Err(Error::Truncated),
+        };
+        let delay_ms = u16_at(self.data, 1)?;
+        let len = u16_at(self.data, 3)? as usize;
+        let end = FRAME_HEADER_LEN + len;
+        let data = self
+            .data
+            .get(FRAME_HEADER_LEN..end)
+            .ok_or(Error::Truncated)?;
+        self.data = &self.data[end..];
+        Ok(Frame {
+            kind,
+            delay_ms,
+            data,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Frame<'a> {
+    pub kind: FrameKind,
+    pub delay_ms: u16,
+    data: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Decodes the frame into `buf`, which must still hold the previous frame
+    /// for XOR frames.
+    pub fn decode_into(&self, buf: &mut [u8]) -> Result<(), Error> {
+        let mut out = 0;
+        let mut emit = |b: u8| -> Result<(), Error> {
+            let dst = buf.get_mut(out).ok_or(Error::BadData)?;
+            match self.kind {
+                FrameKind::Key => *dst = b,
+                FrameKind::Xor => *dst ^= b,
+            }
+            out += 1;
+            Ok(())
+        };
+
+        let mut data = self.data.iter();
+        while let Some(&c) = data.next() {
+            if (c as usize) < MAX_LITERAL {
+                for _ in 0..=c {
+                    emit(*data.next().ok_or(Error::Truncated)?)?;
+                }
+            } else {
+                let b = *data.next().ok_or(Error::Truncated)?;
+                for _ in 0..(c & 0x7f) as usize + MIN_RUN {
+                    emit(b)?;
+                }
+            }
+        }
+        if out != buf.len() {
+            return Err(Error::BadData);
+        }
+        Ok(())
+    }
+}
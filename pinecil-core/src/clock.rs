@@ -0,0 +1,73 @@
+//! The core clock, worked out from the clock configuration registers of the
+//! RCU, for code which runs without the HAL and its `Clocks`.
+//!
+//! Only the parts of the clock tree which lead to the core are decoded: the
+//! system clock source, the PLL with its prescaler and the AHB prescaler.
+//! The Pinecil's crystal is 8 MHz, so [`HXTAL_HZ`] is assumed.
+
+use core::convert::TryFrom;
+
+/// The internal RC oscillator, which the chip runs from after reset.
+pub const IRC8M_HZ: u32 = 8_000_000;
+/// The crystal on the Pinecil.
+pub const HXTAL_HZ: u32 = 8_000_000;
+
+/// The `bits` wide field of `value` at bit `shift`.
+fn field(value: u32, shift: u32, bits: u32) -> u32 {
+    (value >> shift) & ((1 << bits) - 1)
+}
+
+/// Returns the core clock for the values of `RCU_CFG0` and `RCU_CFG1`, or
+/// `None` for a setting the manual calls reserved.
+pub fn core_hz(cfg0: u32, cfg1: u32) -> Option<u32> {
+    // The source actually in use (SCSS), rather than the one asked for.
+    let sys_hz = match field(cfg0, 2, 2) {
+        0b00 => IRC8M_HZ as u64,
+        0b01 => HXTAL_HZ as u64,
+        0b10 => pll_hz(cfg0, cfg1)?,
+        _ => return None,
+    };
+    let ahb_shift = match field(cfg0, 4, 4) {
+        0b0000..=0b0111 => 0,
+        0b1000 => 1,
+        0b1001 => 2,
+        0b1010 => 3,
+        0b1011 => 4,
+        // There is no /32.
+        psc => psc - 0b1100 + 6,
+    };
+    u32::try_from(sys_hz >> ahb_shift).ok()
+}
+
+/// The PLL output, in Hz. Kept as a `u64` as the PLL can be set well beyond
+/// what the chip can run at.
+fn pll_hz(cfg0: u32, cfg1: u32) -> Option<u64> {
+    // PLLSEL: IRC8M / 2, or the PREDV0 output.
+    let source_hz = if field(cfg0, 16, 1) == 0 {
+        IRC8M_HZ as u64 / 2
+    } else {
+        // PREDV0SEL: the crystal, or PLL1.
+        let predv0_in = if field(cfg1, 16, 1) == 0 {
+            HXTAL_HZ as u64
+        } else {
+            let predv1 = field(cfg1, 4, 4) as u64 + 1;
+            let pll1mf = match field(cfg1, 8, 4) {
+                mf @ 0b0110..=0b1110 => mf as u64 + 2,
+                0b1111 => 20,
+                _ => return None,
+            };
+            HXTAL_HZ as u64 / predv1 * pll1mf
+        };
+        predv0_in / (field(cfg1, 0, 4) as u64 + 1)
+    };
+
+    // PLLMF is split over two fields, and counts in halves for x6.5.
+    let pllmf = field(cfg0, 29, 1) << 4 | field(cfg0, 18, 4);
+    let halves = match pllmf {
+        0b00000..=0b01100 => (pllmf + 2) * 2,
+        0b01101 => 13,
+        0b01110 | 0b01111 => 32,
+        _ => (pllmf + 1) * 2,
+    };
+    Some(source_hz * halves as u64 / 2)
+}
@@ -0,0 +1,253 @@
+//! How long the iron has been on, how long it has been heating and how many
+//! times it started heating, over its whole life.
+//!
+//! The board counts with the RTC seconds through a [`Tracker`]. It keeps the
+//! counters in the backup registers as they change, which survive a reset
+//! but not unplugging the iron, and saves them to the settings page of the
+//! flash every [`SAVE_INTERVAL_S`], which survives both. At boot, the newer
+//! of the two is used, see [`Counters::latest`].
+//!
+//! In the settings page, they are stored after the settings, from [`OFFSET`]
+//! to the end of the page. Each save is a new record in the next of the
+//! [`SLOTS`] which is still erased, so that the page is only erased once all
+//! of them are used, and the last good record is the one which counts (see
+//! [`newest`] and [`next_slot`]). A record is:
+//!
+//! ```text
+//! 0   magic, "PCCT"
+//! 4   uptime in seconds (u32)
+//! 8   heating time in seconds (u32)
+//! 12  heat cycles (u32)
+//! 16  CRC-32 of the bytes above
+//! ```
+//!
+//! In the backup registers, which are 16 bits each, they are stored as
+//! [`BACKUP_WORDS`] half-words:
+//!
+//! ```text
+//! 0   magic, "CT"
+//! 1   uptime, low then high half
+//! 3   heating time, low then high half
+//! 5   heat cycles, low then high half
+//! 7   the low half of the CRC-32 of the half-words above
+//! ```
+//!
+//! All values are little-endian. The RTC runs from the IRC40K, which is only
+//! good to a few percent, and so are the times.
+
+use core::{convert::TryInto, fmt};
+
+use crate::image::{crc32, FLASH_PAGE_SIZE};
+
+/// Where the records of the counters start in the settings page.
+pub const OFFSET: usize = 32;
+/// Length of one record in the flash, including the CRC.
+pub const LEN: usize = 20;
+/// Number of records which fit in the rest of the settings page.
+pub const SLOTS: usize = (FLASH_PAGE_SIZE as usize - OFFSET) / LEN;
+/// Number of backup registers the counters take.
+pub const BACKUP_WORDS: usize = 8;
+
+/// How often to save the counters to the flash, as they change. The flash
+/// lasts for 10,000 erases, and the page is erased every [`SLOTS`] saves,
+/// which is over 100,000 hours of use at this rate.
+pub const SAVE_INTERVAL_S: u32 = 15 * 60;
+
+/// "PCCT" in little-endian.
+const MAGIC: u32 = 0x5443_4350;
+/// "CT" in little-endian.
+const BACKUP_MAGIC: u16 = 0x5443;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// Seconds the iron has been on.
+    pub uptime_s: u32,
+    /// Seconds the iron has been heating, including standby.
+    pub heating_s: u32,
+    /// Number of times the iron started heating.
+    pub heat_cycles: u32,
+}
+
+impl Counters {
+    pub fn to_bytes(&self) -> [u8; LEN] {
+        let mut buf = [0; LEN];
+        let values = [MAGIC, self.uptime_s, self.heating_s, self.heat_cycles];
+        for (chunk, value) in buf[0..16].chunks_exact_mut(4).zip(&values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        let crc = crc32(&buf[0..16]);
+        buf[16..20].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    /// The stored counters, `None` for an erased page or a corrupted one.
+    pub fn parse(bytes: &[u8; LEN]) -> Option<Self> {
+        let word =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        if word(0) != MAGIC || word(16) != crc32(&bytes[0..16]) {
+            return None;
+        }
+        Some(Counters {
+            uptime_s: word(4),
+            heating_s: word(8),
+            heat_cycles: word(12),
+        })
+    }
+
+    pub fn to_backup(&self) -> [u16; BACKUP_WORDS] {
+        let mut words = [0; BACKUP_WORDS];
+        words[0] = BACKUP_MAGIC;
+        let values = [self.uptime_s, self.heating_s, self.heat_cycles];
+        for (pair, &value) in words[1..7].chunks_exact_mut(2).zip(&values) {
+            pair[0] = value as u16;
+            pair[1] = (value >> 16) as u16;
+        }
+        words[7] = backup_crc(&words);
+        words
+    }
+
+    /// The counters in the backup registers, `None` after the iron was
+    /// unplugged.
+    pub fn from_backup(words: &[u16; BACKUP_WORDS]) -> Option<Self> {
+        if words[0] != BACKUP_MAGIC || words[7] != backup_crc(words) {
+            return None;
+        }
+        let word = |i: usize| words[i] as u32 | (words[i + 1] as u32) << 16;
+        Some(Counters {
+            uptime_s: word(1),
+            heating_s: word(3),
+            heat_cycles: word(5),
+        })
+    }
+
+    /// The newer of the counters saved in the flash and those kept in the
+    /// backup registers, which is the one with the longer uptime. Zeros if
+    /// there are neither.
+    pub fn latest(flash: Option<Counters>, backup: Option<Counters>) -> Counters {
+        match (flash, backup) {
+            (Some(flash), Some(backup)) if flash.uptime_s > backup.uptime_s => flash,
+            (_, Some(backup)) => backup,
+            (flash, None) => flash.unwrap_or_default(),
+        }
+    }
+}
+
+/// The newest of the records in `records`, the part of the settings page from
+/// [`OFFSET`] on. A record cut short by a reset is skipped.
+pub fn newest(records: &[u8]) -> Option<Counters> {
+    records
+        .chunks_exact(LEN)
+        .rev()
+        .find_map(|record| Counters::parse(record.try_into().unwrap()))
+}
+
+/// The slot in `records` to write the next record to, which is the one after
+/// the last slot in use. `None` once they are all used, and the page has to
+/// be erased.
+pub fn next_slot(records: &[u8]) -> Option<usize> {
+    let mut slots = records.chunks_exact(LEN);
+    let count = slots.len();
+    let used = slots
+        .rposition(|record| record.iter().any(|&b| b != 0xff))
+        .map_or(0, |i| i + 1);
+    if used < count {
+        Some(used)
+    } else {
+        None
+    }
+}
+
+/// The low half of the CRC-32 of the half-words before it.
+fn backup_crc(words: &[u16; BACKUP_WORDS]) -> u16 {
+    let mut bytes = [0; (BACKUP_WORDS - 1) * 2];
+    for (chunk, word) in bytes.chunks_exact_mut(2).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    crc32(&bytes) as u16
+}
+
+/// Counts the seconds of the RTC into the [`Counters`].
+#[derive(Clone, Debug)]
+pub struct Tracker {
+    counters: Counters,
+    /// The counters as they were last saved to the flash.
+    saved: Counters,
+    last_s: u32,
+    saved_at_s: u32,
+    heating: bool,
+}
+
+impl Tracker {
+    /// Carries on from `counters`, as they were loaded at `now_s`.
+    pub fn new(counters: Counters, now_s: u32) -> Self {
+        Tracker {
+            counters,
+            saved: counters,
+            last_s: now_s,
+            saved_at_s: now_s,
+            heating: false,
+        }
+    }
+
+    pub fn counters(&self) -> Counters {
+        self.counters
+    }
+
+    /// Adds the seconds since the last update to the uptime, and to the
+    /// heating time if the iron was heating then. Heating again after not
+    /// heating is a heat cycle. Returns whether the counters changed.
+    pub fn update(&mut self, now_s: u32, heating: bool) -> bool {
+        let before = self.counters;
+        let elapsed_s = now_s.wrapping_sub(self.last_s);
+        self.last_s = now_s;
+        let c = &mut self.counters;
+        c.uptime_s = c.uptime_s.saturating_add(elapsed_s);
+        if self.heating {
+            c.heating_s = c.heating_s.saturating_add(elapsed_s);
+        }
+        if heating && !self.heating {
+            c.heat_cycles = c.heat_cycles.saturating_add(1);
+        }
+        self.heating = heating;
+        self.counters != before
+    }
+
+    /// Whether the counters changed since they were last saved to the
+    /// flash.
+    pub fn unsaved(&self) -> bool {
+        self.counters != self.saved
+    }
+
+    /// Whether it is time to save the counters to the flash.
+    pub fn save_due(&self, now_s: u32) -> bool {
+        self.unsaved() && now_s.wrapping_sub(self.saved_at_s) >= SAVE_INTERVAL_S
+    }
+
+    /// Notes that the counters were saved to the flash at `now_s`.
+    pub fn saved(&mut self, now_s: u32) {
+        self.saved = self.counters;
+        self.saved_at_s = now_s;
+    }
+}
+
+/// Seconds shown as hours and minutes, like "12h 05m".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hours(pub u32);
+
+impl fmt::Display for Hours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}h {:02}m", self.0 / 3600, self.0 / 60 % 60)
+    }
+}
+
+impl fmt::Display for Counters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "up {}, heating {}, {} heat cycles",
+            Hours(self.uptime_s),
+            Hours(self.heating_s),
+            self.heat_cycles
+        )
+    }
+}
@@ -0,0 +1,136 @@
+//! What the trap handler keeps of a crash, for the next boot to report.
+//!
+//! The record is kept in RAM which the startup code leaves alone, so it
+//! survives a reset but not a power cycle. It is stored as words:
+//!
+//! ```text
+//! 0   magic, "CRSH"
+//! 1   mcause
+//! 2   mepc, where it happened
+//! 3   mtval, the bad address or instruction
+//! 4   sp
+//! 5   ra
+//! 6   1 if the stack had overflowed, else 0
+//! 7   CRC-32 of the words above
+//! ```
+//!
+//! Whatever RAM held after a power cycle reads as no record.
+
+use core::fmt;
+
+use crate::image::crc32;
+
+/// "CRSH" in little-endian.
+const MAGIC: u32 = 0x4853_5243;
+/// Length of the stored record, in words.
+pub const WORDS: usize = 8;
+
+/// Bits of `mcause` with the exception code. The Bumblebee core keeps more
+/// state in the bits above.
+const CODE_MASK: u32 = 0xfff;
+const INTERRUPT: u32 = 1 << 31;
+/// Code of the non-maskable interrupt, which the Bumblebee core reports as
+/// an exception.
+const NMI: u32 = 0xfff;
+
+/// The exceptions of the RISC-V privileged spec, by code.
+const EXCEPTIONS: [&str; 12] = [
+    "instruction address misaligned",
+    "instruction access fault",
+    "illegal instruction",
+    "breakpoint",
+    "load address misaligned",
+    "load access fault",
+    "store address misaligned",
+    "store access fault",
+    "ecall from U-mode",
+    "ecall from S-mode",
+    "reserved exception",
+    "ecall from M-mode",
+];
+
+/// The name of what `mcause` says happened.
+pub fn cause_name(mcause: u32) -> &'static str {
+    let code = mcause & CODE_MASK;
+    if mcause & INTERRUPT != 0 {
+        "interrupt"
+    } else if code == NMI {
+        "NMI"
+    } else {
+        EXCEPTIONS
+            .get(code as usize)
+            .copied()
+            .unwrap_or("unknown exception")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrashRecord {
+    pub mcause: u32,
+    pub mepc: u32,
+    pub mtval: u32,
+    pub sp: u32,
+    pub ra: u32,
+    /// The stack had gone past its end, see `stack`. Whatever happened
+    /// after that is likely to be a consequence.
+    pub stack_overflow: bool,
+}
+
+impl CrashRecord {
+    pub fn to_words(&self) -> [u32; WORDS] {
+        let mut words = [
+            MAGIC,
+            self.mcause,
+            self.mepc,
+            self.mtval,
+            self.sp,
+            self.ra,
+            self.stack_overflow as u32,
+            0,
+        ];
+        words[WORDS - 1] = checksum(&words);
+        words
+    }
+
+    /// Reads a stored record, if there is one.
+    pub fn parse(words: &[u32; WORDS]) -> Option<Self> {
+        if words[0] != MAGIC || words[6] > 1 || words[WORDS - 1] != checksum(words) {
+            return None;
+        }
+        Some(CrashRecord {
+            mcause: words[1],
+            mepc: words[2],
+            mtval: words[3],
+            sp: words[4],
+            ra: words[5],
+            stack_overflow: words[6] == 1,
+        })
+    }
+}
+
+fn checksum(words: &[u32; WORDS]) -> u32 {
+    let mut bytes = [0; (WORDS - 1) * 4];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    crc32(&bytes)
+}
+
+/// One line, as printed over the UART.
+impl fmt::Display for CrashRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.stack_overflow {
+            write!(f, "stack overflow, then ")?;
+        }
+        write!(
+            f,
+            "{} (mcause {:#010x}) at {:#010x}, mtval {:#010x}, sp {:#010x}, ra {:#010x}",
+            cause_name(self.mcause),
+            self.mcause,
+            self.mepc,
+            self.mtval,
+            self.sp,
+            self.ra,
+        )
+    }
+}
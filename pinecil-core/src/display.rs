@@ -0,0 +1,134 @@
+//! Drawing on the 96x16 SSD1306 OLED through `embedded-graphics`.
+//!
+//! The UIs draw on any [`DrawTarget`], which on the Pinecil is the
+//! `GraphicsMode` of the `ssd1306` driver, and on the host the simulator in
+//! `tools/`. The helpers here only light pixels, on top of a cleared target.
+//!
+//! [`FrameBuffer`] uses the native layout of the display RAM (see `anim`):
+//! two pages of 8 rows, one byte per column with the top pixel in the least
+//! significant bit. The simulator keeps what was flushed to it in one, and
+//! the tests look at the pixels in it.
+
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    drawable::Pixel,
+    geometry::{Point, Size},
+    pixelcolor::BinaryColor,
+    DrawTarget,
+};
+
+pub const WIDTH: usize = 96;
+pub const HEIGHT: usize = 16;
+pub const BUF_LEN: usize = WIDTH * HEIGHT / 8;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct FrameBuffer {
+    buf: [u8; BUF_LEN],
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameBuffer {
+    pub const fn new() -> Self {
+        FrameBuffer { buf: [0; BUF_LEN] }
+    }
+
+    pub fn clear(&mut self) {
+        self.buf = [0; BUF_LEN];
+    }
+
+    pub fn as_bytes(&self) -> &[u8; BUF_LEN] {
+        &self.buf
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8; BUF_LEN] {
+        &mut self.buf
+    }
+
+    /// Returns whether the pixel is lit. Pixels outside of the display are
+    /// never lit.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        x < WIDTH && y < HEIGHT && self.buf[y / 8 * WIDTH + x] & (1 << (y % 8)) != 0
+    }
+
+    /// Sets a pixel. Pixels outside of the display are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        if x >= WIDTH || y >= HEIGHT {
+            return;
+        }
+        let byte = &mut self.buf[y / 8 * WIDTH + x];
+        if on {
+            *byte |= 1 << (y % 8);
+        } else {
+            *byte &= !(1 << (y % 8));
+        }
+    }
+}
+
+impl DrawTarget<BinaryColor> for FrameBuffer {
+    type Error = Infallible;
+
+    /// Pixels outside of the display are ignored.
+    fn draw_pixel(&mut self, Pixel(point, color): Pixel<BinaryColor>) -> Result<(), Self::Error> {
+        if point.x >= 0 && point.y >= 0 {
+            self.set_pixel(point.x as usize, point.y as usize, color == BinaryColor::On);
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+/// Lights the pixels at `points`, skipping those outside of the target, so
+/// that text running off the edge can't end up elsewhere on targets which
+/// don't clip by themselves.
+pub fn draw_lit<D, I>(target: &mut D, points: I) -> Result<(), D::Error>
+where
+    D: DrawTarget<BinaryColor>,
+    I: IntoIterator<Item = Point>,
+{
+    let size = target.size();
+    let pixels = points
+        .into_iter()
+        .filter(|p| p.x >= 0 && p.y >= 0 && (p.x as u32) < size.width && (p.y as u32) < size.height)
+        .map(|p| Pixel(p, BinaryColor::On));
+    target.draw_iter(pixels)
+}
+
+/// Draws columns of up to 16 pixels from `origin` to the right, each with
+/// its top pixel in the least significant bit. This is the layout of the
+/// pages of the display RAM, the icons and the large font.
+pub fn draw_columns<D, I>(target: &mut D, origin: Point, columns: I) -> Result<(), D::Error>
+where
+    D: DrawTarget<BinaryColor>,
+    I: IntoIterator<Item = u16>,
+{
+    let points = columns
+        .into_iter()
+        .enumerate()
+        .flat_map(move |(x, column)| {
+            (0..16)
+                .filter(move |y| column & (1 << y) != 0)
+                .map(move |y| origin + Point::new(x as i32, y))
+        });
+    draw_lit(target, points)
+}
+
+/// Draws a whole screen in the layout of the display RAM.
+pub fn draw_pages<D>(target: &mut D, pages: &[u8; BUF_LEN]) -> Result<(), D::Error>
+where
+    D: DrawTarget<BinaryColor>,
+{
+    for (page, columns) in pages.chunks_exact(WIDTH).enumerate() {
+        let origin = Point::new(0, page as i32 * 8);
+        draw_columns(target, origin, columns.iter().map(|&column| column as u16))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! A minimal executor for running a fixed set of `async` tasks on one core,
+//! without an allocator.
+//!
+//! The tasks are futures pinned for as long as the firmware runs, usually on
+//! the stack of `main`, and handed to [`Executor::poll`] as a slice. Each
+//! task has a flag in the executor which its waker sets, and `poll` only
+//! polls the tasks whose flag is set. The executor itself never waits: the
+//! board sleeps until an interrupt while [`Executor::is_woken`] is false,
+//! and the interrupt handlers wake the tasks waiting for them (see
+//! `pinecil_bsp::executor`).
+//!
+//! ```ignore
+//! static EXECUTOR: Executor<2> = Executor::new();
+//!
+//! let (blink, echo) = (blink(led), echo(uart));
+//! pin_mut!(blink, echo);
+//! let mut tasks: [Option<Task>; 2] = [Some(blink), Some(echo)];
+//! while EXECUTOR.poll(&mut tasks) {
+//!     sleep_unless(|| EXECUTOR.is_woken());
+//! }
+//! ```
+
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// A task, pinned where it lives. It is taken out of its slot once it has
+/// finished.
+pub type Task<'a> = Pin<&'a mut dyn Future<Output = ()>>;
+
+/// Runs up to `N` tasks. It has to be a `static`, as the wakers point into
+/// it and may be kept by interrupt handlers for as long as the firmware
+/// runs.
+pub struct Executor<const N: usize> {
+    woken: [AtomicBool; N],
+}
+
+// Each task starts out woken, so that it is polled once to get going.
+#[allow(clippy::declare_interior_mutable_const)]
+const WOKEN: AtomicBool = AtomicBool::new(true);
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_waker, wake, wake, drop_waker);
+
+unsafe fn clone_waker(flag: *const ()) -> RawWaker {
+    RawWaker::new(flag, &VTABLE)
+}
+
+unsafe fn wake(flag: *const ()) {
+    (*(flag as *const AtomicBool)).store(true, Ordering::Release);
+}
+
+unsafe fn drop_waker(_: *const ()) {}
+
+impl<const N: usize> Executor<N> {
+    pub const fn new() -> Self {
+        Executor { woken: [WOKEN; N] }
+    }
+
+    /// Polls each task which was woken since it was last polled, once, and
+    /// returns whether any task has yet to finish. A task woken while it is
+    /// being polled is polled again on the next call.
+    ///
+    /// # Panics
+    ///
+    /// If there are more than `N` tasks.
+    pub fn poll(&'static self, tasks: &mut [Option<Task<'_>>]) -> bool {
+        assert!(
+            tasks.len() <= N,
+            "more tasks than the executor has room for"
+        );
+        for (i, woken) in self.woken.iter().enumerate() {
+            // Finished tasks and spare room are never woken for good.
+            let task = match tasks.get_mut(i) {
+                Some(task) => task,
+                None => {
+                    woken.store(false, Ordering::Relaxed);
+                    continue;
+                }
+            };
+            let future = match task {
+                Some(future) => future,
+                None => {
+                    woken.store(false, Ordering::Relaxed);
+                    continue;
+                }
+            };
+            if !woken.swap(false, Ordering::Acquire) {
+                continue;
+            }
+            let raw = RawWaker::new(woken as *const AtomicBool as *const (), &VTABLE);
+            let waker = unsafe { Waker::from_raw(raw) };
+            if let Poll::Ready(()) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                *task = None;
+            }
+        }
+        tasks.iter().any(Option::is_some)
+    }
+
+    /// Whether any task was woken since it was last polled, i.e. whether
+    /// [`poll`](Self::poll) has anything to do.
+    pub fn is_woken(&self) -> bool {
+        self.woken.iter().any(|woken| woken.load(Ordering::Acquire))
+    }
+}
+
+impl<const N: usize> Default for Executor<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pins the futures in each of the local variables given on the stack, for
+/// a [`Task`]. This stands in for `core::pin::pin!`, which is newer than the
+/// toolchain of the firmware.
+#[macro_export]
+macro_rules! pin_mut {
+    ($($name:ident),* $(,)?) => {
+        $(
+            let mut $name = $name;
+            // Shadowed, so that it can't be moved any more.
+            let $name = unsafe { ::core::pin::Pin::new_unchecked(&mut $name) };
+        )*
+    };
+}
+
+/// A future which calls `f` each time it is polled, which is how the futures
+/// waiting for an interrupt are written. This stands in for
+/// `core::future::poll_fn`, which is newer than the toolchain of the
+/// firmware.
+pub fn poll_fn<T, F>(f: F) -> PollFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T>,
+{
+    PollFn { f }
+}
+
+/// See [`poll_fn`].
+pub struct PollFn<F> {
+    f: F,
+}
+
+// The closure is never pinned.
+impl<F> Unpin for PollFn<F> {}
+
+impl<T, F> Future for PollFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T>,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        (self.f)(cx)
+    }
+}
+
+/// Returns `Pending` once, waking the task straight away, so that the other
+/// tasks get a turn during a long computation.
+pub async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
@@ -0,0 +1,178 @@
+//! Application image layout used by the resident bootloader.
+//!
+//! The flash is split into three parts:
+//!
+//! ```text
+//! 0x0800_0000 +------------------+
+//!             | bootloader       |  BOOTLOADER_SIZE
+//! 0x0800_4000 +------------------+
+//!             | image header     |  HEADER_SIZE, padded with 0xFF
+//! 0x0800_4100 +------------------+
+//!             | application      |  linked with `_stext = APP_ENTRY`
+//!             |                  |
+//! 0x0801_fc00 +------------------+
+//!             | settings         |  one page, see `settings`
+//! 0x0802_0000 +------------------+
+//! ```
+//!
+//! The header records the size and the CRC-32 of the application so that the
+//! bootloader can refuse to jump into a half-written image.
+
+pub const FLASH_BASE: u32 = 0x0800_0000;
+pub const FLASH_SIZE: u32 = 128 * 1024;
+pub const FLASH_PAGE_SIZE: u32 = 1024;
+
+/// Flash reserved for the bootloader. Keep in sync with `bootloader/bootloader.x`.
+pub const BOOTLOADER_SIZE: u32 = 16 * 1024;
+
+/// Start of the application region, where the image header lives.
+pub const APP_BASE: u32 = FLASH_BASE + BOOTLOADER_SIZE;
+pub const HEADER_SIZE: u32 = 0x100;
+/// Address of the application reset handler (`_stext` of the application).
+pub const APP_ENTRY: u32 = APP_BASE + HEADER_SIZE;
+pub const APP_MAX_SIZE: u32 = SETTINGS_ADDR - APP_ENTRY;
+
+/// The last page of the flash, which keeps the user settings across firmware
+/// updates. Keep in sync with `memory.x`.
+pub const SETTINGS_ADDR: u32 = FLASH_BASE + FLASH_SIZE - FLASH_PAGE_SIZE;
+
+/// "PCLA" in little-endian.
+pub const HEADER_MAGIC: u32 = 0x414c_4350;
+/// Number of meaningful bytes at the start of the header.
+pub const HEADER_LEN: usize = 16;
+
+/// Word at the top of RAM which the application sets before resetting to ask
+/// the bootloader to stay in DFU mode. The bootloader keeps its stack below it.
+pub const DFU_REQUEST_ADDR: u32 = 0x2000_7ffc;
+pub const DFU_REQUEST_MAGIC: u32 = 0xb007_df00;
+
+/// USB VID/PID of the resident bootloader, the same as the ROM bootloader so
+/// that existing tools recognise it. Also written to the DFU file suffix.
+pub const DFU_VID: u16 = 0x28e9;
+pub const DFU_PID: u16 = 0x0189;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageHeader {
+    /// Size of the application in bytes, not including the header.
+    pub size: u32,
+    /// CRC-32 of the application.
+    pub crc: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderError {
+    BadMagic,
+    BadHeaderCrc,
+    BadSize,
+    BadCrc,
+}
+
+impl ImageHeader {
+    pub fn for_app(app: &[u8]) -> Self {
+        Self {
+            size: app.len() as u32,
+            crc: crc32(app),
+        }
+    }
+
+    /// Serializes the header. The remaining `HEADER_SIZE - HEADER_LEN` bytes
+    /// are expected to be left erased (0xFF).
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut buf = [0; HEADER_LEN];
+        buf[0..4].copy_from_slice(&HEADER_MAGIC.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.size.to_le_bytes());
+        buf[8..12].copy_from_slice(&self.crc.to_le_bytes());
+        let header_crc = crc32(&buf[0..12]);
+        buf[12..16].copy_from_slice(&header_crc.to_le_bytes());
+        buf
+    }
+
+    /// Parses and sanity-checks a header, without looking at the application.
+    pub fn parse(bytes: &[u8]) -> Result<Self, HeaderError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(HeaderError::BadSize);
+        }
+        let word = |i: usize| {
+            let mut w = [0; 4];
+            w.copy_from_slice(&bytes[i..i + 4]);
+            u32::from_le_bytes(w)
+        };
+        if word(0) != HEADER_MAGIC {
+            return Err(HeaderError::BadMagic);
+        }
+        if word(12) != crc32(&bytes[0..12]) {
+            return Err(HeaderError::BadHeaderCrc);
+        }
+        let header = Self {
+            size: word(4),
+            crc: word(8),
+        };
+        if header.size == 0 || header.size > APP_MAX_SIZE {
+            return Err(HeaderError::BadSize);
+        }
+        Ok(header)
+    }
+
+    /// Checks the CRC of the application. `app` may be longer than the image.
+    pub fn verify(&self, app: &[u8]) -> Result<(), HeaderError> {
+        let app = app.get(..self.size as usize).ok_or(HeaderError::BadSize)?;
+        if crc32(app) != self.crc {
+            return Err(HeaderError::BadCrc);
+        }
+        Ok(())
+    }
+}
+
+/// Checks the image currently in flash.
+///
+/// # Safety
+///
+/// Must only be called on the target, as it reads the flash directly.
+pub unsafe fn check_flash_image() -> Result<ImageHeader, HeaderError> {
+    let header = core::slice::from_raw_parts(APP_BASE as *const u8, HEADER_LEN);
+    let header = ImageHeader::parse(header)?;
+    let app = core::slice::from_raw_parts(APP_ENTRY as *const u8, header.size as usize);
+    header.verify(app)?;
+    Ok(header)
+}
+
+/// Asks the bootloader to stay in DFU mode and resets the chip.
+///
+/// # Safety
+///
+/// Must only be called on the target. Clobbers the top word of RAM.
+pub unsafe fn reboot_into_dfu() -> ! {
+    core::ptr::write_volatile(DFU_REQUEST_ADDR as *mut u32, DFU_REQUEST_MAGIC);
+    software_reset()
+}
+
+/// Resets the chip through the MSFTRST register of the Nuclei core timer.
+///
+/// # Safety
+///
+/// Must only be called on the target.
+pub unsafe fn software_reset() -> ! {
+    const TIMER_MSFTRST: *mut u32 = (0xd100_0000_usize + 0xff0) as *mut u32;
+    const TIMER_MSFTRST_KEY: u32 = 0x8000_0a5f;
+    core::ptr::write_volatile(TIMER_MSFTRST, TIMER_MSFTRST_KEY);
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+/// CRC-32 (IEEE 802.3), as used by zlib and the DFU file suffix.
+pub fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Feeds `data` into a running CRC-32 without the initial and final inversion.
+pub fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    crc
+}
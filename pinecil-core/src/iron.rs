@@ -0,0 +1,543 @@
+//! The soldering UI: the target temperature, boost, presets and the settings
+//! menu, all on the two buttons.
+//!
+//! On the main screen:
+//!
+//! - Holding '+' boosts to [`Settings::boost_temp`] until it is let go.
+//! - Pressing '+' or '-' once opens the temperature adjustment.
+//! - Pressing '-' twice in a row switches to the next preset.
+//! - Holding '-' opens the settings menu.
+//! - Holding '+' while off opens the diagnostics.
+//!
+//! While adjusting, each press of '+' or '-' changes the temperature by
+//! [`Settings::step`]. Holding a button repeats the press, and after a while
+//! jumps by [`Settings::fast_step`] instead. The main screen comes back once
+//! the buttons have been left alone for a moment.
+//!
+//! In the settings menu, '-' goes to the next setting and '+' changes it,
+//! with the same repeat as above. Leaving the menu after the last setting (or
+//! after leaving it alone) hands the new settings to the board, see
+//! [`IronUi::take_changed_settings`].
+//!
+//! The diagnostics show the [`counters`](crate::counters) the board hands
+//! over with [`IronUi::set_counters`], one per page. '-' goes to the next
+//! page and leaves after the last one.
+//!
+//! Like [`ui`](crate::ui), the board calls [`IronUi::update`] every
+//! [`TICK_MS`](crate::ui::TICK_MS) and redraws when it returns `true`.
+
+use core::fmt::Write;
+
+use embedded_graphics::{geometry::Point, pixelcolor::BinaryColor, DrawTarget};
+
+use crate::{
+    counters::{Counters, Hours},
+    display::{HEIGHT, WIDTH},
+    lang::Text,
+    settings::{Settings, FAST_STEPS, MAX_TEMP, MIN_TEMP, PRESETS, STEPS},
+    sleep,
+    text::{self, Font},
+    ui::{Buttons, Line},
+};
+
+mod main_screen;
+
+/// How long '+' has to be held to boost, rather than to adjust.
+const BOOST_DELAY_MS: u32 = 400;
+/// Longest wait between the two presses of a double press.
+const DOUBLE_PRESS_MS: u32 = 300;
+/// How long '-' has to be held to open the settings.
+const MENU_HOLD_MS: u32 = 1000;
+/// How long '+' has to be held while off to open the diagnostics.
+const DIAGNOSTICS_HOLD_MS: u32 = 2000;
+/// How long a button is held before it repeats, and how often it repeats.
+const REPEAT_DELAY_MS: u32 = 400;
+const REPEAT_MS: u32 = 100;
+/// How long a button is held before the repeats use the fast step.
+const FAST_AFTER_MS: u32 = 2000;
+/// Time without a press before going back to the main screen.
+const ADJUST_TIMEOUT_MS: u32 = 2000;
+const MENU_TIMEOUT_MS: u32 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Item {
+    Preset(usize),
+    Boost,
+    Step,
+    FastStep,
+    Language,
+}
+
+impl Item {
+    fn next(self) -> Option<Self> {
+        match self {
+            Item::Preset(i) if i + 1 < PRESETS => Some(Item::Preset(i + 1)),
+            Item::Preset(_) => Some(Item::Boost),
+            Item::Boost => Some(Item::Step),
+            Item::Step => Some(Item::FastStep),
+            Item::FastStep => Some(Item::Language),
+            Item::Language => None,
+        }
+    }
+}
+
+/// The pages of the diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Page {
+    Uptime,
+    HeatingTime,
+    HeatCycles,
+}
+
+impl Page {
+    fn next(self) -> Option<Self> {
+        match self {
+            Page::Uptime => Some(Page::HeatingTime),
+            Page::HeatingTime => Some(Page::HeatCycles),
+            Page::HeatCycles => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Main,
+    Adjust,
+    Menu(Item),
+    Diagnostics(Page),
+}
+
+/// What happened to a button during one update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edge {
+    Up,
+    Pressed,
+    Held,
+    Released,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Key {
+    down: bool,
+    held_ms: u32,
+    /// When to repeat next, in terms of `held_ms`.
+    repeat_at_ms: u32,
+    /// The press was taken by a gesture or by a change of screen, so it does
+    /// nothing more until the button is released.
+    used: bool,
+}
+
+impl Key {
+    fn update(&mut self, down: bool, elapsed_ms: u32) -> Edge {
+        let edge = match (self.down, down) {
+            (false, false) => Edge::Up,
+            (false, true) => {
+                self.held_ms = 0;
+                self.repeat_at_ms = REPEAT_DELAY_MS;
+                self.used = false;
+                Edge::Pressed
+            }
+            (true, true) => {
+                self.held_ms += elapsed_ms;
+                Edge::Held
+            }
+            (true, false) => Edge::Released,
+        };
+        self.down = down;
+        edge
+    }
+
+    /// Whether the button steps this update, on the press or on a repeat.
+    /// Returns whether to use the fast step.
+    fn step(&mut self, edge: Edge) -> Option<bool> {
+        match edge {
+            _ if self.used => None,
+            Edge::Pressed => Some(false),
+            Edge::Held if self.held_ms >= self.repeat_at_ms => {
+                self.repeat_at_ms += REPEAT_MS;
+                Some(self.held_ms >= FAST_AFTER_MS)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Moves `value` by one `step` in the direction of `up`, landing on a
+/// multiple of `step`.
+fn step_value(value: i32, step: i32, up: bool) -> i32 {
+    if up {
+        (value.div_euclid(step) + 1) * step
+    } else {
+        (value + step - 1).div_euclid(step) * step - step
+    }
+}
+
+/// What the board measured, for the main screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Status {
+    /// Tip temperature, `None` without a tip.
+    pub tip: Option<i32>,
+    /// Heater duty, per mille.
+    pub duty: u16,
+    /// Input voltage in millivolts, `None` when it can't be measured.
+    pub supply_mv: Option<u32>,
+    pub sleep: sleep::State,
+}
+
+pub struct IronUi {
+    settings: Settings,
+    /// The settings when the menu was opened.
+    saved: Settings,
+    changed: bool,
+    /// The temperature set by the user, 0 for off.
+    setpoint: i32,
+    boost: bool,
+    mode: Mode,
+    plus: Key,
+    minus: Key,
+    /// Time since '-' was let go, while waiting for a second press.
+    minus_wait_ms: Option<u32>,
+    /// Time since a button was last touched, outside of the main screen.
+    idle_ms: u32,
+    counters: Counters,
+    fonts: Fonts,
+}
+
+struct Fonts {
+    small: Font<'static>,
+    medium: Font<'static>,
+}
+
+impl Fonts {
+    /// The fonts are checked by the tests on the host, so they can't fail to
+    /// parse here.
+    fn new() -> Self {
+        Fonts {
+            small: Font::parse(text::SMALL).unwrap(),
+            medium: Font::parse(text::MEDIUM).unwrap(),
+        }
+    }
+}
+
+impl IronUi {
+    /// Starts with the heater off.
+    pub fn new(settings: Settings) -> Self {
+        IronUi {
+            settings,
+            saved: settings,
+            changed: false,
+            setpoint: 0,
+            boost: false,
+            mode: Mode::Main,
+            plus: Key::default(),
+            minus: Key::default(),
+            minus_wait_ms: None,
+            idle_ms: 0,
+            counters: Counters::default(),
+            fonts: Fonts::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The settings, if they were changed in the menu since the last call.
+    pub fn take_changed_settings(&mut self) -> Option<Settings> {
+        if self.changed {
+            self.changed = false;
+            Some(self.settings)
+        } else {
+            None
+        }
+    }
+
+    /// Updates the counters shown by the diagnostics. Returns whether the
+    /// display needs to be redrawn.
+    pub fn set_counters(&mut self, counters: Counters) -> bool {
+        let changed = counters != self.counters;
+        self.counters = counters;
+        changed && matches!(self.mode, Mode::Diagnostics(_))
+    }
+
+    /// The temperature set by the user, in tenths of a degree, 0 for off.
+    pub fn setpoint(&self) -> i32 {
+        self.setpoint
+    }
+
+    pub fn boosting(&self) -> bool {
+        self.boost
+    }
+
+    /// The temperature to heat to, taking boost into account.
+    pub fn target(&self) -> i32 {
+        if self.boost {
+            self.settings.boost_temp.max(self.setpoint)
+        } else {
+            self.setpoint
+        }
+    }
+
+    /// The preset the setpoint is at, counting from 1.
+    pub fn preset(&self) -> Option<usize> {
+        let position = self
+            .settings
+            .presets
+            .iter()
+            .position(|&p| p == self.setpoint);
+        position.map(|i| i + 1)
+    }
+
+    /// Advances the UI by `elapsed_ms`. Returns whether the display needs to
+    /// be redrawn.
+    pub fn update(&mut self, buttons: Buttons, elapsed_ms: u16) -> bool {
+        let before = (self.mode, self.setpoint, self.boost, self.settings);
+        let elapsed_ms = elapsed_ms as u32;
+        let plus = self.plus.update(buttons.plus, elapsed_ms);
+        let minus = self.minus.update(buttons.minus, elapsed_ms);
+        match self.mode {
+            Mode::Main => self.update_main(plus, minus, elapsed_ms),
+            Mode::Adjust => self.update_adjust(plus, minus, elapsed_ms),
+            Mode::Menu(item) => self.update_menu(item, plus, minus, elapsed_ms),
+            Mode::Diagnostics(page) => self.update_diagnostics(page, plus, minus, elapsed_ms),
+        }
+        (self.mode, self.setpoint, self.boost, self.settings) != before
+    }
+
+    fn enter(&mut self, mode: Mode) {
+        if let Mode::Menu(_) = mode {
+            self.saved = self.settings;
+        }
+        if let Mode::Menu(_) = self.mode {
+            if mode == Mode::Main && self.settings != self.saved {
+                self.changed = true;
+            }
+        }
+        self.mode = mode;
+        self.idle_ms = 0;
+        self.minus_wait_ms = None;
+        // Buttons still held down from the last screen do nothing on the new
+        // one.
+        self.plus.used |= self.plus.down;
+        self.minus.used |= self.minus.down;
+    }
+
+    fn update_main(&mut self, plus: Edge, minus: Edge, elapsed_ms: u32) {
+        match plus {
+            Edge::Held
+                if !self.plus.used && self.plus.held_ms >= BOOST_DELAY_MS && self.setpoint > 0 =>
+            {
+                self.boost = true;
+                self.plus.used = true;
+            }
+            Edge::Held
+                if !self.plus.used
+                    && self.plus.held_ms >= DIAGNOSTICS_HOLD_MS
+                    && self.setpoint == 0 =>
+            {
+                return self.enter(Mode::Diagnostics(Page::Uptime));
+            }
+            Edge::Released if !self.plus.used => return self.enter(Mode::Adjust),
+            _ => {}
+        }
+        if !self.plus.down {
+            self.boost = false;
+        }
+
+        match minus {
+            Edge::Pressed if self.minus_wait_ms.is_some() => {
+                self.minus_wait_ms = None;
+                self.minus.used = true;
+                self.next_preset();
+            }
+            Edge::Held if !self.minus.used && self.minus.held_ms >= MENU_HOLD_MS => {
+                self.boost = false;
+                self.enter(Mode::Menu(Item::Preset(0)));
+            }
+            Edge::Released if !self.minus.used => self.minus_wait_ms = Some(0),
+            Edge::Up => {
+                if let Some(wait_ms) = self.minus_wait_ms {
+                    let wait_ms = wait_ms + elapsed_ms;
+                    if wait_ms >= DOUBLE_PRESS_MS {
+                        self.enter(Mode::Adjust);
+                    } else {
+                        self.minus_wait_ms = Some(wait_ms);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn next_preset(&mut self) {
+        let presets = &self.settings.presets;
+        let next = match presets.iter().position(|&p| p == self.setpoint) {
+            Some(i) => (i + 1) % PRESETS,
+            None => 0,
+        };
+        self.setpoint = presets[next];
+    }
+
+    /// Counts the time since a button was last touched, and returns whether
+    /// it has reached `timeout_ms`.
+    fn idle(&mut self, plus: Edge, minus: Edge, elapsed_ms: u32, timeout_ms: u32) -> bool {
+        if plus == Edge::Up && minus == Edge::Up {
+            self.idle_ms += elapsed_ms;
+        } else {
+            self.idle_ms = 0;
+        }
+        self.idle_ms >= timeout_ms
+    }
+
+    fn update_adjust(&mut self, plus: Edge, minus: Edge, elapsed_ms: u32) {
+        let Settings {
+            step, fast_step, ..
+        } = self.settings;
+        let step = |fast| if fast { fast_step } else { step };
+        if let Some(fast) = self.plus.step(plus) {
+            self.setpoint = step_value(self.setpoint, step(fast), true).clamp(MIN_TEMP, MAX_TEMP);
+        }
+        if let Some(fast) = self.minus.step(minus) {
+            let setpoint = step_value(self.setpoint, step(fast), false);
+            // Below the lowest temperature is off.
+            self.setpoint = if setpoint < MIN_TEMP { 0 } else { setpoint };
+        }
+        if self.idle(plus, minus, elapsed_ms, ADJUST_TIMEOUT_MS) {
+            self.enter(Mode::Main);
+        }
+    }
+
+    fn update_menu(&mut self, item: Item, plus: Edge, minus: Edge, elapsed_ms: u32) {
+        if let Some(fast) = self.plus.step(plus) {
+            let settings = &mut self.settings;
+            let step = if fast {
+                settings.fast_step
+            } else {
+                settings.step
+            };
+            // Going past the highest value wraps around to the lowest.
+            let next_temp = |temp: i32| match step_value(temp, step, true) {
+                t if t > MAX_TEMP => MIN_TEMP,
+                t => t,
+            };
+            let next_choice = |choices: &[i32], value: i32| {
+                let i = choices.iter().position(|&c| c == value).unwrap_or(0);
+                choices[(i + 1) % choices.len()]
+            };
+            match item {
+                Item::Preset(i) => settings.presets[i] = next_temp(settings.presets[i]),
+                Item::Boost => settings.boost_temp = next_temp(settings.boost_temp),
+                Item::Step => settings.step = next_choice(&STEPS, settings.step),
+                Item::FastStep => settings.fast_step = next_choice(&FAST_STEPS, settings.fast_step),
+                Item::Language => settings.language = settings.language.next(),
+            }
+        }
+        if minus == Edge::Pressed && !self.minus.used {
+            match item.next() {
+                Some(next) => self.mode = Mode::Menu(next),
+                None => return self.enter(Mode::Main),
+            }
+        }
+        if self.idle(plus, minus, elapsed_ms, MENU_TIMEOUT_MS) {
+            self.enter(Mode::Main);
+        }
+    }
+
+    fn update_diagnostics(&mut self, page: Page, plus: Edge, minus: Edge, elapsed_ms: u32) {
+        if minus == Edge::Pressed && !self.minus.used {
+            match page.next() {
+                Some(next) => self.mode = Mode::Diagnostics(next),
+                None => return self.enter(Mode::Main),
+            }
+        }
+        if self.idle(plus, minus, elapsed_ms, MENU_TIMEOUT_MS) {
+            self.enter(Mode::Main);
+        }
+    }
+
+    fn text(&self, text: Text) -> &'static str {
+        self.settings.language.text(text)
+    }
+
+    /// Draws the current screen. The main screen shows `status` along with
+    /// the target.
+    pub fn draw<D>(&self, target: &mut D, status: &Status) -> Result<(), D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        target.clear(BinaryColor::Off)?;
+        match self.mode {
+            Mode::Main => main_screen::draw(
+                target,
+                &self.fonts.small,
+                status,
+                self.target(),
+                self.boost,
+                self.preset(),
+            ),
+            Mode::Adjust => {
+                let font = &self.fonts.medium;
+                let top = (HEIGHT as i32 - font.line_height() as i32) / 2;
+                let mut line = Line::new();
+                let _ = match self.setpoint {
+                    0 => line.write_str(self.text(Text::Off)),
+                    setpoint => write!(line, "{}°C", setpoint / 10),
+                };
+                font.draw("-", Point::new(2, top), target)?;
+                font.draw_centred(line.as_str(), WIDTH as i32 / 2, top, target)?;
+                let plus_x = WIDTH as i32 - 2 - font.measure("+") as i32;
+                font.draw("+", Point::new(plus_x, top), target)?;
+                Ok(())
+            }
+            Mode::Menu(item) => {
+                let mut title = Line::new();
+                let mut value = Line::new();
+                let s = &self.settings;
+                let mut temp = |text, temp: i32| {
+                    let _ = title.write_str(self.text(text));
+                    let _ = write!(value, "{}°C", temp / 10);
+                };
+                match item {
+                    Item::Preset(i) => {
+                        temp(Text::Preset, s.presets[i]);
+                        let _ = write!(title, " {}", i + 1);
+                    }
+                    Item::Boost => temp(Text::Boost, s.boost_temp),
+                    Item::Step => temp(Text::Step, s.step),
+                    Item::FastStep => temp(Text::FastStep, s.fast_step),
+                    Item::Language => {
+                        let _ = title.write_str(self.text(Text::Language));
+                        let _ = value.write_str(self.text(Text::Name));
+                    }
+                }
+                self.draw_page(target, &title, &value)
+            }
+            Mode::Diagnostics(page) => {
+                let mut value = Line::new();
+                let c = &self.counters;
+                let (text, _) = match page {
+                    Page::Uptime => (Text::Uptime, write!(value, "{}", Hours(c.uptime_s))),
+                    Page::HeatingTime => {
+                        (Text::HeatingTime, write!(value, "{}", Hours(c.heating_s)))
+                    }
+                    Page::HeatCycles => (Text::HeatCycles, write!(value, "{}", c.heat_cycles)),
+                };
+                let mut title = Line::new();
+                let _ = title.write_str(self.text(text));
+                self.draw_page(target, &title, &value)
+            }
+        }
+    }
+
+    /// A title over a value, as in the settings menu.
+    fn draw_page<D>(&self, target: &mut D, title: &Line, value: &Line) -> Result<(), D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        let font = &self.fonts.small;
+        let centre = WIDTH as i32 / 2;
+        font.draw_centred(title.as_str(), centre, 0, target)?;
+        font.draw_centred(value.as_str(), centre, 8, target)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! The main screen: the tip temperature and the target in large digits, the
+//! state and the supply on the right, and the heating power along the edge.
+//!
+//! ```text
+//! x: 0        35  41       77      93
+//!    tip      >   target   icon    power
+//!                          supply
+//! ```
+//!
+//! Both temperatures have room for three large digits.
+
+use core::fmt::Write;
+
+use embedded_graphics::{geometry::Point, pixelcolor::BinaryColor, DrawTarget};
+
+use super::Status;
+use crate::{
+    display::{self, HEIGHT},
+    large_font, sleep,
+    text::Font,
+    ui::Line,
+};
+
+const TIP_X: i32 = 0;
+const ARROW_X: i32 = 35;
+const TARGET_X: i32 = 41;
+const ICON_X: i32 = 77;
+const BAR_X: i32 = 93;
+const BAR_WIDTH: i32 = 3;
+
+/// Highest temperature which fits in three digits, in tenths of a degree.
+const MAX_SHOWN: i32 = 9990;
+
+/// 7x8 icons in the page layout of `display`.
+const BOOST_ICON: [u8; 7] = [0x08, 0x0c, 0x7e, 0x7f, 0x7e, 0x0c, 0x08];
+const SLEEP_ICON: [u8; 7] = [0x09, 0x0d, 0x0b, 0x09, 0x50, 0x70, 0x50];
+/// 5x8, so that two digits fit next to it.
+const SUPPLY_ICON: [u8; 5] = [0x08, 0x6c, 0x3e, 0x1b, 0x09];
+
+fn draw_icon<D>(target: &mut D, x: i32, y: i32, icon: &[u8]) -> Result<(), D::Error>
+where
+    D: DrawTarget<BinaryColor>,
+{
+    let columns = icon.iter().map(|&column| column as u16);
+    display::draw_columns(target, Point::new(x, y), columns)
+}
+
+/// Draws a temperature right aligned in three large digits.
+fn draw_temperature<D>(target: &mut D, x: i32, temp: i32) -> Result<(), D::Error>
+where
+    D: DrawTarget<BinaryColor>,
+{
+    let mut line = Line::new();
+    let _ = write!(line, "{:>3}", temp.clamp(0, MAX_SHOWN) / 10);
+    large_font::draw(target, x, line.as_str()).map(|_| ())
+}
+
+pub(super) fn draw<D>(
+    target: &mut D,
+    font: &Font,
+    status: &Status,
+    target_temp: i32,
+    boost: bool,
+    preset: Option<usize>,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<BinaryColor>,
+{
+    match status.tip {
+        Some(tip) => draw_temperature(target, TIP_X, tip)?,
+        None => {
+            large_font::draw(target, TIP_X, "---")?;
+        }
+    }
+
+    // A small arrow from the tip to the target.
+    let half = HEIGHT as i32 / 2;
+    let arrow = (0..4).flat_map(|dx| (half - 3 + dx..=half + 3 - dx).map(move |y| (dx, y)));
+    display::draw_lit(target, arrow.map(|(dx, y)| Point::new(ARROW_X + dx, y)))?;
+
+    if target_temp == 0 || status.sleep == sleep::State::Off {
+        large_font::draw(target, TARGET_X, "OFF")?;
+    } else {
+        draw_temperature(target, TARGET_X, target_temp)?;
+    }
+
+    if boost {
+        draw_icon(target, ICON_X, 0, &BOOST_ICON)?;
+    } else if status.sleep != sleep::State::Active {
+        draw_icon(target, ICON_X, 0, &SLEEP_ICON)?;
+    } else if let Some(preset) = preset {
+        let mut line = Line::new();
+        let _ = write!(line, "P{}", preset);
+        font.draw(line.as_str(), Point::new(ICON_X, 0), target)?;
+    }
+
+    draw_icon(target, ICON_X, 8, &SUPPLY_ICON)?;
+    let mut line = Line::new();
+    let _ = match status.supply_mv {
+        Some(mv) => write!(line, "{}", ((mv + 500) / 1000).min(99)),
+        None => write!(line, "--"),
+    };
+    // Right aligned against the power bar, as the font is proportional.
+    let supply_x = BAR_X - font.measure(line.as_str()) as i32;
+    font.draw(line.as_str(), Point::new(supply_x, 8), target)?;
+
+    // The heating power, filling up from the bottom.
+    let height = ((status.duty.min(1000) as usize * HEIGHT + 500) / 1000) as i32;
+    let bar = (BAR_X..BAR_X + BAR_WIDTH)
+        .flat_map(|x| (HEIGHT as i32 - height..HEIGHT as i32).map(move |y| Point::new(x, y)));
+    display::draw_lit(target, bar)
+}
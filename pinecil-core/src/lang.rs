@@ -0,0 +1,47 @@
+//! Translations of the strings of the UI.
+//!
+//! Each language is a file in `lang/`, turned into a table in flash by
+//! `build.rs`. The build fails if a language is missing a key of English or
+//! has one English does not, so that a new string can't be forgotten in a
+//! translation. A text can still be left empty to use the English one, for
+//! words which don't need translating. The language is chosen in the
+//! settings menu and stored with the [`Settings`](crate::settings::Settings).
+//!
+//! The texts only use characters which [`text::SMALL`](crate::text::SMALL)
+//! has.
+
+include!(concat!(env!("OUT_DIR"), "/lang.rs"));
+
+impl Language {
+    pub fn text(self, text: Text) -> &'static str {
+        TABLES[self as usize][text as usize]
+    }
+
+    /// The language as it is stored in the settings.
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    /// Falls back to English for languages this firmware does not have.
+    pub fn from_index(index: u8) -> Self {
+        Language::ALL
+            .get(index as usize)
+            .copied()
+            .unwrap_or(Language::English)
+    }
+
+    /// The name of the file in `lang/`, like "en".
+    pub fn code(self) -> &'static str {
+        CODES[self as usize]
+    }
+
+    /// The language with this file name, like "en".
+    pub fn from_code(code: &str) -> Option<Self> {
+        Language::ALL.iter().copied().find(|l| l.code() == code)
+    }
+
+    /// The one after this in [`Language::ALL`], wrapping around.
+    pub fn next(self) -> Self {
+        Language::from_index((self.index() + 1) % Language::ALL.len() as u8)
+    }
+}
@@ -0,0 +1,40 @@
+//! Large digits which fill the height of the OLED, for the temperatures on
+//! the main screen.
+//!
+//! The glyphs are the digits of the public domain X11 misc-fixed `6x10` font
+//! at twice the size, generated from `fonts/6x10.bdf` by `build.rs`. Besides
+//! the digits, there is only '-' and the letters of "OFF". Each glyph is a
+//! column of 16 pixels per `u16`, with the top pixel in the least
+//! significant bit.
+
+use embedded_graphics::{geometry::Point, pixelcolor::BinaryColor, DrawTarget};
+
+use crate::display;
+
+include!(concat!(env!("OUT_DIR"), "/large_font.rs"));
+
+pub const HEIGHT: usize = 16;
+/// Distance from the start of one character to the start of the next.
+pub const ADVANCE: usize = WIDTH + 2;
+
+/// Returns the glyph for `c`, or `None` for characters which are not in the
+/// font, which are left blank.
+pub fn glyph(c: char) -> Option<&'static [u16; WIDTH]> {
+    CHARS.chars().position(|g| g == c).map(|i| &GLYPHS[i])
+}
+
+/// Draws `text` across the full height of the target from column `x`, and
+/// returns the column after the last character.
+pub fn draw<D>(target: &mut D, x: i32, text: &str) -> Result<i32, D::Error>
+where
+    D: DrawTarget<BinaryColor>,
+{
+    let mut x = x;
+    for c in text.chars() {
+        if let Some(glyph) = glyph(c) {
+            display::draw_columns(target, Point::new(x, 0), glyph.iter().copied())?;
+        }
+        x += ADVANCE as i32;
+    }
+    Ok(x)
+}
@@ -0,0 +1,32 @@
+//! Hardware-independent code shared between the Pinecil firmware and the host
+//! tools.
+//!
+//! Nothing in here touches the peripherals directly (except for the few
+//! documented raw pointer writes in [`image`]), so the crate can also be
+//! built and tested on the host.
+
+#![no_std]
+
+pub mod anim;
+pub mod clock;
+pub mod counters;
+pub mod crash;
+pub mod display;
+pub mod executor;
+pub mod image;
+pub mod iron;
+pub mod lang;
+pub mod large_font;
+pub mod pd;
+pub mod pid;
+pub mod power;
+pub mod settings;
+pub mod shell;
+pub mod sleep;
+pub mod stack;
+pub mod text;
+pub mod thermocouple;
+pub mod timers;
+pub mod tip_model;
+pub mod ui;
+pub mod watchdog;
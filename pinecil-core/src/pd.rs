@@ -0,0 +1,19 @@
+//! USB Power Delivery sink.
+//!
+//! The Pinecil runs off USB-C, and only gets more than 5 V by asking a PD
+//! charger for it. The PD controller on the board (an FUSB302B, driven by
+//! `pinecil_bsp::fusb302`) takes care of the physical layer: it sends and
+//! receives whole messages, and answers each message with a GoodCRC by
+//! itself. What is left is split into two parts here:
+//!
+//! - [`message`]: the layout of PD messages and of the power data objects
+//!   which describe what a charger can supply.
+//! - [`sink`]: the protocol layer and the policy engine, which wait for the
+//!   charger to advertise its capabilities, request the best one within the
+//!   configured limits and keep the contract going afterwards.
+//!
+//! Neither part touches the hardware, so they are tested on the host against
+//! message traces in `tests/pd`.
+
+pub mod message;
+pub mod sink;
@@ -0,0 +1,295 @@
+//! PD messages and power data objects.
+//!
+//! A message is a 16-bit header followed by up to seven 32-bit data objects,
+//! all little-endian. The framing, the CRC and the GoodCRC replies are left
+//! to the PD controller, so only the header and the objects are handled here.
+//!
+//! Header layout:
+//!
+//! ```text
+//! bit 15      extended message
+//! bits 14-12  number of data objects, 0 for control messages
+//! bits 11-9   message ID
+//! bit 8       power role: 1 = source
+//! bits 7-6    spec revision: 1 = 2.0, 2 = 3.0
+//! bit 5       data role: 1 = DFP
+//! bits 4-0    message type
+//! ```
+
+pub const MAX_OBJECTS: usize = 7;
+/// Longest message without the CRC, in bytes.
+pub const MAX_LEN: usize = 2 + 4 * MAX_OBJECTS;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Shorter than the header, or than the objects the header announces.
+    Truncated,
+    /// Longer than the objects the header announces.
+    TooLong,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Revision {
+    /// PD 2.0, also used for the obsolete 1.0.
+    Rev20,
+    /// PD 3.0 or later.
+    Rev30,
+}
+
+impl Revision {
+    fn from_bits(bits: u16) -> Self {
+        match bits {
+            0 | 1 => Revision::Rev20,
+            _ => Revision::Rev30,
+        }
+    }
+
+    fn bits(self) -> u16 {
+        match self {
+            Revision::Rev20 => 1,
+            Revision::Rev30 => 2,
+        }
+    }
+}
+
+/// Message types. Those the sink does not care about keep their raw type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    GoodCrc,
+    GotoMin,
+    Accept,
+    Reject,
+    Ping,
+    PsRdy,
+    GetSourceCap,
+    GetSinkCap,
+    DrSwap,
+    PrSwap,
+    VconnSwap,
+    Wait,
+    SoftReset,
+    NotSupported,
+    OtherControl(u8),
+
+    SourceCapabilities,
+    Request,
+    SinkCapabilities,
+    VendorDefined,
+    OtherData(u8),
+
+    Extended(u8),
+}
+
+const CONTROL_KINDS: [(u8, Kind); 14] = [
+    (1, Kind::GoodCrc),
+    (2, Kind::GotoMin),
+    (3, Kind::Accept),
+    (4, Kind::Reject),
+    (5, Kind::Ping),
+    (6, Kind::PsRdy),
+    (7, Kind::GetSourceCap),
+    (8, Kind::GetSinkCap),
+    (9, Kind::DrSwap),
+    (10, Kind::PrSwap),
+    (11, Kind::VconnSwap),
+    (12, Kind::Wait),
+    (13, Kind::SoftReset),
+    (16, Kind::NotSupported),
+];
+
+const DATA_KINDS: [(u8, Kind); 4] = [
+    (1, Kind::SourceCapabilities),
+    (2, Kind::Request),
+    (4, Kind::SinkCapabilities),
+    (15, Kind::VendorDefined),
+];
+
+impl Kind {
+    fn from_header(header: u16) -> Self {
+        let code = (header & 0x1f) as u8;
+        let find = |kinds: &[(u8, Kind)]| {
+            kinds
+                .iter()
+                .find(|&&(c, _)| c == code)
+                .map(|&(_, kind)| kind)
+        };
+        if header & 0x8000 != 0 {
+            Kind::Extended(code)
+        } else if header >> 12 & 7 == 0 {
+            find(&CONTROL_KINDS).unwrap_or(Kind::OtherControl(code))
+        } else {
+            find(&DATA_KINDS).unwrap_or(Kind::OtherData(code))
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            Kind::OtherControl(code) | Kind::OtherData(code) | Kind::Extended(code) => code,
+            _ => CONTROL_KINDS
+                .iter()
+                .chain(DATA_KINDS.iter())
+                .find(|&&(_, k)| k == self)
+                .map(|&(c, _)| c)
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub kind: Kind,
+    pub id: u8,
+    pub revision: Revision,
+    /// Sent by a source, as opposed to a sink.
+    pub from_source: bool,
+    objects: [u32; MAX_OBJECTS],
+    count: usize,
+}
+
+impl Message {
+    /// A message from this sink, which is always the UFP.
+    ///
+    /// # Panics
+    ///
+    /// If there are more than [`MAX_OBJECTS`] objects.
+    pub fn new(kind: Kind, id: u8, revision: Revision, objects: &[u32]) -> Self {
+        debug_assert!(
+            objects.len() <= MAX_OBJECTS,
+            "more objects than a message has room for"
+        );
+        let mut message = Message {
+            kind,
+            id: id & 7,
+            revision,
+            from_source: false,
+            objects: [0; MAX_OBJECTS],
+            count: objects.len(),
+        };
+        message.objects[..objects.len()].copy_from_slice(objects);
+        message
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 2 {
+            return Err(Error::Truncated);
+        }
+        let header = u16::from_le_bytes([bytes[0], bytes[1]]);
+        let count = (header >> 12 & 7) as usize;
+        let len = 2 + 4 * count;
+        if bytes.len() < len {
+            return Err(Error::Truncated);
+        }
+        if bytes.len() > len {
+            return Err(Error::TooLong);
+        }
+        let mut objects = [0; MAX_OBJECTS];
+        for (object, chunk) in objects.iter_mut().zip(bytes[2..].chunks_exact(4)) {
+            *object = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        Ok(Message {
+            kind: Kind::from_header(header),
+            id: (header >> 9 & 7) as u8,
+            revision: Revision::from_bits(header >> 6 & 3),
+            from_source: header & 0x100 != 0,
+            objects,
+            count,
+        })
+    }
+
+    pub fn header(&self) -> u16 {
+        let extended = matches!(self.kind, Kind::Extended(_)) as u16;
+        extended << 15
+            | (self.count as u16) << 12
+            | (self.id as u16) << 9
+            | (self.from_source as u16) << 8
+            | self.revision.bits() << 6
+            | self.kind.code() as u16
+    }
+
+    /// Writes the message into `buf`, returning the part which was used.
+    pub fn encode<'b>(&self, buf: &'b mut [u8; MAX_LEN]) -> &'b [u8] {
+        buf[..2].copy_from_slice(&self.header().to_le_bytes());
+        for (chunk, object) in buf[2..].chunks_exact_mut(4).zip(self.objects()) {
+            chunk.copy_from_slice(&object.to_le_bytes());
+        }
+        &buf[..2 + 4 * self.count]
+    }
+
+    pub fn objects(&self) -> &[u32] {
+        &self.objects[..self.count]
+    }
+}
+
+/// A power data object, one of the supplies listed in the capabilities.
+/// Voltages are in millivolts, currents in milliamps and power in
+/// milliwatts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pdo {
+    Fixed {
+        mv: u32,
+        max_ma: u32,
+    },
+    Battery {
+        min_mv: u32,
+        max_mv: u32,
+        max_mw: u32,
+    },
+    Variable {
+        min_mv: u32,
+        max_mv: u32,
+        max_ma: u32,
+    },
+    /// Programmable power supply, from PD 3.0.
+    Pps {
+        min_mv: u32,
+        max_mv: u32,
+        max_ma: u32,
+    },
+    /// Some other augmented PDO.
+    Other(u32),
+}
+
+impl Pdo {
+    pub fn parse(raw: u32) -> Self {
+        let field = |shift: u32, bits: u32| raw >> shift & ((1 << bits) - 1);
+        match raw >> 30 {
+            0 => Pdo::Fixed {
+                mv: field(10, 10) * 50,
+                max_ma: field(0, 10) * 10,
+            },
+            1 => Pdo::Battery {
+                min_mv: field(10, 10) * 50,
+                max_mv: field(20, 10) * 50,
+                max_mw: field(0, 10) * 250,
+            },
+            2 => Pdo::Variable {
+                min_mv: field(10, 10) * 50,
+                max_mv: field(20, 10) * 50,
+                max_ma: field(0, 10) * 10,
+            },
+            _ if field(28, 2) == 0 => Pdo::Pps {
+                min_mv: field(8, 8) * 100,
+                max_mv: field(17, 8) * 100,
+                max_ma: field(0, 7) * 50,
+            },
+            _ => Pdo::Other(raw),
+        }
+    }
+}
+
+/// A fixed supply PDO, as listed in the sink capabilities.
+pub fn fixed_pdo(mv: u32, max_ma: u32) -> u32 {
+    ((mv / 50) << 10) | (max_ma / 10)
+}
+
+/// A request data object for the fixed supply at `position` in the source
+/// capabilities, counting from 1. The iron never uses USB communication and
+/// must not be suspended, so those bits are fixed.
+pub fn fixed_request(position: u8, operating_ma: u32, max_ma: u32, mismatch: bool) -> u32 {
+    const NO_USB_SUSPEND: u32 = 1 << 24;
+    ((position as u32 & 7) << 28)
+        | ((mismatch as u32) << 26)
+        | NO_USB_SUSPEND
+        | ((operating_ma / 10) << 10)
+        | (max_ma / 10)
+}
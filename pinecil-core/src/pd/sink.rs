@@ -0,0 +1,347 @@
+//! Protocol layer and policy engine of the sink.
+//!
+//! [`Sink`] is fed with what happens on the wire and returns what to send
+//! back; the board code passes messages between it and the PD controller,
+//! and calls [`Sink::poll`] regularly for the timeouts. All times are in
+//! milliseconds from an arbitrary start, and may wrap around.
+//!
+//! The negotiation goes:
+//!
+//! 1. Once attached, the source sends its capabilities.
+//! 2. The sink requests the fixed supply with the highest voltage within
+//!    [`Config::max_mv`], see [`select`].
+//! 3. The source accepts and, once the supply has changed, sends PS_RDY.
+//!
+//! A message which makes no sense while waiting for the source to accept the
+//! request or change the supply gets a soft reset, which starts the message
+//! IDs over and negotiates again without touching the supply. If the source
+//! stops answering along the way, the sink asks for a hard reset, which takes
+//! the supply back to 5 V and starts over. After [`HARD_RESET_COUNT`] of them
+//! it gives up and stays at 5 V, which is also what happens on a USB port
+//! without PD.
+
+use super::message::{self, Kind, Message, Pdo, Revision};
+
+/// How long to wait for the source capabilities after attaching
+/// (tTypeCSinkWaitCap is 310 to 620 ms).
+pub const WAIT_CAPABILITIES_MS: u32 = 500;
+/// How long the source has to answer a request (tSenderResponse is 24 to
+/// 30 ms).
+pub const SENDER_RESPONSE_MS: u32 = 30;
+/// How long the source has to change the supply (tPSTransition is 450 to
+/// 550 ms).
+pub const PS_TRANSITION_MS: u32 = 500;
+/// How long the source takes to recover from a hard reset and advertise its
+/// capabilities again.
+pub const HARD_RESET_RECOVERY_MS: u32 = 1500;
+/// How long to wait before requesting again after the source answered Wait
+/// (tSinkRequest).
+pub const SINK_REQUEST_MS: u32 = 100;
+/// Number of hard resets before giving up (nHardResetCount).
+pub const HARD_RESET_COUNT: u8 = 2;
+
+/// What to ask the source for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Highest voltage to request, in millivolts.
+    pub max_mv: u32,
+    /// Most current to draw, in milliamps.
+    pub max_ma: u32,
+}
+
+/// The supply agreed on with the source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Contract {
+    pub mv: u32,
+    pub ma: u32,
+}
+
+/// A fixed supply picked from the source capabilities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    /// Position in the capabilities, counting from 1.
+    pub position: u8,
+    pub contract: Contract,
+}
+
+/// Picks the fixed supply with the highest voltage up to `config.max_mv`,
+/// preferring the one with more current if there are several, and draws no
+/// more than `config.max_ma` from it. The first supply is always the 5 V
+/// one, which is used if nothing else fits.
+pub fn select(config: &Config, capabilities: &[u32]) -> Selection {
+    let fixed = |raw| match Pdo::parse(raw) {
+        Pdo::Fixed { mv, max_ma } => Some((mv, max_ma)),
+        _ => None,
+    };
+    let best = capabilities
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &raw)| Some((fixed(raw)?, i)))
+        .filter(|&((mv, _), _)| mv <= config.max_mv)
+        .max_by_key(|&(supply, _)| supply);
+    let ((mv, max_ma), i) = best.unwrap_or_else(|| {
+        let first = capabilities.first().and_then(|&raw| fixed(raw));
+        (first.unwrap_or((5000, 0)), 0)
+    });
+    Selection {
+        position: i as u8 + 1,
+        contract: Contract {
+            mv,
+            ma: max_ma.min(config.max_ma),
+        },
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Not attached to a source.
+    Detached,
+    /// Waiting for the source capabilities.
+    WaitCapabilities,
+    /// A request has been sent, waiting for the source to accept it.
+    WaitAccept,
+    /// The request was accepted, waiting for the supply to change.
+    WaitPsRdy,
+    /// A Soft_Reset has been sent, waiting for the source to accept it.
+    SoftReset,
+    /// The contract is in place.
+    Ready,
+    /// The source never answered, so there is only 5 V.
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Send(Message),
+    /// Signal a hard reset on the wire.
+    HardReset,
+}
+
+pub struct Sink {
+    config: Config,
+    state: State,
+    /// When the current state times out.
+    deadline: Option<u32>,
+    /// When to request again after the source answered Wait.
+    retry_at: Option<u32>,
+    hard_resets: u8,
+    revision: Revision,
+    /// ID of the next message to send.
+    tx_id: u8,
+    /// ID of the last message received, to drop retransmissions.
+    rx_id: Option<u8>,
+    capabilities: Option<Message>,
+    requested: Option<Contract>,
+    contract: Option<Contract>,
+}
+
+impl Sink {
+    pub fn new(config: Config) -> Self {
+        Sink {
+            config,
+            state: State::Detached,
+            deadline: None,
+            retry_at: None,
+            hard_resets: 0,
+            revision: Revision::Rev30,
+            tx_id: 0,
+            rx_id: None,
+            capabilities: None,
+            requested: None,
+            contract: None,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// The supply agreed on, or `None` while running on the default 5 V.
+    pub fn contract(&self) -> Option<Contract> {
+        self.contract
+    }
+
+    /// The capabilities last advertised by the source.
+    pub fn capabilities(&self) -> impl Iterator<Item = Pdo> + '_ {
+        let objects = self.capabilities.as_ref().map_or(&[][..], |m| m.objects());
+        objects.iter().map(|&raw| Pdo::parse(raw))
+    }
+
+    /// A source has been attached to the CC line.
+    pub fn attach(&mut self, now_ms: u32) {
+        self.hard_resets = 0;
+        self.reset_protocol();
+        self.contract = None;
+        self.wait_capabilities(now_ms, WAIT_CAPABILITIES_MS);
+    }
+
+    pub fn detach(&mut self) {
+        self.reset_protocol();
+        self.contract = None;
+        self.capabilities = None;
+        self.state = State::Detached;
+        self.deadline = None;
+    }
+
+    /// The source signalled a hard reset. The supply goes back to 5 V.
+    pub fn hard_reset_received(&mut self, now_ms: u32) {
+        self.reset_protocol();
+        self.contract = None;
+        self.wait_capabilities(now_ms, HARD_RESET_RECOVERY_MS);
+    }
+
+    /// Handles the timeouts.
+    pub fn poll(&mut self, now_ms: u32) -> Option<Action> {
+        if let Some(retry_at) = self.retry_at {
+            if reached(now_ms, retry_at) {
+                self.retry_at = None;
+                if let Some(capabilities) = self.capabilities {
+                    return Some(self.request(&capabilities, now_ms));
+                }
+            }
+        }
+        match self.deadline {
+            Some(deadline) if reached(now_ms, deadline) => self.timeout(now_ms),
+            _ => None,
+        }
+    }
+
+    fn timeout(&mut self, now_ms: u32) -> Option<Action> {
+        self.deadline = None;
+        match self.state {
+            State::WaitCapabilities | State::WaitAccept | State::WaitPsRdy | State::SoftReset => {
+                self.hard_reset(now_ms)
+            }
+            _ => None,
+        }
+    }
+
+    fn hard_reset(&mut self, now_ms: u32) -> Option<Action> {
+        if self.hard_resets == HARD_RESET_COUNT {
+            self.state = State::Failed;
+            self.deadline = None;
+            return None;
+        }
+        self.hard_resets += 1;
+        self.hard_reset_received(now_ms);
+        Some(Action::HardReset)
+    }
+
+    /// Handles a message from the source.
+    pub fn receive(&mut self, message: &Message, now_ms: u32) -> Option<Action> {
+        if self.state == State::Detached {
+            return None;
+        }
+        match message.kind {
+            // GoodCRC is handled by the PD controller, and these do not need
+            // an answer.
+            Kind::GoodCrc | Kind::Ping => return None,
+            Kind::SoftReset => {
+                self.reset_protocol();
+                self.wait_capabilities(now_ms, WAIT_CAPABILITIES_MS);
+                return Some(self.send(Kind::Accept, &[]));
+            }
+            _ => {}
+        }
+        if self.rx_id == Some(message.id) {
+            // The source did not see our GoodCRC and sent it again.
+            return None;
+        }
+        self.rx_id = Some(message.id);
+
+        match (self.state, message.kind) {
+            (_, Kind::SourceCapabilities) => {
+                self.revision = message.revision.min(Revision::Rev30);
+                self.capabilities = Some(*message);
+                self.hard_resets = 0;
+                Some(self.request(message, now_ms))
+            }
+            (State::WaitAccept, Kind::Accept) => {
+                self.state = State::WaitPsRdy;
+                self.deadline = Some(now_ms.wrapping_add(PS_TRANSITION_MS));
+                None
+            }
+            (State::WaitAccept, Kind::Reject) | (State::WaitAccept, Kind::Wait) => {
+                // Keep the old contract if there is one.
+                if message.kind == Kind::Wait {
+                    self.retry_at = Some(now_ms.wrapping_add(SINK_REQUEST_MS));
+                }
+                match self.contract {
+                    Some(_) => {
+                        self.state = State::Ready;
+                        self.deadline = None;
+                    }
+                    None => self.wait_capabilities(now_ms, WAIT_CAPABILITIES_MS),
+                }
+                None
+            }
+            (State::WaitPsRdy, Kind::PsRdy) => {
+                self.state = State::Ready;
+                self.deadline = None;
+                self.contract = self.requested;
+                None
+            }
+            // Anything else in the middle of a power transition means that
+            // the source and the sink disagree about what is going on.
+            (State::WaitAccept, _) | (State::WaitPsRdy, _) => Some(self.soft_reset(now_ms)),
+            (State::SoftReset, Kind::Accept) => {
+                self.wait_capabilities(now_ms, WAIT_CAPABILITIES_MS);
+                None
+            }
+            (_, Kind::GetSinkCap) => {
+                let mut pdos = [message::fixed_pdo(5000, self.config.max_ma); 2];
+                pdos[1] = message::fixed_pdo(self.config.max_mv, self.config.max_ma);
+                let count = if self.config.max_mv > 5000 { 2 } else { 1 };
+                Some(self.send(Kind::SinkCapabilities, &pdos[..count]))
+            }
+            // Structured VDMs may be ignored before PD 3.0.
+            (_, Kind::VendorDefined) if self.revision == Revision::Rev20 => None,
+            (_, _) => Some(match self.revision {
+                Revision::Rev20 => self.send(Kind::Reject, &[]),
+                Revision::Rev30 => self.send(Kind::NotSupported, &[]),
+            }),
+        }
+    }
+
+    /// Starts the message IDs over, keeping the supply, and waits for the
+    /// source to accept.
+    fn soft_reset(&mut self, now_ms: u32) -> Action {
+        self.reset_protocol();
+        self.state = State::SoftReset;
+        self.deadline = Some(now_ms.wrapping_add(SENDER_RESPONSE_MS));
+        self.send(Kind::SoftReset, &[])
+    }
+
+    fn reset_protocol(&mut self) {
+        self.tx_id = 0;
+        self.rx_id = None;
+        self.retry_at = None;
+        self.requested = None;
+    }
+
+    fn wait_capabilities(&mut self, now_ms: u32, timeout_ms: u32) {
+        self.state = State::WaitCapabilities;
+        self.deadline = Some(now_ms.wrapping_add(timeout_ms));
+    }
+
+    fn request(&mut self, capabilities: &Message, now_ms: u32) -> Action {
+        let selection = select(&self.config, capabilities.objects());
+        let ma = selection.contract.ma;
+        let rdo = message::fixed_request(selection.position, ma, ma, false);
+        self.requested = Some(selection.contract);
+        self.state = State::WaitAccept;
+        self.deadline = Some(now_ms.wrapping_add(SENDER_RESPONSE_MS));
+        self.send(Kind::Request, &[rdo])
+    }
+
+    fn send(&mut self, kind: Kind, objects: &[u32]) -> Action {
+        let message = Message::new(kind, self.tx_id, self.revision, objects);
+        self.tx_id = (self.tx_id + 1) & 7;
+        Action::Send(message)
+    }
+}
+
+/// Whether `now` has reached `deadline`, allowing for wrap-around.
+fn reached(now: u32, deadline: u32) -> bool {
+    now.wrapping_sub(deadline) as i32 >= 0
+}
@@ -0,0 +1,137 @@
+//! Fixed-point PID controller for the tip temperature.
+//!
+//! The controller runs once per heater period, taking the target and the
+//! measured temperature in tenths of a degree and returning the duty cycle
+//! of the next period in per mille. On top of the textbook PID it has:
+//!
+//! - Integral clamping: the integral term stays between zero and the output
+//!   limit, and stops growing while the output is saturated, so it does not
+//!   wind up while the tip is heating at full power.
+//! - Derivative on measurement: the derivative term follows the change of
+//!   the measured temperature rather than the error, so changing the target
+//!   does not kick the output.
+//! - Slew limiting: the output changes by at most `max_step` per period,
+//!   except that it drops at once when the limit is lowered.
+//! - A power budget, set with [`Pid::set_limit`], which caps the output
+//!   below what the supply could deliver, see [`power_limit`].
+
+/// The gains are in 1/256 per mille of duty per tenth of a degree.
+pub const GAIN_ONE: i32 = 256;
+
+/// Gains of the controller, in units of [`GAIN_ONE`]. The integral and
+/// derivative gains are per period rather than per second, so they depend on
+/// how often the controller runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gains {
+    pub kp: i32,
+    pub ki: i32,
+    pub kd: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub gains: Gains,
+    /// Highest output, in per mille.
+    pub max_output: u16,
+    /// Largest change of the output from one period to the next, in per
+    /// mille.
+    pub max_step: u16,
+}
+
+/// Tuned against [`tip_model::PINECIL_TIP`](crate::tip_model::PINECIL_TIP)
+/// at 20 V, with the 100 ms heater period.
+pub const PINECIL_CONFIG: Config = Config {
+    gains: Gains {
+        kp: 8 * GAIN_ONE,
+        ki: GAIN_ONE / 8,
+        kd: 30 * GAIN_ONE,
+    },
+    max_output: 900,
+    max_step: 250,
+};
+
+/// Returns the duty cycle in per mille which draws `budget_mw` from a
+/// `supply_mv` supply through a heater of `resistance_mohm`, or full duty if
+/// the supply can't deliver that much. An unknown resistance of 0 gives no
+/// duty at all.
+pub fn power_limit(budget_mw: u32, supply_mv: u32, resistance_mohm: u32) -> u16 {
+    if resistance_mohm == 0 {
+        return 0;
+    }
+    let full_mw = supply_mv as u64 * supply_mv as u64 / resistance_mohm as u64;
+    if full_mw == 0 {
+        return 1000;
+    }
+    (budget_mw as u64 * 1000 / full_mw).min(1000) as u16
+}
+
+#[derive(Clone, Debug)]
+pub struct Pid {
+    config: Config,
+    /// The power budget, in per mille.
+    limit: u16,
+    /// The integral term, in units of [`GAIN_ONE`].
+    integral: i32,
+    last_measured: Option<i32>,
+    output: u16,
+}
+
+impl Pid {
+    pub const fn new(config: Config) -> Self {
+        Pid {
+            config,
+            limit: 1000,
+            integral: 0,
+            last_measured: None,
+            output: 0,
+        }
+    }
+
+    /// Forgets the history, e.g. after the heater has been off for a while.
+    pub fn reset(&mut self) {
+        self.integral = 0;
+        self.last_measured = None;
+        self.output = 0;
+    }
+
+    /// Caps the output at `limit` per mille, in addition to `max_output`.
+    pub fn set_limit(&mut self, limit: u16) {
+        self.limit = limit;
+    }
+
+    /// The output of the last [`update`](Pid::update).
+    pub fn output(&self) -> u16 {
+        self.output
+    }
+
+    /// Runs the controller for one period and returns the new output.
+    pub fn update(&mut self, target: i32, measured: i32) -> u16 {
+        let Config {
+            gains,
+            max_output,
+            max_step,
+        } = self.config;
+        let limit = max_output.min(self.limit) as i32;
+
+        let error = target - measured;
+        let change = measured - self.last_measured.unwrap_or(measured);
+        self.last_measured = Some(measured);
+
+        let p = gains.kp.saturating_mul(error);
+        let d = -gains.kd.saturating_mul(change);
+        let unclamped = p.saturating_add(self.integral).saturating_add(d) / GAIN_ONE;
+        // Only integrate while the output could still make use of it.
+        let saturated = (unclamped >= limit && error > 0) || (unclamped <= 0 && error < 0);
+        if !saturated {
+            self.integral = self.integral.saturating_add(gains.ki.saturating_mul(error));
+        }
+        self.integral = self.integral.clamp(0, limit * GAIN_ONE);
+
+        let previous = self.output as i32;
+        let output = (p.saturating_add(self.integral).saturating_add(d) / GAIN_ONE)
+            .clamp(previous - max_step as i32, previous + max_step as i32)
+            .clamp(0, limit);
+        self.output = output as u16;
+        self.output
+    }
+}
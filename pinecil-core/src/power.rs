@@ -0,0 +1,107 @@
+//! Classification of the input supply, and whether it is good enough to
+//! heat the tip.
+//!
+//! The supply is only known by its voltage here. A USB PD charger gives one
+//! of the fixed PD voltages, and anything else, e.g. a DC supply on the
+//! breakout board, counts as DC. A DC supply which happens to be at a PD
+//! voltage can't be told apart from PD this way.
+
+use core::fmt;
+
+/// How far a PD supply may be from its nominal voltage. The PD spec allows
+/// 5%, plus the drop in the cable and the divider tolerance.
+const TOLERANCE_PERCENT: u32 = 8;
+
+/// Below this the heater is not turned on: at 5 V the tip gets about 3 W,
+/// which is too little to be of use, and USB ports without PD should not be
+/// loaded that much anyway.
+pub const MIN_HEAT_MV: u32 = 8_000;
+/// Once refused, the supply has to come back up by this much before the
+/// heater is allowed again, so that a supply sagging under the load does
+/// not turn the heater on and off.
+pub const HYSTERESIS_MV: u32 = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// Plain USB at 5 V.
+    Usb,
+    /// USB PD at the given fixed voltage.
+    Pd(u8),
+    /// Anything else.
+    Dc,
+}
+
+impl Source {
+    /// The nominal voltages of USB and the PD fixed supplies, in volts.
+    const LEVELS: [u8; 5] = [5, 9, 12, 15, 20];
+
+    pub fn classify(mv: u32) -> Self {
+        for &volts in Self::LEVELS.iter() {
+            let nominal = volts as u32 * 1000;
+            let tolerance = nominal * TOLERANCE_PERCENT / 100;
+            let diff = if mv > nominal {
+                mv - nominal
+            } else {
+                nominal - mv
+            };
+            if diff <= tolerance {
+                return match volts {
+                    5 => Source::Usb,
+                    _ => Source::Pd(volts),
+                };
+            }
+        }
+        Source::Dc
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Source::Usb => f.write_str("USB"),
+            Source::Pd(volts) => write!(f, "PD {}V", volts),
+            Source::Dc => f.write_str("DC"),
+        }
+    }
+}
+
+/// Keeps track of the supply voltage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Supply {
+    mv: u32,
+    can_heat: bool,
+}
+
+impl Supply {
+    /// Starts out with heating refused until the first reading.
+    pub const fn new() -> Self {
+        Supply {
+            mv: 0,
+            can_heat: false,
+        }
+    }
+
+    /// Takes a new reading of the supply, in millivolts.
+    pub fn update(&mut self, mv: u32) {
+        self.mv = mv;
+        let threshold = if self.can_heat {
+            MIN_HEAT_MV
+        } else {
+            MIN_HEAT_MV + HYSTERESIS_MV
+        };
+        self.can_heat = mv >= threshold;
+    }
+
+    pub fn mv(&self) -> u32 {
+        self.mv
+    }
+
+    pub fn source(&self) -> Source {
+        Source::classify(self.mv)
+    }
+
+    /// Whether the supply is high enough to turn on the heater.
+    pub fn can_heat(&self) -> bool {
+        self.can_heat
+    }
+}
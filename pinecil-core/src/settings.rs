@@ -0,0 +1,140 @@
+//! User settings, and their layout in the settings page of the flash.
+//!
+//! The settings live in the last page of the flash ([`image::SETTINGS_ADDR`]),
+//! which is outside of the application region so that updating the firmware
+//! does not erase them. They are stored as:
+//!
+//! ```text
+//! 0   magic, "PCST"
+//! 4   version (u16)
+//! 6   presets (3 x i16)
+//! 12  boost temperature (i16)
+//! 14  step (i16)
+//! 16  fast step (i16)
+//! 18  language (u8), see `Language::index`
+//! 19  padding, 0xFF
+//! 20  CRC-32 of the bytes above
+//! ```
+//!
+//! All temperatures are in tenths of a degree and all values little-endian.
+//! An erased page, a page from another version or a corrupted one reads as
+//! an error, and the firmware falls back to [`Settings::DEFAULT`]. A language
+//! this firmware does not have reads as English, and so does the 0xFF
+//! padding which the language byte was before it was added.
+//!
+//! The rest of the page holds the [`counters`](crate::counters), from
+//! [`counters::OFFSET`](crate::counters::OFFSET) on.
+//!
+//! [`image::SETTINGS_ADDR`]: crate::image::SETTINGS_ADDR
+
+use crate::{image::crc32, lang::Language};
+
+/// Number of temperature presets.
+pub const PRESETS: usize = 3;
+
+/// Lowest temperature which can be set, apart from 0 for off.
+pub const MIN_TEMP: i32 = 1000;
+/// Highest temperature which can be set.
+pub const MAX_TEMP: i32 = 4500;
+
+/// The choices for [`Settings::step`].
+pub const STEPS: [i32; 3] = [10, 50, 100];
+/// The choices for [`Settings::fast_step`].
+pub const FAST_STEPS: [i32; 3] = [50, 100, 250];
+
+/// "PCST" in little-endian.
+const MAGIC: u32 = 0x5453_4350;
+const VERSION: u16 = 1;
+/// Length of the stored settings, including the CRC.
+pub const LEN: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// Temperatures cycled through by double-pressing '-'.
+    pub presets: [i32; PRESETS],
+    /// Temperature while '+' is held down.
+    pub boost_temp: i32,
+    /// How much a press of '+' or '-' changes the temperature.
+    pub step: i32,
+    /// How much each repeat changes the temperature once a button has been
+    /// held for a while.
+    pub fast_step: i32,
+    /// The language of the UI.
+    pub language: Language,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Nothing stored yet, or something else than settings.
+    BadMagic,
+    /// Stored by another version of the firmware.
+    BadVersion,
+    BadCrc,
+    /// A value is out of its range.
+    BadValue,
+}
+
+impl Settings {
+    pub const DEFAULT: Settings = Settings {
+        presets: [2800, 3200, 3500],
+        boost_temp: 4000,
+        step: 10,
+        fast_step: 100,
+        language: Language::English,
+    };
+
+    pub fn to_bytes(&self) -> [u8; LEN] {
+        let mut buf = [0xff; LEN];
+        buf[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        buf[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        let [p0, p1, p2] = self.presets;
+        let values = [p0, p1, p2, self.boost_temp, self.step, self.fast_step];
+        for (chunk, &value) in buf[6..18].chunks_exact_mut(2).zip(&values) {
+            chunk.copy_from_slice(&(value as i16).to_le_bytes());
+        }
+        buf[18] = self.language.index();
+        let crc = crc32(&buf[0..20]);
+        buf[20..24].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    pub fn parse(bytes: &[u8; LEN]) -> Result<Self, Error> {
+        let word =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let half = |i: usize| i16::from_le_bytes([bytes[i], bytes[i + 1]]) as i32;
+        if word(0) != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        if half(4) as u16 != VERSION {
+            return Err(Error::BadVersion);
+        }
+        if crc32(&bytes[0..20]) != word(20) {
+            return Err(Error::BadCrc);
+        }
+        let settings = Settings {
+            presets: [half(6), half(8), half(10)],
+            boost_temp: half(12),
+            step: half(14),
+            fast_step: half(16),
+            language: Language::from_index(bytes[18]),
+        };
+        if !settings.is_valid() {
+            return Err(Error::BadValue);
+        }
+        Ok(settings)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        let temp = |t: &i32| (MIN_TEMP..=MAX_TEMP).contains(t);
+        self.presets.iter().all(temp)
+            && temp(&self.boost_temp)
+            && STEPS.contains(&self.step)
+            && FAST_STEPS.contains(&self.fast_step)
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings::DEFAULT
+    }
+}
@@ -0,0 +1,110 @@
+//! A small command shell for the UART.
+//!
+//! [`LineEditor`] collects what is typed into a line, and tells the firmware
+//! what to echo back so that a terminal shows it. Backspace works, and
+//! anything but printable ASCII is dropped. Once the line is entered, the
+//! firmware looks it up with [`Command::parse`] and prints the answer.
+
+/// The longest line, the rest is dropped.
+pub const LINE_LEN: usize = 32;
+
+pub const PROMPT: &str = "> ";
+
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+
+/// What to do about a byte received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit<'a> {
+    /// Send the byte back.
+    Echo(u8),
+    /// Take the last character off the screen, with "\x08 \x08".
+    Erase,
+    /// The line was entered.
+    Line(&'a str),
+    Nothing,
+}
+
+pub struct LineEditor {
+    buf: [u8; LINE_LEN],
+    len: usize,
+    /// Whether the last byte was a CR, so that the LF of a CRLF does not
+    /// enter an empty line.
+    after_cr: bool,
+}
+
+impl LineEditor {
+    pub const fn new() -> Self {
+        LineEditor {
+            buf: [0; LINE_LEN],
+            len: 0,
+            after_cr: false,
+        }
+    }
+
+    pub fn push(&mut self, byte: u8) -> Edit<'_> {
+        let after_cr = core::mem::replace(&mut self.after_cr, byte == b'\r');
+        match byte {
+            b'\n' if after_cr => Edit::Nothing,
+            b'\r' | b'\n' => {
+                let len = core::mem::take(&mut self.len);
+                // Only printable ASCII gets in.
+                Edit::Line(core::str::from_utf8(&self.buf[..len]).unwrap())
+            }
+            BACKSPACE | DELETE if self.len > 0 => {
+                self.len -= 1;
+                Edit::Erase
+            }
+            b' '..=b'~' if self.len < LINE_LEN => {
+                self.buf[self.len] = byte;
+                self.len += 1;
+                Edit::Echo(byte)
+            }
+            _ => Edit::Nothing,
+        }
+    }
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        LineEditor::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    Stack,
+    Counters,
+}
+
+impl Command {
+    pub const ALL: [Command; 3] = [Command::Help, Command::Stack, Command::Counters];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Help => "help",
+            Command::Stack => "stack",
+            Command::Counters => "counters",
+        }
+    }
+
+    /// One line for `help`.
+    pub fn description(self) -> &'static str {
+        match self {
+            Command::Help => "list the commands",
+            Command::Stack => "show the most stack used so far",
+            Command::Counters => "show the uptime, heating time and heat cycles",
+        }
+    }
+
+    /// The command on `line`, ignoring spaces around it. `None` for an
+    /// empty line as well as for an unknown command.
+    pub fn parse(line: &str) -> Option<Command> {
+        let line = line.trim();
+        Command::ALL
+            .iter()
+            .copied()
+            .find(|command| command.name() == line)
+    }
+}
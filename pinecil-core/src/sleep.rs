@@ -0,0 +1,112 @@
+//! Idle sleep: lowers the tip to a standby temperature when the iron has been
+//! left alone for a while, and turns the heater off after a longer time.
+//!
+//! Whether the iron is in use is judged from the BMA223. Its any-motion
+//! interrupt can't be used, as the interrupt pins are wired to JTAG (see
+//! `notes/01-JTAG.md`), so the samples are compared instead: the iron has
+//! moved once any axis is more than [`Config::threshold`] away from where it
+//! was at the last movement. Comparing against the last movement rather than
+//! the previous sample means that a slow tilt is noticed as well, while the
+//! noise of an iron lying in its stand is not.
+
+use crate::ui::Accel;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Change of any axis which counts as movement, in counts of 1/64 g.
+    pub threshold: u8,
+    /// Time without movement before going to standby.
+    pub standby_after_ms: u32,
+    /// Time without movement before turning the heater off, counted from the
+    /// last movement as well.
+    pub off_after_ms: u32,
+    /// Target while in standby, in tenths of a degree. A lower target is
+    /// kept as it is.
+    pub standby_temp: i32,
+}
+
+pub const DEFAULT_CONFIG: Config = Config {
+    threshold: 6,
+    standby_after_ms: 60_000,
+    off_after_ms: 600_000,
+    standby_temp: 1500,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// In use, heating to the target.
+    Active,
+    /// Left alone, kept at the standby temperature.
+    Standby,
+    /// Left alone for long, heater off.
+    Off,
+}
+
+pub struct Sleep {
+    config: Config,
+    state: State,
+    /// The reading at the last movement.
+    reference: Option<Accel>,
+    /// When the iron was last moved or a button was pressed.
+    last_activity_ms: u32,
+}
+
+impl Sleep {
+    pub fn new(config: Config, now_ms: u32) -> Self {
+        Sleep {
+            config,
+            state: State::Active,
+            reference: None,
+            last_activity_ms: now_ms,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Takes a new reading of the accelerometer, `None` if it could not be
+    /// read, and whether a button was pressed since the last update.
+    ///
+    /// Without a reading there is no telling whether the iron is in use, so
+    /// it is assumed to be.
+    pub fn update(&mut self, accel: Option<Accel>, pressed: bool, now_ms: u32) -> State {
+        let moved = match (accel, self.reference) {
+            (Some(accel), Some(reference)) => {
+                let threshold = self.config.threshold as i16;
+                let diff = |a: i8, b: i8| (a as i16 - b as i16).abs() > threshold;
+                diff(accel.x, reference.x)
+                    || diff(accel.y, reference.y)
+                    || diff(accel.z, reference.z)
+            }
+            // The first reading only sets the reference.
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        if moved || self.reference.is_none() {
+            self.reference = accel;
+        }
+        if moved || pressed {
+            self.last_activity_ms = now_ms;
+        }
+
+        let idle_ms = now_ms.wrapping_sub(self.last_activity_ms);
+        self.state = if idle_ms >= self.config.off_after_ms {
+            State::Off
+        } else if idle_ms >= self.config.standby_after_ms {
+            State::Standby
+        } else {
+            State::Active
+        };
+        self.state
+    }
+
+    /// The target to heat to, given the one set by the user.
+    pub fn target(&self, target: i32) -> i32 {
+        match self.state {
+            State::Active => target,
+            State::Standby => target.min(self.config.standby_temp),
+            State::Off => 0,
+        }
+    }
+}
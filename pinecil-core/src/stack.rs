@@ -0,0 +1,94 @@
+//! Stack usage, measured by painting.
+//!
+//! The stack is filled with [`PAINT`] at boot, from its bottom up to where
+//! it is at the time. Whatever the code writes on the stack later wipes the
+//! paint, so the paint left at the bottom shows how deep it has gone at most.
+//! A call with a buffer it never writes to can skip over the paint, so this
+//! is a good estimate rather than a bound.
+//!
+//! The lowest [`GUARD_WORDS`] words are the guard: once they are wiped, the
+//! stack has most likely gone further, over whatever is below it.
+
+/// What the stack is painted with.
+pub const PAINT: u32 = 0xaa55_aa55;
+
+/// The words at the bottom of the stack which it should never reach.
+pub const GUARD_WORDS: usize = 8;
+
+/// The usage of the stack which gets a warning by default, in percent.
+pub const WARN_PERCENT: u32 = 75;
+
+/// How much of the stack has been used at most, in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Usage {
+    pub peak: usize,
+    pub size: usize,
+}
+
+impl Usage {
+    /// Measures the painted `stack`, which goes from its bottom up.
+    pub fn measure(stack: impl ExactSizeIterator<Item = u32>) -> Self {
+        let size = stack.len() * 4;
+        let painted = stack.take_while(|&word| word == PAINT).count();
+        Usage {
+            peak: size - painted * 4,
+            size,
+        }
+    }
+
+    pub fn percent(&self) -> u32 {
+        if self.size == 0 {
+            return 100;
+        }
+        (self.peak * 100 / self.size) as u32
+    }
+
+    /// Whether the guard has been wiped.
+    pub fn overflowed(&self) -> bool {
+        self.size - self.peak < GUARD_WORDS * 4
+    }
+}
+
+impl core::fmt::Display for Usage {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "{} of {} bytes ({}%)",
+            self.peak,
+            self.size,
+            self.percent()
+        )
+    }
+}
+
+/// Tells once when the usage goes over a threshold.
+#[derive(Clone, Copy, Debug)]
+pub struct Watch {
+    threshold_percent: u32,
+    warned: bool,
+}
+
+impl Watch {
+    pub const fn new(threshold_percent: u32) -> Self {
+        Watch {
+            threshold_percent,
+            warned: false,
+        }
+    }
+
+    /// Whether `usage` has just gone over the threshold. It is only true
+    /// once, as the peak never goes back down.
+    pub fn check(&mut self, usage: Usage) -> bool {
+        if self.warned || usage.percent() < self.threshold_percent {
+            return false;
+        }
+        self.warned = true;
+        true
+    }
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Watch::new(WARN_PERCENT)
+    }
+}
@@ -0,0 +1,236 @@
+//! Bitmap fonts with glyphs of any size, drawn on an `embedded-graphics`
+//! [`DrawTarget`].
+//!
+//! Each glyph has its own bitmap size, offset and advance, so the same code
+//! draws proportional text and fonts of any height. There is no kerning. Fonts are converted
+//! from BDF files by `fontconv` in `tools/`.
+//!
+//! File layout (all numbers little-endian):
+//!
+//! ```text
+//! "PFNT"  magic
+//! u8      line height
+//! u8      baseline, in rows from the top of the line
+//! u16     number of ranges
+//! u16     number of glyphs
+//! ranges, sorted by code point:
+//!     u16 first code point
+//!     u16 number of code points
+//!     u16 index of the glyph of the first code point
+//! glyphs:
+//!     u16 offset of the bitmap, from the start of the bitmaps
+//!     u8  bitmap width
+//!     u8  bitmap height
+//!     i8  x offset of the bitmap from the pen position
+//!     i8  y offset of the bitmap from the top of the line
+//!     u8  advance
+//! bitmaps:
+//!     ... rows from the top, one bit per pixel with the leftmost pixel in
+//!         the most significant bit, not padded between rows
+//! ```
+//!
+//! Only the Basic Multilingual Plane is supported, which holds the scripts
+//! the UI could be translated to. Characters which the font does not have
+//! are drawn as '?'.
+
+use embedded_graphics::{geometry::Point, pixelcolor::BinaryColor, DrawTarget};
+
+use crate::display;
+
+pub const MAGIC: &[u8; 4] = b"PFNT";
+pub const HEADER_LEN: usize = 10;
+pub const RANGE_LEN: usize = 6;
+pub const GLYPH_LEN: usize = 7;
+
+/// The X11 `5x8` font, cropped for proportional text: one line of 8 rows.
+pub const SMALL: &[u8] = include_bytes!("../fonts/small.pfnt");
+/// The X11 bold `6x13` font, cropped for proportional text: 13 rows.
+pub const MEDIUM: &[u8] = include_bytes!("../fonts/medium.pfnt");
+
+/// Drawn for characters which are not in the font.
+const REPLACEMENT: char = '?';
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    BadMagic,
+    Truncated,
+    /// A range points past the glyphs, or the ranges are not sorted.
+    BadRange,
+    /// A bitmap lies outside of the data.
+    BadGlyph,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Font<'a> {
+    line_height: u8,
+    baseline: u8,
+    ranges: &'a [u8],
+    glyphs: &'a [u8],
+    bitmaps: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Glyph<'a> {
+    pub width: u8,
+    pub height: u8,
+    pub x_offset: i8,
+    pub y_offset: i8,
+    pub advance: u8,
+    bitmap: &'a [u8],
+}
+
+impl<'a> Glyph<'a> {
+    /// Returns whether the pixel at `(x, y)` of the bitmap is lit.
+    pub fn pixel(&self, x: u8, y: u8) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let bit = y as usize * self.width as usize + x as usize;
+        self.bitmap[bit / 8] & (0x80 >> (bit % 8)) != 0
+    }
+}
+
+impl<'a> Font<'a> {
+    /// Checks the whole font, so that looking up and drawing glyphs can't
+    /// fail later.
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if data.get(0..4) != Some(&MAGIC[..]) {
+            return Err(Error::BadMagic);
+        }
+        if data.len() < HEADER_LEN {
+            return Err(Error::Truncated);
+        }
+        let range_count = u16_at(data, 6) as usize;
+        let glyph_count = u16_at(data, 8) as usize;
+        let glyphs_start = HEADER_LEN + range_count * RANGE_LEN;
+        let bitmaps_start = glyphs_start + glyph_count * GLYPH_LEN;
+        if data.len() < bitmaps_start {
+            return Err(Error::Truncated);
+        }
+        let font = Font {
+            line_height: data[4],
+            baseline: data[5],
+            ranges: &data[HEADER_LEN..glyphs_start],
+            glyphs: &data[glyphs_start..bitmaps_start],
+            bitmaps: &data[bitmaps_start..],
+        };
+
+        let mut next_code = 0;
+        for range in font.ranges.chunks_exact(RANGE_LEN) {
+            let first = u16_at(range, 0) as usize;
+            let count = u16_at(range, 2) as usize;
+            let index = u16_at(range, 4) as usize;
+            if first < next_code || index + count > glyph_count {
+                return Err(Error::BadRange);
+            }
+            next_code = first + count;
+        }
+        for glyph in font.glyphs.chunks_exact(GLYPH_LEN) {
+            let offset = u16_at(glyph, 0) as usize;
+            let bits = glyph[2] as usize * glyph[3] as usize;
+            if offset + (bits + 7) / 8 > font.bitmaps.len() {
+                return Err(Error::BadGlyph);
+            }
+        }
+        Ok(font)
+    }
+
+    /// Distance from one line to the next.
+    pub fn line_height(&self) -> u32 {
+        self.line_height as u32
+    }
+
+    /// Rows from the top of the line to the baseline.
+    pub fn baseline(&self) -> u32 {
+        self.baseline as u32
+    }
+
+    fn glyph_at(&self, index: usize) -> Glyph<'a> {
+        let g = &self.glyphs[index * GLYPH_LEN..][..GLYPH_LEN];
+        let offset = u16_at(g, 0) as usize;
+        let len = (g[2] as usize * g[3] as usize + 7) / 8;
+        Glyph {
+            width: g[2],
+            height: g[3],
+            x_offset: g[4] as i8,
+            y_offset: g[5] as i8,
+            advance: g[6],
+            bitmap: &self.bitmaps[offset..offset + len],
+        }
+    }
+
+    /// Returns the glyph for `c`, if the font has one.
+    pub fn glyph(&self, c: char) -> Option<Glyph<'a>> {
+        let code = c as u32;
+        // The ranges are sorted, but there are only a handful of them.
+        for range in self.ranges.chunks_exact(RANGE_LEN) {
+            let first = u16_at(range, 0) as u32;
+            let count = u16_at(range, 2) as u32;
+            if code < first {
+                break;
+            }
+            if code < first + count {
+                let index = u16_at(range, 4) as u32 + code - first;
+                return Some(self.glyph_at(index as usize));
+            }
+        }
+        None
+    }
+
+    fn glyph_or_replacement(&self, c: char) -> Option<Glyph<'a>> {
+        self.glyph(c).or_else(|| self.glyph(REPLACEMENT))
+    }
+
+    /// Width of `text` when drawn, in pixels.
+    pub fn measure(&self, text: &str) -> u32 {
+        text.chars()
+            .filter_map(|c| self.glyph_or_replacement(c))
+            .map(|g| g.advance as u32)
+            .sum()
+    }
+
+    /// Draws `text` with the top left corner of the line at `position`, and
+    /// returns where the next character would go. Only the lit pixels of the
+    /// glyphs are drawn.
+    pub fn draw<D>(&self, text: &str, position: Point, target: &mut D) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        let mut pen = position;
+        for c in text.chars() {
+            let glyph = match self.glyph_or_replacement(c) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            let origin = pen + Point::new(glyph.x_offset as i32, glyph.y_offset as i32);
+            let points = (0..glyph.height).flat_map(move |y| {
+                (0..glyph.width)
+                    .filter(move |&x| glyph.pixel(x, y))
+                    .map(move |x| origin + Point::new(x as i32, y as i32))
+            });
+            display::draw_lit(target, points)?;
+            pen.x += glyph.advance as i32;
+        }
+        Ok(pen)
+    }
+
+    /// Draws `text` centred on column `centre_x`, with the top of the line at
+    /// row `top`.
+    pub fn draw_centred<D>(
+        &self,
+        text: &str,
+        centre_x: i32,
+        top: i32,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        let x = centre_x - self.measure(text) as i32 / 2;
+        self.draw(text, Point::new(x, top), target)
+    }
+}
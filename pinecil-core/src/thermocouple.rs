@@ -0,0 +1,94 @@
+//! Type K thermocouple conversion, for the tip.
+//!
+//! The thermocouple measures the difference between the tip and the cold
+//! junction, which is where the tip meets the copper of the handle. To get
+//! the tip temperature, the voltage of the cold junction at its own
+//! temperature is added to the measured voltage, and the sum is converted
+//! back to a temperature.
+//!
+//! Rather than evaluating the NIST ITS-90 polynomials, which need floating
+//! point and `exp`, the voltage is interpolated from a table of the NIST
+//! reference voltages every 10 °C. This is accurate to better than 0.1 °C.
+//!
+//! Temperatures are in tenths of a degree Celsius and voltages in microvolts.
+
+/// Temperature of the first table entry, in °C.
+const TABLE_MIN: i32 = -50;
+/// Distance between table entries, in °C.
+const TABLE_STEP: i32 = 10;
+
+/// Lowest temperature which can be converted, in tenths of a degree.
+pub const MIN_TEMP: i32 = TABLE_MIN * 10;
+/// Highest temperature which can be converted, in tenths of a degree.
+pub const MAX_TEMP: i32 = (TABLE_MIN + TABLE_STEP * (TABLE_UV.len() as i32 - 1)) * 10;
+
+/// Thermocouple voltage in microvolts from -50 °C to 1370 °C, every 10 °C,
+/// with the reference junction at 0 °C.
+const TABLE_UV: [i32; 143] = [
+    -1889, -1527, -1156, -778, -392, 0, 397, 798, 1203, 1612, 2023, 2436, 2851, 3267, 3682, 4096,
+    4509, 4920, 5328, 5735, 6138, 6540, 6941, 7340, 7739, 8138, 8539, 8940, 9343, 9747, 10153,
+    10561, 10971, 11382, 11795, 12209, 12624, 13040, 13457, 13874, 14293, 14713, 15133, 15554,
+    15975, 16397, 16820, 17243, 17667, 18091, 18516, 18941, 19366, 19792, 20218, 20644, 21071,
+    21497, 21924, 22350, 22776, 23203, 23629, 24055, 24480, 24905, 25330, 25755, 26179, 26602,
+    27025, 27447, 27869, 28289, 28710, 29129, 29548, 29965, 30382, 30798, 31213, 31628, 32041,
+    32453, 32865, 33275, 33685, 34093, 34501, 34908, 35313, 35718, 36121, 36524, 36925, 37326,
+    37725, 38124, 38522, 38918, 39314, 39708, 40101, 40494, 40885, 41276, 41665, 42053, 42440,
+    42826, 43211, 43595, 43978, 44359, 44740, 45119, 45497, 45873, 46249, 46623, 46995, 47367,
+    47737, 48105, 48473, 48838, 49202, 49565, 49926, 50286, 50644, 51000, 51355, 51708, 52060,
+    52410, 52759, 53106, 53451, 53795, 54138, 54479, 54819,
+];
+
+/// Divides, rounding to the nearest integer. `n` must not be negative.
+fn div_round(n: i32, d: i32) -> i32 {
+    (n + d / 2) / d
+}
+
+/// Returns the thermocouple voltage at `temp` with the reference junction
+/// at 0 °C. Temperatures outside of `MIN_TEMP..=MAX_TEMP` are clamped.
+pub fn emf_uv(temp: i32) -> i32 {
+    let offset = temp.clamp(MIN_TEMP, MAX_TEMP) - MIN_TEMP;
+    let step = TABLE_STEP * 10;
+    let i = (offset / step) as usize;
+    let frac = offset % step;
+    if frac == 0 {
+        return TABLE_UV[i];
+    }
+    let (lo, hi) = (TABLE_UV[i], TABLE_UV[i + 1]);
+    lo + div_round((hi - lo) * frac, step)
+}
+
+/// Returns the temperature for a thermocouple voltage with the reference
+/// junction at 0 °C, or `None` if it is out of range, which usually means
+/// that there is no tip or that it is broken.
+pub fn temperature(emf_uv: i32) -> Option<i32> {
+    if emf_uv < TABLE_UV[0] || emf_uv > TABLE_UV[TABLE_UV.len() - 1] {
+        return None;
+    }
+    // Index of the last entry not above `emf_uv`.
+    let i = match TABLE_UV.binary_search(&emf_uv) {
+        Ok(i) => return Some((TABLE_MIN + TABLE_STEP * i as i32) * 10),
+        Err(i) => i - 1,
+    };
+    let (lo, hi) = (TABLE_UV[i], TABLE_UV[i + 1]);
+    let base = (TABLE_MIN + TABLE_STEP * i as i32) * 10;
+    Some(base + div_round((emf_uv - lo) * TABLE_STEP * 10, hi - lo))
+}
+
+/// Returns the tip temperature from the measured thermocouple voltage and
+/// the temperature of the cold junction.
+pub fn tip_temperature(tip_uv: i32, cold_junction: i32) -> Option<i32> {
+    temperature(tip_uv + emf_uv(cold_junction))
+}
+
+/// Converts the output of the TMP36 handle temperature sensor, which is
+/// 500 mV at 0 °C plus 10 mV per degree.
+pub fn tmp36_temperature(uv: i32) -> i32 {
+    (uv - 500_000) / 1000
+}
+
+/// Converts the temperature register of the BMA223 accelerometer, which is
+/// 0.5 °C per count around 23 °C. It sits further from the tip than the
+/// TMP36, but can stand in for it as the cold junction.
+pub fn bma223_temperature(raw: i8) -> i32 {
+    230 + raw as i32 * 5
+}
@@ -0,0 +1,202 @@
+//! Software timers on a millisecond clock, so that different jobs can run at
+//! their own rates from one loop instead of nested delays.
+//!
+//! The timers keep a value of the caller's choosing, usually an enum of the
+//! jobs (or a function pointer), and [`Timers::poll`] hands it back once the
+//! timer expires. The loop polls until there is nothing left, runs the jobs
+//! it got back, and waits for the next tick of the clock.
+//!
+//! They are kept in a hashed timing wheel: a ring of [`SLOTS`] lists, one per
+//! millisecond, with each timer in the list of its deadline modulo the ring.
+//! Polling only walks the lists of the milliseconds which went by, so the
+//! cost does not grow with the number of timers waiting for later. Times are
+//! `u32` milliseconds which wrap around after 49 days, so no timer may be
+//! longer than half of that.
+
+/// Number of lists in the wheel.
+pub const SLOTS: usize = 32;
+
+/// Refers to a started timer. Stays valid for a periodic timer until it is
+/// cancelled, and for a one-shot timer until it expires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerId {
+    index: usize,
+    /// Tells a timer apart from a later one in the same entry.
+    generation: u32,
+}
+
+/// All the timers are taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Full;
+
+#[derive(Clone, Copy, Debug)]
+struct Entry<T> {
+    value: T,
+    deadline_ms: u32,
+    /// 0 for a one-shot timer.
+    period_ms: u32,
+    /// The next entry in the same slot.
+    next: Option<usize>,
+}
+
+/// Up to `N` timers, each holding a `T`.
+pub struct Timers<T: Copy, const N: usize> {
+    entries: [Option<Entry<T>>; N],
+    generations: [u32; N],
+    slots: [Option<usize>; SLOTS],
+    /// The time of the last poll, which new timers count from.
+    now_ms: u32,
+    /// Up to when the slots have been walked.
+    walked_ms: u32,
+}
+
+/// Whether `deadline_ms` has come at `now_ms`, allowing for wrap-around.
+fn due(deadline_ms: u32, now_ms: u32) -> bool {
+    (now_ms.wrapping_sub(deadline_ms) as i32) >= 0
+}
+
+fn slot(ms: u32) -> usize {
+    ms as usize % SLOTS
+}
+
+impl<T: Copy, const N: usize> Timers<T, N> {
+    pub fn new(now_ms: u32) -> Self {
+        Timers {
+            entries: [None; N],
+            generations: [0; N],
+            slots: [None; SLOTS],
+            now_ms,
+            walked_ms: now_ms,
+        }
+    }
+
+    /// Starts a timer which expires once, `delay_ms` after the last poll.
+    /// It expires on the next poll at the earliest, even with a delay of 0.
+    pub fn start_once(&mut self, delay_ms: u32, value: T) -> Result<TimerId, Full> {
+        self.start(delay_ms, 0, value)
+    }
+
+    /// Starts a timer which expires every `period_ms`, counting from the
+    /// last poll. When polls are late, it keeps to its original rhythm, and
+    /// periods which were missed entirely are skipped rather than made up.
+    pub fn start_periodic(&mut self, period_ms: u32, value: T) -> Result<TimerId, Full> {
+        self.start(period_ms, period_ms.max(1), value)
+    }
+
+    fn start(&mut self, delay_ms: u32, period_ms: u32, value: T) -> Result<TimerId, Full> {
+        let index = self.entries.iter().position(|e| e.is_none()).ok_or(Full)?;
+        let deadline_ms = self.now_ms.wrapping_add(delay_ms.max(1));
+        self.entries[index] = Some(Entry {
+            value,
+            deadline_ms,
+            period_ms,
+            next: None,
+        });
+        self.link(index);
+        Ok(TimerId {
+            index,
+            generation: self.generations[index],
+        })
+    }
+
+    /// Whether the timer is still waiting to expire.
+    pub fn is_running(&self, id: TimerId) -> bool {
+        self.generations[id.index] == id.generation && self.entries[id.index].is_some()
+    }
+
+    /// Stops the timer, and returns its value if it was still running.
+    pub fn cancel(&mut self, id: TimerId) -> Option<T> {
+        if !self.is_running(id) {
+            return None;
+        }
+        self.unlink(id.index);
+        self.free(id.index)
+    }
+
+    /// The earliest deadline of the running timers, e.g. for how long the
+    /// board may sleep.
+    pub fn next_deadline(&self) -> Option<u32> {
+        self.entries
+            .iter()
+            .flatten()
+            .map(|e| e.deadline_ms)
+            .min_by_key(|&deadline_ms| deadline_ms.wrapping_sub(self.now_ms))
+    }
+
+    /// Returns the value of a timer which has expired by `now_ms`, or `None`
+    /// once there are no more. Call it in a loop until it returns `None`.
+    /// Timers which expired at the same time come back in no particular
+    /// order.
+    pub fn poll(&mut self, now_ms: u32) -> Option<T> {
+        self.now_ms = now_ms;
+        // After a long gap, each slot only needs walking once.
+        if now_ms.wrapping_sub(self.walked_ms) > SLOTS as u32 {
+            self.walked_ms = now_ms.wrapping_sub(SLOTS as u32);
+        }
+        while self.walked_ms != now_ms {
+            let ms = self.walked_ms.wrapping_add(1);
+            if let Some(value) = self.take_due(slot(ms), now_ms) {
+                return Some(value);
+            }
+            self.walked_ms = ms;
+        }
+        None
+    }
+
+    /// Takes the first timer in `slot` which is due at `now_ms`.
+    fn take_due(&mut self, slot: usize, now_ms: u32) -> Option<T> {
+        let mut cursor = self.slots[slot];
+        while let Some(index) = cursor {
+            let entry = self.entries[index].unwrap();
+            if due(entry.deadline_ms, now_ms) {
+                self.unlink(index);
+                if entry.period_ms == 0 {
+                    return self.free(index);
+                }
+                let mut deadline_ms = entry.deadline_ms;
+                while due(deadline_ms, now_ms) {
+                    deadline_ms = deadline_ms.wrapping_add(entry.period_ms);
+                }
+                self.entries[index] = Some(Entry {
+                    deadline_ms,
+                    ..entry
+                });
+                self.link(index);
+                return Some(entry.value);
+            }
+            cursor = entry.next;
+        }
+        None
+    }
+
+    /// Puts the entry at the head of the list of its deadline.
+    fn link(&mut self, index: usize) {
+        let entry = self.entries[index].as_mut().unwrap();
+        let slot = slot(entry.deadline_ms);
+        entry.next = self.slots[slot];
+        self.slots[slot] = Some(index);
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let entry = self.entries[index].unwrap();
+        let slot = slot(entry.deadline_ms);
+        if self.slots[slot] == Some(index) {
+            self.slots[slot] = entry.next;
+            return;
+        }
+        let mut cursor = self.slots[slot];
+        while let Some(i) = cursor {
+            let previous = self.entries[i].as_mut().unwrap();
+            if previous.next == Some(index) {
+                previous.next = entry.next;
+                return;
+            }
+            cursor = previous.next;
+        }
+    }
+
+    fn free(&mut self, index: usize) -> Option<T> {
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.entries[index].take().map(|e| e.value)
+    }
+}
@@ -0,0 +1,82 @@
+//! First-order thermal model of the tip, for testing the temperature control
+//! on the host.
+//!
+//! The tip is a single lump with a heat capacity, heated by the heater and
+//! losing heat to the air through a thermal resistance. This ignores the lag
+//! between the heater and the thermocouple, and that the losses grow faster
+//! than linearly when hot, but it is close enough to catch a controller
+//! which overshoots or winds up.
+//!
+//! Temperatures are in tenths of a degree Celsius, like everywhere else.
+
+/// Physical properties of a tip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TipParams {
+    /// Resistance of the heater, in milliohms.
+    pub resistance_mohm: u32,
+    /// Heat capacity of the tip, in millijoules per kelvin.
+    pub heat_capacity_mj_per_k: u32,
+    /// Thermal resistance from the tip to the air, in kelvins per watt.
+    pub thermal_resistance_k_per_w: u32,
+}
+
+/// Roughly a Pinecil (TS100 style) tip: 8 Ω, heats up at about 20 °C/s at
+/// 50 W, and needs about 6 W to stay at 320 °C.
+pub const PINECIL_TIP: TipParams = TipParams {
+    resistance_mohm: 8_000,
+    heat_capacity_mj_per_k: 2_500,
+    thermal_resistance_k_per_w: 50,
+};
+
+/// Heater power in milliwatts at `duty` per mille from a `supply_mv` supply.
+pub fn heater_power_mw(resistance_mohm: u32, supply_mv: u32, duty: u16) -> u32 {
+    let full = supply_mv as u64 * supply_mv as u64 / resistance_mohm as u64;
+    (full * duty.min(1000) as u64 / 1000) as u32
+}
+
+#[derive(Clone, Debug)]
+pub struct TipModel {
+    params: TipParams,
+    /// Ambient temperature, in thousandths of a degree.
+    ambient_mc: i64,
+    /// Tip temperature, in thousandths of a degree.
+    temp_mc: i64,
+    /// Heat lost to something touching the tip, in milliwatts.
+    load_mw: u32,
+}
+
+impl TipModel {
+    /// Creates a tip which has cooled down to `ambient`.
+    pub fn new(params: TipParams, ambient: i32) -> Self {
+        TipModel {
+            params,
+            ambient_mc: ambient as i64 * 100,
+            temp_mc: ambient as i64 * 100,
+            load_mw: 0,
+        }
+    }
+
+    /// The tip temperature, in tenths of a degree.
+    pub fn temperature(&self) -> i32 {
+        (self.temp_mc / 100) as i32
+    }
+
+    /// Draws `load_mw` from the tip on top of the losses to the air, like a
+    /// large joint would.
+    pub fn set_load(&mut self, load_mw: u32) {
+        self.load_mw = load_mw;
+    }
+
+    /// Runs the model for `ms` milliseconds with the heater at `duty` per
+    /// mille from a `supply_mv` supply.
+    pub fn step(&mut self, duty: u16, supply_mv: u32, ms: u32) {
+        let p = &self.params;
+        let heat_mw = heater_power_mw(p.resistance_mohm, supply_mv, duty) as i64;
+        // Kelvins per watt is the same as millikelvins per milliwatt.
+        let loss_mw = (self.temp_mc - self.ambient_mc) / p.thermal_resistance_k_per_w as i64
+            + self.load_mw as i64;
+        // Milliwatts times milliseconds are microjoules, and microjoules per
+        // millijoule per kelvin are millikelvins.
+        self.temp_mc += (heat_mw - loss_mw) * ms as i64 / p.heat_capacity_mj_per_k as i64;
+    }
+}
@@ -0,0 +1,326 @@
+//! The screens of the OLED demo, kept free of hardware so that the same code
+//! runs on the Pinecil and in the host simulator.
+//!
+//! The board calls [`OledDemo::update`] every [`TICK_MS`] with the current
+//! state of the inputs, and redraws when it returns `true`.
+//!
+//! Each screen but the animation has one line, with a status line for the
+//! supply below it: the voltage and the kind of supply, or that it is too low
+//! to heat the tip.
+
+use core::fmt::Write;
+
+use embedded_graphics::{geometry::Point, pixelcolor::BinaryColor, DrawTarget};
+
+use crate::{
+    anim::{Animation, Frames},
+    display::{self, BUF_LEN, WIDTH},
+    lang::{Language, Text},
+    power::Supply,
+    text::{self, Font},
+};
+
+/// How often the board is expected to call `update`.
+pub const TICK_MS: u16 = 25;
+
+/// How long each character is shown for on the alphabet screen.
+const CHAR_MS: u16 = 100;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Buttons {
+    pub plus: bool,
+    pub minus: bool,
+}
+
+/// Raw reading of the BMA223 accelerometer, 64 counts per g.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Accel {
+    pub x: i8,
+    pub y: i8,
+    pub z: i8,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Inputs {
+    pub buttons: Buttons,
+    /// `None` when the board has no accelerometer to read.
+    pub accel: Option<Accel>,
+    /// Input voltage in millivolts, `None` when it can't be measured.
+    pub supply_mv: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Screen {
+    Animation,
+    Hello,
+    Alphabet,
+    Brightness,
+    Accel,
+}
+
+impl Screen {
+    fn next(self) -> Self {
+        match self {
+            Screen::Animation => Screen::Hello,
+            Screen::Hello => Screen::Alphabet,
+            Screen::Alphabet => Screen::Brightness,
+            Screen::Brightness => Screen::Accel,
+            Screen::Accel => Screen::Animation,
+        }
+    }
+}
+
+/// '-' switches to the next screen and '+' turns up the brightness.
+pub struct OledDemo<'a> {
+    screen: Screen,
+    brightness: u8,
+    previous: Buttons,
+    accel: Option<Accel>,
+    supply: Option<Supply>,
+    /// Time left until the next animation frame or character.
+    wait_ms: u16,
+
+    anim: Animation<'a>,
+    frames: Frames<'a>,
+    anim_buf: [u8; BUF_LEN],
+
+    /// Number of characters shown on the alphabet screen.
+    chars: usize,
+
+    language: Language,
+    font: Font<'static>,
+}
+
+impl<'a> OledDemo<'a> {
+    pub fn new(anim: Animation<'a>, language: Language) -> Self {
+        OledDemo {
+            screen: Screen::Animation,
+            brightness: 0x0f,
+            previous: Buttons::default(),
+            accel: None,
+            supply: None,
+            wait_ms: 0,
+            anim,
+            frames: anim.frames(),
+            anim_buf: [0; BUF_LEN],
+            chars: 0,
+            language,
+            // Checked by the tests on the host.
+            font: Font::parse(text::SMALL).unwrap(),
+        }
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    /// Advances the UI by `elapsed_ms`. Returns whether the display needs to
+    /// be redrawn.
+    pub fn update(&mut self, inputs: Inputs, elapsed_ms: u16) -> bool {
+        let pressed = Buttons {
+            plus: inputs.buttons.plus && !self.previous.plus,
+            minus: inputs.buttons.minus && !self.previous.minus,
+        };
+        self.previous = inputs.buttons;
+
+        let mut redraw = false;
+        if pressed.minus {
+            self.enter(self.screen.next());
+            redraw = true;
+        }
+        if pressed.plus {
+            self.brightness = self.brightness.wrapping_add(16);
+            redraw = true;
+        }
+        if self.screen == Screen::Accel && inputs.accel != self.accel {
+            redraw = true;
+        }
+        self.accel = inputs.accel;
+        let supply = inputs.supply_mv.map(|mv| {
+            let mut supply = self.supply.unwrap_or_default();
+            supply.update(mv);
+            supply
+        });
+        if self.screen != Screen::Animation && supply != self.supply {
+            redraw = true;
+        }
+        self.supply = supply;
+
+        match self.screen {
+            Screen::Animation | Screen::Alphabet => {
+                self.wait_ms = self.wait_ms.saturating_sub(elapsed_ms);
+                if self.wait_ms == 0 {
+                    self.advance();
+                    redraw = true;
+                }
+            }
+            _ => {}
+        }
+        redraw
+    }
+
+    fn enter(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.wait_ms = 0;
+        self.frames = self.anim.frames();
+        self.chars = 0;
+    }
+
+    fn advance(&mut self) {
+        match self.screen {
+            Screen::Animation => {
+                let frame = match self.frames.next() {
+                    Some(frame) => frame,
+                    None => {
+                        self.frames = self.anim.frames();
+                        self.frames.next().unwrap()
+                    }
+                };
+                // The animation was validated when it was parsed, so only a
+                // corrupted frame further on can fail. Show it anyway.
+                let delay_ms = match frame {
+                    Ok(frame) => {
+                        let _ = frame.decode_into(&mut self.anim_buf);
+                        frame.delay_ms
+                    }
+                    Err(_) => 0,
+                };
+                self.wait_ms = delay_ms.max(TICK_MS);
+            }
+            Screen::Alphabet => {
+                self.chars += 1;
+                self.wait_ms = CHAR_MS;
+            }
+            _ => {}
+        }
+    }
+
+    /// Draws one line of text, with its top at row `y`.
+    fn draw_line<D>(&self, target: &mut D, y: i32, line: &str) -> Result<(), D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        self.font.draw(line, Point::new(0, y), target).map(|_| ())
+    }
+
+    /// Draws one line of translated text, with its top at row `y`.
+    fn draw_text<D>(&self, target: &mut D, y: i32, text: Text) -> Result<(), D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        self.draw_line(target, y, self.language.text(text))
+    }
+
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        target.clear(BinaryColor::Off)?;
+        match self.screen {
+            // The animation takes up the whole display.
+            Screen::Animation => return display::draw_pages(target, &self.anim_buf),
+            Screen::Hello => self.draw_text(target, 0, Text::Hello)?,
+            Screen::Alphabet => self.draw_alphabet(target)?,
+            Screen::Brightness => {
+                let mut line = Line::new();
+                let text = self.language.text(Text::Brightness);
+                let _ = write!(line, "{} {}", text, self.brightness);
+                self.draw_line(target, 0, line.as_str())?;
+            }
+            Screen::Accel => match self.accel {
+                Some(a) => {
+                    let mut line = Line::new();
+                    let _ = write!(line, "X{:+} Y{:+} Z{:+}", a.x, a.y, a.z);
+                    self.draw_line(target, 0, line.as_str())?;
+                }
+                None => self.draw_text(target, 0, Text::NoAccelerometer)?,
+            },
+        }
+        self.draw_status(target)
+    }
+
+    /// The status line: the supply voltage and type, or that it is too low
+    /// for heating.
+    fn draw_status<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        let supply = match self.supply {
+            Some(supply) => supply,
+            None => return self.draw_text(target, 8, Text::NoSupply),
+        };
+        let mut line = Line::new();
+        let mv = supply.mv();
+        let _ = write!(line, "{}.{:02}V ", mv / 1000, mv % 1000 / 10);
+        let _ = if supply.can_heat() {
+            write!(line, "{}", supply.source())
+        } else {
+            line.write_str(self.language.text(Text::TooLow))
+        };
+        self.draw_line(target, 8, line.as_str())
+    }
+
+    /// Types out letters and digits, one per cell, starting over at the left
+    /// once the line is full.
+    fn draw_alphabet<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<BinaryColor>,
+    {
+        const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+        const CELL_WIDTH: usize = 6;
+        const CELLS: usize = WIDTH / CELL_WIDTH;
+
+        let chars = self.chars;
+        let shown = match chars {
+            0 => 0,
+            _ => (chars - 1) % CELLS + 1,
+        };
+        for (cell, i) in (chars - shown..chars).enumerate() {
+            let c = CHARS[i % CHARS.len()] as char;
+            let mut buf = [0; 4];
+            // The font is proportional, so each character is centred in its
+            // cell.
+            let centre = cell * CELL_WIDTH + CELL_WIDTH / 2;
+            self.font
+                .draw_centred(c.encode_utf8(&mut buf), centre as i32, 0, target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Room for a line of the small font, even in two-byte characters.
+const LINE_LEN: usize = 40;
+
+/// Fixed-size buffer for formatting one line of text without an allocator.
+pub(crate) struct Line {
+    buf: [u8; LINE_LEN],
+    len: usize,
+}
+
+impl Line {
+    pub(crate) fn new() -> Self {
+        Line {
+            buf: [0; LINE_LEN],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+impl Write for Line {
+    /// Anything which does not fit is cut off, between characters.
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            let len = c.len_utf8();
+            if self.len + len > self.buf.len() {
+                break;
+            }
+            c.encode_utf8(&mut self.buf[self.len..]);
+            self.len += len;
+        }
+        Ok(())
+    }
+}
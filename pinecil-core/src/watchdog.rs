@@ -0,0 +1,112 @@
+//! The free watchdog timer (FWDGT) of the GD32VF103, and telling what reset
+//! the chip.
+//!
+//! The FWDGT counts down from its reload value on the 40 kHz IRC40K, through
+//! a prescaler of 4 to 256, and resets the chip when it gets to 0. Feeding
+//! it starts it over from the reload value. The IRC40K is only roughly
+//! 40 kHz, as little as 30 kHz by the datasheet, so the timeout should leave
+//! plenty of room.
+
+use core::fmt;
+
+/// The clock of the FWDGT, nominally.
+pub const CLOCK_HZ: u32 = 40_000;
+/// The largest reload value, which is 12 bits.
+pub const MAX_RELOAD: u16 = 0xfff;
+/// The largest prescaler setting, dividing by 256.
+pub const MAX_PSC: u8 = 6;
+/// The longest timeout, at the nominal clock.
+pub const MAX_TIMEOUT_MS: u32 = (MAX_RELOAD as u32 + 1) * (4 << MAX_PSC) / (CLOCK_HZ / 1000);
+
+/// The timeout is 0 or above [`MAX_TIMEOUT_MS`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BadTimeout;
+
+/// The register values for a timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Prescale {
+    /// Divides the clock by `4 << psc`.
+    pub psc: u8,
+    pub reload: u16,
+}
+
+impl Prescale {
+    /// The finest setting for `timeout_ms`, which is rounded down to a count
+    /// of the prescaled clock.
+    pub fn for_timeout(timeout_ms: u32) -> Result<Self, BadTimeout> {
+        if timeout_ms == 0 || timeout_ms > MAX_TIMEOUT_MS {
+            return Err(BadTimeout);
+        }
+        let ticks = timeout_ms * (CLOCK_HZ / 1000);
+        (0..=MAX_PSC)
+            .map(|psc| Prescale {
+                psc,
+                reload: ((ticks / (4 << psc)).max(1) - 1).min(u16::MAX as u32) as u16,
+            })
+            .find(|p| p.reload <= MAX_RELOAD)
+            .ok_or(BadTimeout)
+    }
+
+    /// The timeout at the nominal clock.
+    pub fn timeout_ms(&self) -> u32 {
+        (self.reload as u32 + 1) * (4 << self.psc) / (CLOCK_HZ / 1000)
+    }
+}
+
+// The reset flags in RCU_RSTSCK.
+const EPRSTF: u32 = 1 << 26;
+const PORRSTF: u32 = 1 << 27;
+const SWRSTF: u32 = 1 << 28;
+const FWDGTRSTF: u32 = 1 << 29;
+const WWDGTRSTF: u32 = 1 << 30;
+const LPRSTF: u32 = 1 << 31;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetCause {
+    /// Power on, or the supply dipping too low.
+    PowerOn,
+    /// The NRST pin, from a debugger.
+    Pin,
+    Software,
+    /// The FWDGT was not fed in time.
+    Watchdog,
+    /// The window watchdog.
+    WindowWatchdog,
+    /// Entering standby or deep-sleep with the option bytes set to reset.
+    LowPower,
+    /// No flag is set, which happens if they were cleared since.
+    Unknown,
+}
+
+impl ResetCause {
+    /// Decodes RCU_RSTSCK. A reset from within the chip also pulls NRST
+    /// low and sets the pin flag, and a power on sets that as well, so the
+    /// pin only counts if it is the only one.
+    pub fn from_flags(rstsck: u32) -> Self {
+        [
+            (PORRSTF, ResetCause::PowerOn),
+            (FWDGTRSTF, ResetCause::Watchdog),
+            (WWDGTRSTF, ResetCause::WindowWatchdog),
+            (SWRSTF, ResetCause::Software),
+            (LPRSTF, ResetCause::LowPower),
+            (EPRSTF, ResetCause::Pin),
+        ]
+        .iter()
+        .find(|(flag, _)| rstsck & flag != 0)
+        .map_or(ResetCause::Unknown, |&(_, cause)| cause)
+    }
+}
+
+impl fmt::Display for ResetCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ResetCause::PowerOn => "power on",
+            ResetCause::Pin => "reset pin",
+            ResetCause::Software => "software",
+            ResetCause::Watchdog => "watchdog",
+            ResetCause::WindowWatchdog => "window watchdog",
+            ResetCause::LowPower => "low power",
+            ResetCause::Unknown => "unknown",
+        })
+    }
+}
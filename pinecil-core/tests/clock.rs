@@ -0,0 +1,58 @@
+use pinecil_core::clock::{core_hz, HXTAL_HZ, IRC8M_HZ};
+
+const SCSS_HXTAL: u32 = 0b01 << 2;
+const SCSS_PLL: u32 = 0b10 << 2;
+const PLLSEL_PREDV0: u32 = 1 << 16;
+const PREDV0SEL_PLL1: u32 = 1 << 16;
+
+fn pllmf(mf: u32) -> u32 {
+    (mf & 0xf) << 18 | (mf >> 4) << 29
+}
+
+#[test]
+fn after_reset() {
+    assert_eq!(core_hz(0, 0), Some(IRC8M_HZ));
+    assert_eq!(core_hz(SCSS_HXTAL, 0), Some(HXTAL_HZ));
+}
+
+#[test]
+fn demo_04_pll() {
+    // The crystal through the PLL at x12, as `init_clock` sets it up.
+    let cfg0 = SCSS_PLL | PLLSEL_PREDV0 | pllmf(0b01010);
+    assert_eq!(core_hz(cfg0, 0), Some(96_000_000));
+    // The same from the RC oscillator, which is halved first.
+    assert_eq!(core_hz(SCSS_PLL | pllmf(0b01010), 0), Some(48_000_000));
+}
+
+#[test]
+fn pll_factors() {
+    let hz = |mf| core_hz(SCSS_PLL | pllmf(mf), 0).unwrap();
+    assert_eq!(hz(0b00000), 8_000_000);
+    assert_eq!(hz(0b01100), 56_000_000);
+    assert_eq!(hz(0b01101), 26_000_000);
+    assert_eq!(hz(0b01110), 64_000_000);
+    assert_eq!(hz(0b01111), 64_000_000);
+    assert_eq!(hz(0b10000), 68_000_000);
+    assert_eq!(hz(0b11111), 128_000_000);
+}
+
+#[test]
+fn prescalers() {
+    let cfg0 = SCSS_PLL | PLLSEL_PREDV0 | pllmf(0b01010);
+    // PREDV0 /2 halves the PLL input.
+    assert_eq!(core_hz(cfg0, 1), Some(48_000_000));
+    // PLL1 at 8 MHz / 2 x 10 = 40 MHz, then PREDV0 /5 and the PLL x12.
+    let cfg1 = PREDV0SEL_PLL1 | 0b1000 << 8 | 1 << 4 | 4;
+    assert_eq!(core_hz(cfg0, cfg1), Some(96_000_000));
+    // AHB /2 and /512.
+    assert_eq!(core_hz(cfg0 | 0b1000 << 4, 0), Some(48_000_000));
+    assert_eq!(core_hz(SCSS_HXTAL | 0b1111 << 4, 0), Some(15_625));
+    assert_eq!(core_hz(SCSS_HXTAL | 0b1100 << 4, 0), Some(125_000));
+}
+
+#[test]
+fn reserved() {
+    assert_eq!(core_hz(0b11 << 2, 0), None);
+    let cfg0 = SCSS_PLL | PLLSEL_PREDV0;
+    assert_eq!(core_hz(cfg0, PREDV0SEL_PLL1 | 0b0101 << 8), None);
+}
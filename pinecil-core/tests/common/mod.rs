@@ -0,0 +1,35 @@
+//! Reading the traces which the PD and idle sleep tests are driven by.
+
+use std::{fs, path::Path};
+
+/// A trace from `tests/<dir>`, one event or check per line. Anything after a
+/// `#` is a comment.
+pub struct Trace {
+    name: String,
+    text: String,
+}
+
+impl Trace {
+    pub fn read(dir: &str, name: &str) -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(dir)
+            .join(name);
+        Trace {
+            name: name.to_string(),
+            text: fs::read_to_string(path).unwrap(),
+        }
+    }
+
+    /// The words of each line which is not blank, with the file and line
+    /// number for the messages of the checks.
+    pub fn lines(&self) -> impl Iterator<Item = (String, Vec<&str>)> + '_ {
+        self.text.lines().enumerate().filter_map(move |(i, line)| {
+            let words: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+            if words.is_empty() {
+                return None;
+            }
+            Some((format!("{}:{}", self.name, i + 1), words))
+        })
+    }
+}
@@ -0,0 +1,128 @@
+use pinecil_core::counters::{self, Counters, Hours, Tracker, LEN, SAVE_INTERVAL_S, SLOTS};
+
+const COUNTERS: Counters = Counters {
+    uptime_s: 0x0123_4567,
+    heating_s: 90_061,
+    heat_cycles: 42,
+};
+
+#[test]
+fn round_trip() {
+    let bytes = COUNTERS.to_bytes();
+    assert_eq!(&bytes[0..4], b"PCCT");
+    assert_eq!(Counters::parse(&bytes), Some(COUNTERS));
+    assert_eq!(Counters::from_backup(&COUNTERS.to_backup()), Some(COUNTERS));
+}
+
+#[test]
+fn corrupted() {
+    // A freshly erased page, and backup registers after a power cycle.
+    assert_eq!(Counters::parse(&[0xff; LEN]), None);
+    assert_eq!(Counters::from_backup(&[0; 8]), None);
+
+    let mut bytes = COUNTERS.to_bytes();
+    bytes[9] ^= 0x01;
+    assert_eq!(Counters::parse(&bytes), None);
+    let mut words = COUNTERS.to_backup();
+    words[2] ^= 0x8000;
+    assert_eq!(Counters::from_backup(&words), None);
+}
+
+#[test]
+fn latest() {
+    let older = Counters {
+        uptime_s: 100,
+        ..COUNTERS
+    };
+    let newer = Counters {
+        uptime_s: 200,
+        ..COUNTERS
+    };
+    assert_eq!(Counters::latest(Some(older), Some(newer)), newer);
+    assert_eq!(Counters::latest(Some(newer), Some(older)), newer);
+    // Just saved: the same in both.
+    assert_eq!(Counters::latest(Some(newer), Some(newer)), newer);
+    assert_eq!(Counters::latest(Some(older), None), older);
+    assert_eq!(Counters::latest(None, Some(older)), older);
+    assert_eq!(Counters::latest(None, None), Counters::default());
+}
+
+#[test]
+fn records() {
+    let mut records = [0xff; SLOTS * LEN];
+    assert_eq!(counters::newest(&records), None);
+    assert_eq!(counters::next_slot(&records), Some(0));
+
+    let older = Counters {
+        uptime_s: 100,
+        ..COUNTERS
+    };
+    records[..LEN].copy_from_slice(&older.to_bytes());
+    records[LEN..2 * LEN].copy_from_slice(&COUNTERS.to_bytes());
+    assert_eq!(counters::newest(&records), Some(COUNTERS));
+    assert_eq!(counters::next_slot(&records), Some(2));
+
+    // A record cut short by a reset is skipped, but its slot is used.
+    records[2 * LEN..2 * LEN + 8].copy_from_slice(&older.to_bytes()[..8]);
+    assert_eq!(counters::newest(&records), Some(COUNTERS));
+    assert_eq!(counters::next_slot(&records), Some(3));
+
+    // Full, so the page has to be erased.
+    records[(SLOTS - 1) * LEN..].copy_from_slice(&older.to_bytes());
+    assert_eq!(counters::newest(&records), Some(older));
+    assert_eq!(counters::next_slot(&records), None);
+}
+
+#[test]
+fn tracking() {
+    let mut tracker = Tracker::new(Counters::default(), 1000);
+    assert!(!tracker.update(1000, false));
+    assert!(tracker.update(1010, false));
+    // Starting to heat is a cycle, and the time until the next update is
+    // heating time.
+    assert!(tracker.update(1020, true));
+    tracker.update(1050, true);
+    tracker.update(1060, false);
+    tracker.update(1070, true);
+    tracker.update(1075, false);
+    assert_eq!(
+        tracker.counters(),
+        Counters {
+            uptime_s: 75,
+            heating_s: 45,
+            heat_cycles: 2,
+        }
+    );
+
+    // The RTC wrapping around.
+    let mut tracker = Tracker::new(COUNTERS, u32::MAX - 1);
+    tracker.update(3, false);
+    assert_eq!(tracker.counters().uptime_s, COUNTERS.uptime_s + 5);
+}
+
+#[test]
+fn saving() {
+    let mut tracker = Tracker::new(COUNTERS, 0);
+    assert!(!tracker.unsaved());
+    // Nothing has changed, so there is nothing to save.
+    assert!(!tracker.save_due(SAVE_INTERVAL_S));
+    tracker.update(10, false);
+    assert!(tracker.unsaved());
+    assert!(!tracker.save_due(SAVE_INTERVAL_S - 1));
+    assert!(tracker.save_due(SAVE_INTERVAL_S));
+    tracker.saved(SAVE_INTERVAL_S);
+    assert!(!tracker.unsaved());
+    tracker.update(SAVE_INTERVAL_S + 10, false);
+    assert!(!tracker.save_due(SAVE_INTERVAL_S + 10));
+    assert!(tracker.save_due(2 * SAVE_INTERVAL_S));
+}
+
+#[test]
+fn display() {
+    assert_eq!(Hours(0).to_string(), "0h 00m");
+    assert_eq!(Hours(90_061).to_string(), "25h 01m");
+    assert_eq!(
+        COUNTERS.to_string(),
+        "up 5302h 25m, heating 25h 01m, 42 heat cycles"
+    );
+}
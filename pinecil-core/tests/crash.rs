@@ -0,0 +1,61 @@
+use pinecil_core::crash::{cause_name, CrashRecord, WORDS};
+
+const RECORD: CrashRecord = CrashRecord {
+    mcause: 0x3800_0002,
+    mepc: 0x0800_4a10,
+    mtval: 0xffff_ffff,
+    sp: 0x2000_7e80,
+    ra: 0x0800_51c2,
+    stack_overflow: false,
+};
+
+#[test]
+fn round_trip() {
+    let words = RECORD.to_words();
+    assert_eq!(words[0].to_le_bytes(), *b"CRSH");
+    assert_eq!(CrashRecord::parse(&words), Some(RECORD));
+}
+
+#[test]
+fn garbage_is_no_record() {
+    assert_eq!(CrashRecord::parse(&[0; WORDS]), None);
+    assert_eq!(CrashRecord::parse(&[0xffff_ffff; WORDS]), None);
+
+    let overflow = CrashRecord {
+        stack_overflow: true,
+        ..RECORD
+    };
+    assert_eq!(CrashRecord::parse(&overflow.to_words()), Some(overflow));
+
+    let mut corrupted = RECORD.to_words();
+    corrupted[2] ^= 0x100;
+    assert_eq!(CrashRecord::parse(&corrupted), None);
+}
+
+#[test]
+fn names() {
+    // The Bumblebee core sets MPP and MPIE in the high bits of mcause.
+    assert_eq!(cause_name(0x3800_0002), "illegal instruction");
+    assert_eq!(cause_name(5), "load access fault");
+    assert_eq!(cause_name(7), "store access fault");
+    assert_eq!(cause_name(11), "ecall from M-mode");
+    assert_eq!(cause_name(0xfff), "NMI");
+    assert_eq!(cause_name(0x8000_0007), "interrupt");
+    assert_eq!(cause_name(24), "unknown exception");
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        RECORD.to_string(),
+        "illegal instruction (mcause 0x38000002) at 0x08004a10, mtval 0xffffffff, \
+         sp 0x20007e80, ra 0x080051c2"
+    );
+    let overflow = CrashRecord {
+        stack_overflow: true,
+        ..RECORD
+    };
+    assert!(overflow
+        .to_string()
+        .starts_with("stack overflow, then illegal instruction"));
+}
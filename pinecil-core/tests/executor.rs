@@ -0,0 +1,108 @@
+use core::{
+    cell::Cell,
+    task::{Poll, Waker},
+};
+use std::cell::RefCell;
+
+use pinecil_core::{
+    executor::{poll_fn, yield_now, Executor, Task},
+    pin_mut,
+};
+
+/// Stands in for an interrupt: a task waits until it is fired.
+#[derive(Default)]
+struct Event {
+    fired: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl Event {
+    async fn wait(&self) {
+        poll_fn(|cx| {
+            if self.fired.take() {
+                return Poll::Ready(());
+            }
+            *self.waker.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn fire(&self) {
+        self.fired.set(true);
+        if let Some(waker) = self.waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+}
+
+#[test]
+fn polls_only_woken_tasks() {
+    static EXECUTOR: Executor<2> = Executor::new();
+    let event = Event::default();
+    let polls = Cell::new(0);
+    let waiting = async {
+        loop {
+            polls.set(polls.get() + 1);
+            event.wait().await;
+        }
+    };
+    let done = Cell::new(false);
+    let finishing = async { done.set(true) };
+    pin_mut!(waiting, finishing);
+    let mut tasks: [Option<Task>; 2] = [Some(waiting), Some(finishing)];
+
+    assert!(EXECUTOR.is_woken());
+    assert!(EXECUTOR.poll(&mut tasks));
+    assert!(done.get());
+    assert!(tasks[1].is_none());
+    assert_eq!(polls.get(), 1);
+
+    // Nothing to do until the event fires.
+    assert!(!EXECUTOR.is_woken());
+    assert!(EXECUTOR.poll(&mut tasks));
+    assert_eq!(polls.get(), 1);
+
+    event.fire();
+    assert!(EXECUTOR.is_woken());
+    assert!(EXECUTOR.poll(&mut tasks));
+    assert_eq!(polls.get(), 2);
+    assert!(!EXECUTOR.is_woken());
+}
+
+#[test]
+fn runs_until_all_tasks_finish() {
+    static EXECUTOR: Executor<3> = Executor::new();
+    let order = RefCell::new(Vec::new());
+    let task = |name: &'static str, yields: usize| {
+        let order = &order;
+        async move {
+            for _ in 0..yields {
+                order.borrow_mut().push(name);
+                yield_now().await;
+            }
+        }
+    };
+    let (a, b) = (task("a", 3), task("b", 1));
+    pin_mut!(a, b);
+    let mut tasks: [Option<Task>; 2] = [Some(a), Some(b)];
+    // The spare room is not counted as woken.
+    let mut rounds = 0;
+    while EXECUTOR.poll(&mut tasks) {
+        rounds += 1;
+        assert!(EXECUTOR.is_woken());
+    }
+    assert_eq!(rounds, 3);
+    assert!(!EXECUTOR.is_woken());
+    assert_eq!(*order.borrow(), ["a", "b", "a", "a"]);
+}
+
+#[test]
+#[should_panic]
+fn too_many_tasks() {
+    static EXECUTOR: Executor<1> = Executor::new();
+    let (a, b) = (async {}, async {});
+    pin_mut!(a, b);
+    let mut tasks: [Option<Task>; 2] = [Some(a), Some(b)];
+    EXECUTOR.poll(&mut tasks);
+}
@@ -0,0 +1,77 @@
+use pinecil_core::image::{crc32, HeaderError, ImageHeader, APP_MAX_SIZE, HEADER_LEN, HEADER_SIZE};
+
+const APP: &[u8] = b"\x97\x01\x00\x20 not quite a reset handler";
+
+/// The header as it is in flash, padded with erased bytes.
+fn in_flash(header: &ImageHeader) -> Vec<u8> {
+    let mut bytes = vec![0xff; HEADER_SIZE as usize];
+    bytes[..HEADER_LEN].copy_from_slice(&header.to_bytes());
+    bytes
+}
+
+/// A header with a valid CRC over whatever it says.
+fn with_size(size: u32) -> [u8; HEADER_LEN] {
+    let mut bytes = ImageHeader::for_app(APP).to_bytes();
+    bytes[4..8].copy_from_slice(&size.to_le_bytes());
+    let crc = crc32(&bytes[0..12]);
+    bytes[12..16].copy_from_slice(&crc.to_le_bytes());
+    bytes
+}
+
+#[test]
+fn round_trip() {
+    let header = ImageHeader::for_app(APP);
+    assert_eq!(header.size, APP.len() as u32);
+    assert_eq!(header.crc, crc32(APP));
+    let bytes = header.to_bytes();
+    assert_eq!(&bytes[0..4], b"PCLA");
+    assert_eq!(ImageHeader::parse(&bytes), Ok(header));
+    assert_eq!(ImageHeader::parse(&in_flash(&header)), Ok(header));
+}
+
+#[test]
+fn bad_headers() {
+    // Erased flash, as before anything was flashed.
+    assert_eq!(
+        ImageHeader::parse(&[0xff; HEADER_SIZE as usize]),
+        Err(HeaderError::BadMagic)
+    );
+    let bytes = ImageHeader::for_app(APP).to_bytes();
+    assert_eq!(
+        ImageHeader::parse(&bytes[..HEADER_LEN - 1]),
+        Err(HeaderError::BadSize)
+    );
+
+    let mut corrupted = bytes;
+    corrupted[5] ^= 0x01;
+    assert_eq!(
+        ImageHeader::parse(&corrupted),
+        Err(HeaderError::BadHeaderCrc)
+    );
+
+    assert_eq!(ImageHeader::parse(&with_size(0)), Err(HeaderError::BadSize));
+    assert_eq!(
+        ImageHeader::parse(&with_size(APP_MAX_SIZE + 1)),
+        Err(HeaderError::BadSize)
+    );
+    assert!(ImageHeader::parse(&with_size(APP_MAX_SIZE)).is_ok());
+}
+
+#[test]
+fn verify() {
+    let header = ImageHeader::for_app(APP);
+    assert_eq!(header.verify(APP), Ok(()));
+    // The rest of the flash after the image does not count.
+    let mut flash = APP.to_vec();
+    flash.extend_from_slice(&[0xff; 64]);
+    assert_eq!(header.verify(&flash), Ok(()));
+
+    // Half-written.
+    assert_eq!(
+        header.verify(&APP[..APP.len() - 1]),
+        Err(HeaderError::BadSize)
+    );
+    let mut damaged = APP.to_vec();
+    damaged[3] = 0x00;
+    assert_eq!(header.verify(&damaged), Err(HeaderError::BadCrc));
+}
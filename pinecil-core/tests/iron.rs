@@ -0,0 +1,287 @@
+//! Tests of the button gestures of the soldering UI.
+
+use pinecil_core::{
+    counters::Counters,
+    iron::IronUi,
+    lang::Language,
+    settings::{Settings, MAX_TEMP, MIN_TEMP},
+    ui::{Buttons, TICK_MS},
+};
+
+const NONE: Buttons = Buttons {
+    plus: false,
+    minus: false,
+};
+const PLUS: Buttons = Buttons {
+    plus: true,
+    minus: false,
+};
+const MINUS: Buttons = Buttons {
+    plus: false,
+    minus: true,
+};
+
+/// Drives the UI like the board does, one tick at a time.
+struct Fingers {
+    ui: IronUi,
+}
+
+impl Fingers {
+    fn new() -> Self {
+        Fingers {
+            ui: IronUi::new(Settings::DEFAULT),
+        }
+    }
+
+    fn hold(&mut self, buttons: Buttons, ms: u32) {
+        for _ in 0..ms / TICK_MS as u32 {
+            self.ui.update(buttons, TICK_MS);
+        }
+    }
+
+    /// A short press followed by a short pause.
+    fn tap(&mut self, buttons: Buttons) {
+        self.hold(buttons, 100);
+        self.hold(NONE, 100);
+    }
+
+    /// Lets the UI go back to the main screen.
+    fn wait(&mut self) {
+        self.hold(NONE, 10_000);
+    }
+
+    /// Sets the setpoint from off through the adjustment.
+    fn set(&mut self, setpoint: i32) {
+        self.tap(PLUS);
+        while self.ui.setpoint() < setpoint {
+            self.tap(PLUS);
+        }
+        self.wait();
+        assert_eq!(self.ui.setpoint(), setpoint);
+    }
+}
+
+#[test]
+fn starts_off() {
+    let mut f = Fingers::new();
+    f.wait();
+    assert_eq!(f.ui.setpoint(), 0);
+    assert_eq!(f.ui.target(), 0);
+}
+
+#[test]
+fn adjust() {
+    let mut f = Fingers::new();
+    // The first press only opens the adjustment.
+    f.tap(PLUS);
+    assert_eq!(f.ui.setpoint(), 0);
+    // Up from off starts at the lowest temperature.
+    f.tap(PLUS);
+    assert_eq!(f.ui.setpoint(), MIN_TEMP);
+    f.tap(PLUS);
+    f.tap(PLUS);
+    assert_eq!(f.ui.setpoint(), MIN_TEMP + 20);
+    f.tap(MINUS);
+    assert_eq!(f.ui.setpoint(), MIN_TEMP + 10);
+    f.tap(MINUS);
+    f.tap(MINUS);
+    assert_eq!(f.ui.setpoint(), 0);
+
+    // The adjustment closes by itself, and the next press opens it again
+    // without changing anything.
+    f.tap(PLUS);
+    assert_eq!(f.ui.setpoint(), MIN_TEMP);
+    f.wait();
+    f.tap(PLUS);
+    assert_eq!(f.ui.setpoint(), MIN_TEMP);
+}
+
+#[test]
+fn accelerated_repeat() {
+    let mut f = Fingers::new();
+    f.set(3000);
+    f.tap(PLUS);
+
+    // A second of holding: the press, then a repeat every 100 ms after the
+    // first 400 ms.
+    f.hold(PLUS, 1000);
+    let slow = f.ui.setpoint() - 3000;
+    assert!((60..=80).contains(&slow), "{}", slow);
+
+    // After two seconds, the steps are 10 degrees, landing on multiples of
+    // 10 degrees.
+    f.hold(PLUS, 1000);
+    let before = f.ui.setpoint();
+    f.hold(PLUS, 500);
+    let fast = f.ui.setpoint() - before;
+    assert!(fast >= 400, "{}", fast);
+    assert_eq!(f.ui.setpoint() % 100, 0);
+
+    // It stops at the top.
+    f.hold(PLUS, 5000);
+    assert_eq!(f.ui.setpoint(), MAX_TEMP);
+    f.hold(NONE, 100);
+    f.hold(MINUS, 3000);
+    assert!(f.ui.setpoint() < MAX_TEMP - 1000);
+}
+
+#[test]
+fn boost() {
+    let mut f = Fingers::new();
+    // Holding '+' while off does not heat.
+    f.hold(PLUS, 1000);
+    assert!(!f.ui.boosting());
+    f.hold(NONE, 100);
+    f.wait();
+
+    f.set(3200);
+    f.hold(PLUS, 300);
+    assert_eq!(f.ui.target(), 3200);
+    f.hold(PLUS, 200);
+    assert!(f.ui.boosting());
+    assert_eq!(f.ui.target(), Settings::DEFAULT.boost_temp);
+    f.hold(PLUS, 5000);
+    assert_eq!(f.ui.target(), Settings::DEFAULT.boost_temp);
+
+    // Letting go ends it, without opening the adjustment.
+    f.hold(NONE, 100);
+    assert!(!f.ui.boosting());
+    assert_eq!(f.ui.target(), 3200);
+    f.tap(MINUS);
+    f.hold(NONE, 300);
+    f.tap(MINUS);
+    assert_eq!(f.ui.setpoint(), 3190);
+}
+
+#[test]
+fn presets() {
+    let mut f = Fingers::new();
+    let presets = Settings::DEFAULT.presets;
+    let double = |f: &mut Fingers| {
+        f.tap(MINUS);
+        f.tap(MINUS);
+        f.wait();
+    };
+    double(&mut f);
+    assert_eq!(f.ui.setpoint(), presets[0]);
+    assert_eq!(f.ui.preset(), Some(1));
+    double(&mut f);
+    assert_eq!(f.ui.setpoint(), presets[1]);
+    double(&mut f);
+    double(&mut f);
+    assert_eq!(f.ui.setpoint(), presets[0]);
+
+    // Off a preset, the cycle starts over.
+    f.tap(PLUS);
+    f.tap(PLUS);
+    f.wait();
+    assert_eq!(f.ui.preset(), None);
+    double(&mut f);
+    assert_eq!(f.ui.preset(), Some(1));
+
+    // Two presses too far apart open the adjustment instead.
+    f.tap(MINUS);
+    f.hold(NONE, 300);
+    f.tap(MINUS);
+    assert_eq!(f.ui.setpoint(), presets[0] - 10);
+}
+
+#[test]
+fn settings_menu() {
+    let mut f = Fingers::new();
+    f.hold(MINUS, 1000);
+    // Holding on does not skip through the menu.
+    f.hold(MINUS, 2000);
+    f.hold(NONE, 100);
+    assert_eq!(f.ui.setpoint(), 0);
+
+    // Preset 1: up by two steps.
+    f.tap(PLUS);
+    f.tap(PLUS);
+    // Preset 2 and 3 as they are.
+    f.tap(MINUS);
+    f.tap(MINUS);
+    f.tap(MINUS);
+    // Boost: past the top wraps around to the bottom.
+    f.hold(PLUS, 5000);
+    f.hold(NONE, 100);
+    f.tap(MINUS);
+    // Step and fast step: next choice.
+    f.tap(PLUS);
+    f.tap(MINUS);
+    f.tap(PLUS);
+    f.tap(MINUS);
+    // Language: the next one.
+    f.tap(PLUS);
+    assert_eq!(f.ui.take_changed_settings(), None);
+    f.tap(MINUS);
+
+    let settings = f.ui.take_changed_settings().unwrap();
+    assert_eq!(settings.presets, [2820, 3200, 3500]);
+    assert!(settings.boost_temp < Settings::DEFAULT.boost_temp);
+    assert_eq!(settings.step, 50);
+    assert_eq!(settings.fast_step, 250);
+    assert_eq!(settings.language, Language::German);
+    assert!(settings.is_valid());
+    assert_eq!(f.ui.take_changed_settings(), None);
+    assert_eq!(f.ui.settings(), &settings);
+
+    // Back on the main screen, the new step is used.
+    f.set(1000);
+    f.tap(PLUS);
+    f.tap(PLUS);
+    assert_eq!(f.ui.setpoint(), 1050);
+}
+
+#[test]
+fn settings_menu_unchanged() {
+    let mut f = Fingers::new();
+    f.hold(MINUS, 1000);
+    f.hold(NONE, 100);
+    f.wait();
+    assert_eq!(f.ui.take_changed_settings(), None);
+}
+
+#[test]
+fn diagnostics() {
+    let mut f = Fingers::new();
+    let counters = Counters {
+        uptime_s: 3600,
+        heating_s: 60,
+        heat_cycles: 1,
+    };
+    // Not shown, so nothing to redraw.
+    assert!(!f.ui.set_counters(counters));
+
+    f.hold(PLUS, 2100);
+    f.hold(NONE, 100);
+    assert!(f.ui.set_counters(Counters {
+        uptime_s: 3601,
+        ..counters
+    }));
+    // Letting go did not open the adjustment.
+    f.tap(PLUS);
+    assert_eq!(f.ui.setpoint(), 0);
+
+    // Through the three pages and back to the main screen.
+    f.tap(MINUS);
+    f.tap(MINUS);
+    assert!(f.ui.set_counters(counters));
+    f.tap(MINUS);
+    assert!(!f.ui.set_counters(Counters::default()));
+    f.tap(PLUS);
+    f.tap(PLUS);
+    assert_eq!(f.ui.setpoint(), MIN_TEMP);
+}
+
+#[test]
+fn no_diagnostics_while_heating() {
+    let mut f = Fingers::new();
+    f.set(3000);
+    f.hold(PLUS, 3000);
+    assert!(f.ui.boosting());
+    assert!(!f.ui.set_counters(Counters {
+        uptime_s: 1,
+        ..Counters::default()
+    }));
+}
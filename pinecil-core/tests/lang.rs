@@ -0,0 +1,63 @@
+use pinecil_core::{
+    display::WIDTH,
+    lang::{Language, Text},
+    text::{self, Font},
+};
+
+const ALL_TEXTS: [Text; 15] = [
+    Text::Name,
+    Text::Hello,
+    Text::Brightness,
+    Text::NoAccelerometer,
+    Text::NoSupply,
+    Text::TooLow,
+    Text::Off,
+    Text::Preset,
+    Text::Boost,
+    Text::Step,
+    Text::FastStep,
+    Text::Language,
+    Text::Uptime,
+    Text::HeatingTime,
+    Text::HeatCycles,
+];
+
+#[test]
+fn translations() {
+    assert_eq!(Language::English.text(Text::Hello), "Hello world!");
+    assert_eq!(Language::German.text(Text::Hello), "Hallo Welt!");
+    assert_eq!(Language::French.text(Text::Name), "Français");
+    // Left empty in German, so the English text is used.
+    assert_eq!(Language::German.text(Text::Boost), "Boost");
+}
+
+#[test]
+fn texts_fit_the_display() {
+    let font = Font::parse(text::SMALL).unwrap();
+    for language in Language::ALL {
+        for text in ALL_TEXTS {
+            let s = language.text(text);
+            for c in s.chars() {
+                assert!(font.glyph(c).is_some(), "{:?} in {:?}", c, s);
+            }
+            assert!(font.measure(s) <= WIDTH as u32, "{:?} is too wide", s);
+        }
+        // The status line of demo 06 puts the voltage in front of it.
+        let s = language.text(Text::TooLow);
+        let width = font.measure("20.00V ") + font.measure(s);
+        assert!(width <= WIDTH as u32, "{:?} is too wide", s);
+    }
+}
+
+#[test]
+fn stored_index() {
+    for (i, language) in Language::ALL.iter().enumerate() {
+        assert_eq!(language.index() as usize, i);
+        assert_eq!(Language::from_index(i as u8), *language);
+        assert_eq!(Language::from_code(language.code()), Some(*language));
+    }
+    assert_eq!(Language::from_index(200), Language::English);
+    assert_eq!(Language::from_code("xx"), None);
+    assert_eq!(Language::English.next(), Language::German);
+    assert_eq!(Language::French.next(), Language::English);
+}
@@ -0,0 +1,310 @@
+//! Tests of the USB PD sink against message traces.
+//!
+//! Each trace in `tests/pd` lists what happens on the wire, one line each:
+//!
+//! ```text
+//! config <mV> <mA>      limits of the sink, before anything else
+//! <ms> attach           a source is attached
+//! <ms> detach           the source is unplugged
+//! <ms> src <bytes>      a message from the source, header first
+//! <ms> hard-reset       the source signals a hard reset
+//! <ms> tick             only time passes
+//!      snk <bytes>      the sink must send this message next
+//!      snk hard-reset   the sink must signal a hard reset next
+//!      state <state>    the sink must be in this state
+//!      contract <mV> <mA> | none
+//! ```
+//!
+//! The sink is polled at the time of each event before the event itself, and
+//! everything it sends must be listed in the trace.
+
+mod common;
+
+use std::collections::VecDeque;
+
+use common::Trace;
+use pinecil_core::pd::{
+    message::{self, Kind, Message, Pdo, Revision, MAX_LEN},
+    sink::{self, Action, Config, Contract, Sink, State},
+};
+
+fn parse_bytes(words: &[&str]) -> Vec<u8> {
+    words
+        .iter()
+        .map(|w| u8::from_str_radix(w, 16).unwrap())
+        .collect()
+}
+
+fn parse_state(name: &str) -> State {
+    match name {
+        "detached" => State::Detached,
+        "wait-capabilities" => State::WaitCapabilities,
+        "wait-accept" => State::WaitAccept,
+        "wait-ps-rdy" => State::WaitPsRdy,
+        "soft-reset" => State::SoftReset,
+        "ready" => State::Ready,
+        "failed" => State::Failed,
+        _ => panic!("unknown state {}", name),
+    }
+}
+
+fn run_trace(name: &str) {
+    let trace = Trace::read("pd", name);
+    let mut sink = None;
+    let mut sent = VecDeque::new();
+
+    for (at, words) in trace.lines() {
+        let (time, words) = match words[0].parse::<u32>() {
+            Ok(time) => (Some(time), &words[1..]),
+            Err(_) => (None, &words[..]),
+        };
+        if words[0] == "config" {
+            let config = Config {
+                max_mv: words[1].parse().unwrap(),
+                max_ma: words[2].parse().unwrap(),
+            };
+            sink = Some(Sink::new(config));
+            continue;
+        }
+        let sink: &mut Sink = sink.as_mut().expect("no config");
+
+        if let Some(now) = time {
+            assert!(sent.is_empty(), "{}: unexpected {:?}", at, sent);
+            sent.extend(sink.poll(now));
+            let action = match words[0] {
+                "attach" => {
+                    sink.attach(now);
+                    None
+                }
+                "detach" => {
+                    sink.detach();
+                    None
+                }
+                "src" => {
+                    let bytes = parse_bytes(&words[1..]);
+                    let message = Message::parse(&bytes).unwrap();
+                    assert!(message.from_source, "{}", at);
+                    sink.receive(&message, now)
+                }
+                "hard-reset" => {
+                    sink.hard_reset_received(now);
+                    None
+                }
+                "tick" => None,
+                w => panic!("{}: unknown event {}", at, w),
+            };
+            sent.extend(action);
+            continue;
+        }
+
+        match words[0] {
+            "snk" => {
+                let action = sent.pop_front();
+                let action = action.unwrap_or_else(|| panic!("{}: nothing sent", at));
+                match (action, words[1]) {
+                    (Action::HardReset, "hard-reset") => {}
+                    (Action::Send(message), _) => {
+                        let mut buf = [0; MAX_LEN];
+                        let bytes = message.encode(&mut buf);
+                        assert_eq!(bytes, &parse_bytes(&words[1..])[..], "{}", at);
+                    }
+                    (action, _) => panic!("{}: sent {:?}", at, action),
+                }
+            }
+            "state" => assert_eq!(sink.state(), parse_state(words[1]), "{}", at),
+            "contract" => {
+                let contract = match words[1] {
+                    "none" => None,
+                    mv => Some(Contract {
+                        mv: mv.parse().unwrap(),
+                        ma: words[2].parse().unwrap(),
+                    }),
+                };
+                assert_eq!(sink.contract(), contract, "{}", at);
+            }
+            w => panic!("{}: unknown check {}", at, w),
+        }
+    }
+    assert!(
+        sent.is_empty(),
+        "{}: unexpected {:?} at the end",
+        name,
+        sent
+    );
+}
+
+#[test]
+fn charger_65w() {
+    run_trace("charger-65w.txt");
+}
+
+#[test]
+fn limit_12v() {
+    run_trace("limit-12v.txt");
+}
+
+#[test]
+fn rev20_9v() {
+    run_trace("rev20-9v.txt");
+}
+
+#[test]
+fn reject() {
+    run_trace("reject.txt");
+}
+
+#[test]
+fn wait() {
+    run_trace("wait.txt");
+}
+
+#[test]
+fn no_ps_rdy() {
+    run_trace("no-ps-rdy.txt");
+}
+
+#[test]
+fn no_pd() {
+    run_trace("no-pd.txt");
+}
+
+#[test]
+fn soft_reset() {
+    run_trace("soft-reset.txt");
+}
+
+#[test]
+fn protocol_errors() {
+    run_trace("protocol-errors.txt");
+}
+
+#[test]
+fn unexpected_message() {
+    run_trace("unexpected-message.txt");
+}
+
+#[test]
+fn parse_source_capabilities() {
+    let bytes = [
+        0xa1, 0x51, 0x2c, 0x91, 0x01, 0x0a, 0x2c, 0xd1, 0x02, 0x00, 0x2c, 0xb1, 0x04, 0x00, 0x45,
+        0x41, 0x06, 0x00, 0x3c, 0x21, 0xa4, 0xc1,
+    ];
+    let message = Message::parse(&bytes).unwrap();
+    assert_eq!(message.kind, Kind::SourceCapabilities);
+    assert_eq!(message.id, 0);
+    assert_eq!(message.revision, Revision::Rev30);
+    assert!(message.from_source);
+    let pdos: Vec<Pdo> = message.objects().iter().map(|&o| Pdo::parse(o)).collect();
+    assert_eq!(
+        pdos,
+        [
+            Pdo::Fixed {
+                mv: 5000,
+                max_ma: 3000
+            },
+            Pdo::Fixed {
+                mv: 9000,
+                max_ma: 3000
+            },
+            Pdo::Fixed {
+                mv: 15000,
+                max_ma: 3000
+            },
+            Pdo::Fixed {
+                mv: 20000,
+                max_ma: 3250
+            },
+            Pdo::Pps {
+                min_mv: 3300,
+                max_mv: 21000,
+                max_ma: 3000
+            },
+        ]
+    );
+
+    // Encoding gives back the same bytes, apart from the data role, which is
+    // always UFP for the sink.
+    let mut buf = [0; MAX_LEN];
+    let encoded = message.encode(&mut buf);
+    assert_eq!(encoded[0], bytes[0] & !0x20);
+    assert_eq!(encoded[1..], bytes[1..]);
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(Message::parse(&[0xa1]), Err(message::Error::Truncated));
+    // One data object announced, none there.
+    assert_eq!(
+        Message::parse(&[0xa1, 0x11]),
+        Err(message::Error::Truncated)
+    );
+    assert_eq!(
+        Message::parse(&[0xa3, 0x03, 0x00]),
+        Err(message::Error::TooLong)
+    );
+    // Unknown and extended messages are kept as they are.
+    let unknown = Message::parse(&[0xbf, 0x03]).unwrap();
+    assert_eq!(unknown.kind, Kind::OtherControl(0x1f));
+    let extended = Message::parse(&[0xa1, 0x93, 0x02, 0x00, 0x00, 0x00]).unwrap();
+    assert_eq!(extended.kind, Kind::Extended(1));
+    let mut buf = [0; MAX_LEN];
+    assert_eq!(extended.encode(&mut buf)[1], 0x93);
+}
+
+#[test]
+fn other_pdos() {
+    // 9 to 20 V battery at 45 W, 5 to 12 V variable at 2 A.
+    assert_eq!(
+        Pdo::parse(0x5902_d0b4),
+        Pdo::Battery {
+            min_mv: 9000,
+            max_mv: 20000,
+            max_mw: 45000
+        }
+    );
+    assert_eq!(
+        Pdo::parse(0x8f01_90c8),
+        Pdo::Variable {
+            min_mv: 5000,
+            max_mv: 12000,
+            max_ma: 2000
+        }
+    );
+    assert_eq!(Pdo::parse(0xd000_0000), Pdo::Other(0xd000_0000));
+}
+
+#[test]
+fn select() {
+    let caps = [
+        message::fixed_pdo(5000, 3000),
+        message::fixed_pdo(9000, 3000),
+        message::fixed_pdo(15000, 2000),
+        message::fixed_pdo(15000, 3000),
+        message::fixed_pdo(20000, 5000),
+    ];
+    let config = |max_mv, max_ma| Config { max_mv, max_ma };
+
+    let s = sink::select(&config(20000, 3250), &caps);
+    assert_eq!(s.position, 5);
+    assert_eq!(
+        s.contract,
+        Contract {
+            mv: 20000,
+            ma: 3250
+        }
+    );
+    // The one with more current wins.
+    let s = sink::select(&config(19000, 5000), &caps);
+    assert_eq!(s.position, 4);
+    assert_eq!(
+        s.contract,
+        Contract {
+            mv: 15000,
+            ma: 3000
+        }
+    );
+    // Nothing fits, so take the 5 V supply.
+    let s = sink::select(&config(3300, 1000), &caps);
+    assert_eq!(s.position, 1);
+    assert_eq!(s.contract, Contract { mv: 5000, ma: 1000 });
+}
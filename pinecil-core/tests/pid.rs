@@ -0,0 +1,188 @@
+//! Closed-loop tests of the PID controller against the tip model.
+//!
+//! Each step of the loop measures the tip, runs the controller and then
+//! heats the tip for one 100 ms heater period, like the firmware does.
+
+use pinecil_core::{
+    pid::{self, Config, Gains, Pid, GAIN_ONE, PINECIL_CONFIG},
+    tip_model::{self, TipModel, PINECIL_TIP},
+};
+
+const PERIOD_MS: u32 = 100;
+const SUPPLY_MV: u32 = 20_000;
+const AMBIENT: i32 = 250;
+
+struct Loop {
+    pid: Pid,
+    tip: TipModel,
+    supply_mv: u32,
+    /// Temperature and output at the end of each period.
+    trace: Vec<(i32, u16)>,
+}
+
+impl Loop {
+    fn new(config: Config) -> Self {
+        Loop {
+            pid: Pid::new(config),
+            tip: TipModel::new(PINECIL_TIP, AMBIENT),
+            supply_mv: SUPPLY_MV,
+            trace: Vec::new(),
+        }
+    }
+
+    fn run(&mut self, target: i32, ms: u32) {
+        for _ in 0..ms / PERIOD_MS {
+            let duty = self.pid.update(target, self.tip.temperature());
+            self.tip.step(duty, self.supply_mv, PERIOD_MS);
+            self.trace.push((self.tip.temperature(), duty));
+        }
+    }
+
+    /// How far the temperature went above `target` from period `from` on.
+    fn overshoot(&self, from: usize, target: i32) -> i32 {
+        self.trace[from..].iter().map(|&(t, _)| t).max().unwrap() - target
+    }
+
+    /// The first period after which the temperature stays within `band` of
+    /// `target`.
+    fn settled(&self, target: i32, band: i32) -> usize {
+        self.trace
+            .iter()
+            .rposition(|&(t, _)| (t - target).abs() > band)
+            .map_or(0, |i| i + 1)
+    }
+}
+
+#[test]
+fn step_response() {
+    let mut l = Loop::new(PINECIL_CONFIG);
+    l.run(3200, 60_000);
+    let overshoot = l.overshoot(0, 3200);
+    assert!(overshoot <= 20, "overshoot {}", overshoot);
+    // The tip reaches 315 °C after 18 s at full power.
+    let settled = l.settled(3200, 10);
+    assert!(settled <= 220, "settled after {} periods", settled);
+    assert!((l.tip.temperature() - 3200).abs() <= 2);
+}
+
+#[test]
+fn step_down_and_up() {
+    let mut l = Loop::new(PINECIL_CONFIG);
+    l.run(3200, 60_000);
+    let start = l.trace.len();
+    l.run(2000, 120_000);
+    let undershoot = 2000 - l.trace[start..].iter().map(|&(t, _)| t).min().unwrap();
+    assert!(undershoot <= 20, "undershoot {}", undershoot);
+    assert!((l.tip.temperature() - 2000).abs() <= 2);
+
+    let start = l.trace.len();
+    l.run(4000, 60_000);
+    let overshoot = l.overshoot(start, 4000);
+    assert!(overshoot <= 20, "overshoot {}", overshoot);
+    assert!((l.tip.temperature() - 4000).abs() <= 2);
+}
+
+#[test]
+fn load_is_rejected() {
+    let mut l = Loop::new(PINECIL_CONFIG);
+    l.run(3200, 60_000);
+    // A large joint takes 15 W for 5 s.
+    l.tip.set_load(15_000);
+    let start = l.trace.len();
+    l.run(3200, 5_000);
+    let dip = 3200 - l.trace[start..].iter().map(|&(t, _)| t).min().unwrap();
+    assert!(dip <= 100, "dipped by {}", dip);
+    l.tip.set_load(0);
+    let start = l.trace.len();
+    l.run(3200, 30_000);
+    let overshoot = l.overshoot(start, 3200);
+    assert!(overshoot <= 20, "overshoot {}", overshoot);
+    assert!((l.tip.temperature() - 3200).abs() <= 2);
+}
+
+#[test]
+fn no_windup_when_power_limited() {
+    // Spend a minute at a budget too low to reach the target, then lift it.
+    let mut l = Loop::new(PINECIL_CONFIG);
+    let limit = pid::power_limit(4_000, SUPPLY_MV, PINECIL_TIP.resistance_mohm);
+    l.pid.set_limit(limit);
+    l.run(3200, 60_000);
+    assert!(l.tip.temperature() < 2500);
+    assert!(l.trace.iter().all(|&(_, duty)| duty <= limit));
+
+    l.pid.set_limit(1000);
+    let start = l.trace.len();
+    l.run(3200, 60_000);
+    let overshoot = l.overshoot(start, 3200);
+    assert!(overshoot <= 20, "overshoot {}", overshoot);
+}
+
+#[test]
+fn low_supply() {
+    // 12 V gives about a third of the power. The tip heats up a lot slower,
+    // but must not overshoot more.
+    let mut l = Loop::new(PINECIL_CONFIG);
+    l.supply_mv = 12_000;
+    l.run(3200, 90_000);
+    let overshoot = l.overshoot(0, 3200);
+    assert!(overshoot <= 20, "overshoot {}", overshoot);
+    assert!((l.tip.temperature() - 3200).abs() <= 2);
+}
+
+#[test]
+fn output_is_slew_limited() {
+    let mut l = Loop::new(PINECIL_CONFIG);
+    l.run(3200, 30_000);
+    l.run(1000, 10_000);
+    let mut previous = 0;
+    for &(_, duty) in &l.trace {
+        assert!((duty as i32 - previous as i32).abs() <= PINECIL_CONFIG.max_step as i32);
+        assert!(duty <= PINECIL_CONFIG.max_output);
+        previous = duty;
+    }
+}
+
+#[test]
+fn no_derivative_kick() {
+    let config = Config {
+        gains: Gains {
+            kp: 0,
+            ki: 0,
+            kd: 10 * GAIN_ONE,
+        },
+        max_output: 1000,
+        max_step: 1000,
+    };
+    let mut pid = Pid::new(config);
+    assert_eq!(pid.update(1000, 1000), 0);
+    // Only the target changes.
+    assert_eq!(pid.update(3000, 1000), 0);
+    // The tip cools down by 1 °C.
+    assert_eq!(pid.update(3000, 990), 10 * 10);
+}
+
+#[test]
+fn reset() {
+    let mut pid = Pid::new(PINECIL_CONFIG);
+    for _ in 0..100 {
+        pid.update(3200, 3000);
+    }
+    pid.reset();
+    assert_eq!(pid.output(), 0);
+    // The integral has been forgotten, so there is no output at the target.
+    assert_eq!(pid.update(3200, 3200), 0);
+}
+
+#[test]
+fn power_limit() {
+    // 20 V into 8 Ω is 50 W.
+    assert_eq!(pid::power_limit(25_000, 20_000, 8_000), 500);
+    assert_eq!(pid::power_limit(50_000, 20_000, 8_000), 1000);
+    assert_eq!(pid::power_limit(100_000, 20_000, 8_000), 1000);
+    assert_eq!(pid::power_limit(10_000, 0, 8_000), 1000);
+    assert_eq!(pid::power_limit(10_000, 20_000, 0), 0);
+    assert_eq!(
+        tip_model::heater_power_mw(8_000, 20_000, pid::power_limit(20_000, 20_000, 8_000)),
+        20_000
+    );
+}
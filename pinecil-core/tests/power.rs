@@ -0,0 +1,55 @@
+use pinecil_core::power::{Source, Supply, HYSTERESIS_MV, MIN_HEAT_MV};
+
+#[test]
+fn classify() {
+    assert_eq!(Source::classify(5_050), Source::Usb);
+    assert_eq!(Source::classify(4_700), Source::Usb);
+    assert_eq!(Source::classify(8_800), Source::Pd(9));
+    assert_eq!(Source::classify(12_300), Source::Pd(12));
+    assert_eq!(Source::classify(14_900), Source::Pd(15));
+    assert_eq!(Source::classify(19_500), Source::Pd(20));
+    assert_eq!(Source::classify(21_500), Source::Pd(20));
+    // Between the PD levels, and beyond them.
+    assert_eq!(Source::classify(0), Source::Dc);
+    assert_eq!(Source::classify(7_000), Source::Dc);
+    assert_eq!(Source::classify(17_500), Source::Dc);
+    assert_eq!(Source::classify(24_000), Source::Dc);
+}
+
+#[test]
+fn names() {
+    assert_eq!(Source::Usb.to_string(), "USB");
+    assert_eq!(Source::Pd(15).to_string(), "PD 15V");
+    assert_eq!(Source::Dc.to_string(), "DC");
+}
+
+#[test]
+fn refuses_low_supply() {
+    let mut supply = Supply::new();
+    assert!(!supply.can_heat());
+    supply.update(5_000);
+    assert!(!supply.can_heat());
+    assert_eq!(supply.source(), Source::Usb);
+    supply.update(20_000);
+    assert!(supply.can_heat());
+    assert_eq!(supply.mv(), 20_000);
+    supply.update(MIN_HEAT_MV - 1);
+    assert!(!supply.can_heat());
+}
+
+#[test]
+fn hysteresis() {
+    let mut supply = Supply::new();
+    // Just above the limit is not enough to start heating...
+    supply.update(MIN_HEAT_MV);
+    assert!(!supply.can_heat());
+    supply.update(MIN_HEAT_MV + HYSTERESIS_MV);
+    assert!(supply.can_heat());
+    // ...but enough to keep heating once started.
+    supply.update(MIN_HEAT_MV);
+    assert!(supply.can_heat());
+    supply.update(MIN_HEAT_MV - 1);
+    assert!(!supply.can_heat());
+    supply.update(MIN_HEAT_MV + HYSTERESIS_MV - 1);
+    assert!(!supply.can_heat());
+}
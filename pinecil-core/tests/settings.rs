@@ -0,0 +1,67 @@
+use pinecil_core::{
+    lang::Language,
+    settings::{Error, Settings, LEN},
+};
+
+#[test]
+fn round_trip() {
+    let settings = Settings {
+        presets: [1000, 3330, 4500],
+        boost_temp: 4200,
+        step: 50,
+        fast_step: 250,
+        language: Language::German,
+    };
+    assert!(settings.is_valid());
+    let bytes = settings.to_bytes();
+    assert_eq!(&bytes[0..4], b"PCST");
+    assert_eq!(Settings::parse(&bytes), Ok(settings));
+    assert_eq!(
+        Settings::parse(&Settings::DEFAULT.to_bytes()),
+        Ok(Settings::DEFAULT)
+    );
+}
+
+#[test]
+fn errors() {
+    // A freshly erased page.
+    assert_eq!(Settings::parse(&[0xff; LEN]), Err(Error::BadMagic));
+
+    let bytes = Settings::DEFAULT.to_bytes();
+    let mut newer = bytes;
+    newer[4] = 2;
+    assert_eq!(Settings::parse(&newer), Err(Error::BadVersion));
+
+    let mut corrupted = bytes;
+    corrupted[7] ^= 0x10;
+    assert_eq!(Settings::parse(&corrupted), Err(Error::BadCrc));
+
+    // Stored with a valid CRC, but with nonsense in it.
+    let hot = Settings {
+        boost_temp: 6000,
+        ..Settings::DEFAULT
+    };
+    assert!(!hot.is_valid());
+    assert_eq!(Settings::parse(&hot.to_bytes()), Err(Error::BadValue));
+    let odd_step = Settings {
+        step: 30,
+        ..Settings::DEFAULT
+    };
+    assert_eq!(Settings::parse(&odd_step.to_bytes()), Err(Error::BadValue));
+}
+
+#[test]
+fn unknown_language_is_english() {
+    let mut bytes = Settings {
+        language: Language::French,
+        ..Settings::DEFAULT
+    }
+    .to_bytes();
+    // 0xFF is the padding of the settings saved before there was a language.
+    for &index in &[200, 0xff] {
+        bytes[18] = index;
+        let crc = pinecil_core::image::crc32(&bytes[0..20]);
+        bytes[20..24].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(Settings::parse(&bytes), Ok(Settings::DEFAULT));
+    }
+}
@@ -0,0 +1,55 @@
+use pinecil_core::shell::{Command, Edit, LineEditor, LINE_LEN};
+
+/// Types `bytes`, and returns the lines entered and what was echoed.
+fn type_in(editor: &mut LineEditor, bytes: &[u8]) -> (Vec<String>, String) {
+    let mut lines = Vec::new();
+    let mut echo = String::new();
+    for &byte in bytes {
+        match editor.push(byte) {
+            Edit::Echo(b) => echo.push(b as char),
+            Edit::Erase => echo.push_str("\x08 \x08"),
+            Edit::Line(line) => lines.push(line.to_string()),
+            Edit::Nothing => {}
+        }
+    }
+    (lines, echo)
+}
+
+#[test]
+fn lines() {
+    let mut editor = LineEditor::new();
+    let (lines, echo) = type_in(&mut editor, b"stack\rhelp\r\n\n");
+    assert_eq!(lines, ["stack", "help", ""]);
+    assert_eq!(echo, "stackhelp");
+}
+
+#[test]
+fn editing() {
+    let mut editor = LineEditor::new();
+    let (lines, echo) = type_in(&mut editor, b"\x08stak\x7fck\x1b\xe2\r");
+    assert_eq!(lines, ["stack"]);
+    assert_eq!(echo, "stak\x08 \x08ck");
+}
+
+#[test]
+fn long_line_is_cut() {
+    let mut editor = LineEditor::new();
+    let long = [b'x'; LINE_LEN + 5];
+    let (lines, echo) = type_in(&mut editor, &long);
+    assert!(lines.is_empty());
+    assert_eq!(echo.len(), LINE_LEN);
+    let (lines, _) = type_in(&mut editor, b"\r");
+    assert_eq!(lines, ["x".repeat(LINE_LEN)]);
+}
+
+#[test]
+fn commands() {
+    assert_eq!(Command::parse("stack"), Some(Command::Stack));
+    assert_eq!(Command::parse("counters"), Some(Command::Counters));
+    assert_eq!(Command::parse("  help "), Some(Command::Help));
+    assert_eq!(Command::parse(""), None);
+    assert_eq!(Command::parse("stacks"), None);
+    for command in Command::ALL {
+        assert_eq!(Command::parse(command.name()), Some(command));
+    }
+}
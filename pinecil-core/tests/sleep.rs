@@ -0,0 +1,177 @@
+//! Tests of the idle sleep against scripted accelerometer traces.
+//!
+//! Each trace in `tests/sleep` is a list of events, one line each:
+//!
+//! ```text
+//! config <threshold> <standby s> <off s> <standby temp>
+//! <ms> accel <x> <y> <z>        one reading
+//! <ms>-<ms> accel <x> <y> <z> [<noise>]
+//!                               a reading every 100 ms up to (but not
+//!                               including) the second time, with up to
+//!                               <noise> counts added to each axis
+//! <ms>-<ms> tilt <x> <y> <z> <x> <y> <z>
+//!                               a reading every 100 ms, moving in a
+//!                               straight line from the first position to
+//!                               the second
+//! <ms> none                     the accelerometer could not be read
+//! <ms> button                   a reading as the last one, and a button
+//!                               press
+//!      state <state>            the state after the last reading
+//!      target <set> <expected>  the target for the one set by the user
+//! ```
+
+mod common;
+
+use common::Trace;
+use pinecil_core::{
+    sleep::{Config, Sleep, State},
+    ui::Accel,
+};
+
+const SAMPLE_MS: u32 = 100;
+
+fn parse_state(name: &str) -> State {
+    match name {
+        "active" => State::Active,
+        "standby" => State::Standby,
+        "off" => State::Off,
+        _ => panic!("unknown state {}", name),
+    }
+}
+
+fn parse_accel(words: &[&str]) -> Accel {
+    let axis = |i: usize| words[i].parse().unwrap();
+    Accel {
+        x: axis(0),
+        y: axis(1),
+        z: axis(2),
+    }
+}
+
+/// Deterministic noise between `-amplitude` and `amplitude`, different for
+/// each axis.
+fn noise(i: u32, axis: u32, amplitude: i8) -> i8 {
+    let period = 2 * amplitude as u32 + 1;
+    ((i * 7 + axis * 3) % period) as i8 - amplitude
+}
+
+fn run_trace(name: &str) {
+    let trace = Trace::read("sleep", name);
+    let mut sleep = None;
+    let mut last = None;
+
+    for (at, words) in trace.lines() {
+        let (first, rest) = words.split_first().unwrap();
+        if *first == "config" {
+            let config = Config {
+                threshold: rest[0].parse().unwrap(),
+                standby_after_ms: rest[1].parse::<u32>().unwrap() * 1000,
+                off_after_ms: rest[2].parse::<u32>().unwrap() * 1000,
+                standby_temp: rest[3].parse().unwrap(),
+            };
+            sleep = Some(Sleep::new(config, 0));
+            continue;
+        }
+        let sleep: &mut Sleep = sleep.as_mut().expect("no config");
+
+        let times: Vec<u32> = first.split('-').map_while(|t| t.parse().ok()).collect();
+        match (&times[..], rest) {
+            (&[now], ["accel", accel @ ..]) => {
+                last = Some(parse_accel(accel));
+                sleep.update(last, false, now);
+            }
+            (&[start, end], ["accel", accel @ ..]) => {
+                let base = parse_accel(accel);
+                let amplitude = accel.get(3).map_or(0, |n| n.parse().unwrap());
+                for (n, now) in (start..end).step_by(SAMPLE_MS as usize).enumerate() {
+                    let n = n as u32;
+                    let accel = Accel {
+                        x: base.x + noise(n, 0, amplitude),
+                        y: base.y + noise(n, 1, amplitude),
+                        z: base.z + noise(n, 2, amplitude),
+                    };
+                    last = Some(accel);
+                    sleep.update(last, false, now);
+                }
+            }
+            (&[start, end], ["tilt", positions @ ..]) => {
+                let from = parse_accel(&positions[..3]);
+                let to = parse_accel(&positions[3..]);
+                let steps = ((end - start) / SAMPLE_MS) as i32;
+                let lerp =
+                    |a: i8, b: i8, n: i32| (a as i32 + (b as i32 - a as i32) * n / steps) as i8;
+                for (n, now) in (start..end).step_by(SAMPLE_MS as usize).enumerate() {
+                    let n = n as i32;
+                    let accel = Accel {
+                        x: lerp(from.x, to.x, n),
+                        y: lerp(from.y, to.y, n),
+                        z: lerp(from.z, to.z, n),
+                    };
+                    last = Some(accel);
+                    sleep.update(last, false, now);
+                }
+                last = Some(to);
+            }
+            (&[now], ["none"]) => {
+                last = None;
+                sleep.update(None, false, now);
+            }
+            (&[now], ["button"]) => {
+                sleep.update(last, true, now);
+            }
+            (&[], _) => match words[..] {
+                ["state", state] => assert_eq!(sleep.state(), parse_state(state), "{}", at),
+                ["target", set, expected] => assert_eq!(
+                    sleep.target(set.parse().unwrap()),
+                    expected.parse::<i32>().unwrap(),
+                    "{}",
+                    at
+                ),
+                _ => panic!("{}: unknown check {:?}", at, words),
+            },
+            _ => panic!("{}: unknown event {:?}", at, words),
+        }
+    }
+}
+
+#[test]
+fn on_stand() {
+    run_trace("on-stand.txt");
+}
+
+#[test]
+fn button_wake() {
+    run_trace("button-wake.txt");
+}
+
+#[test]
+fn slow_tilt() {
+    run_trace("slow-tilt.txt");
+}
+
+#[test]
+fn knocks() {
+    run_trace("knocks.txt");
+}
+
+#[test]
+fn no_accel() {
+    run_trace("no-accel.txt");
+}
+
+#[test]
+fn standby_keeps_lower_target() {
+    let config = Config {
+        standby_temp: 1500,
+        ..pinecil_core::sleep::DEFAULT_CONFIG
+    };
+    let mut sleep = Sleep::new(config, 0);
+    let still = Some(Accel { x: 0, y: 0, z: 64 });
+    sleep.update(still, false, 0);
+    assert_eq!(sleep.target(3200), 3200);
+    sleep.update(still, false, config.standby_after_ms);
+    assert_eq!(sleep.state(), State::Standby);
+    assert_eq!(sleep.target(3200), 1500);
+    assert_eq!(sleep.target(1000), 1000);
+    assert_eq!(sleep.target(0), 0);
+}
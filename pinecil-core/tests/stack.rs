@@ -0,0 +1,51 @@
+use pinecil_core::stack::{Usage, Watch, GUARD_WORDS, PAINT};
+
+fn measure_slice(stack: &[u32]) -> Usage {
+    Usage::measure(stack.iter().copied())
+}
+
+/// A stack of `words`, used down to `used` words from the top.
+fn stack(words: usize, used: usize) -> Vec<u32> {
+    let mut stack = vec![PAINT; words];
+    for word in &mut stack[words - used..] {
+        *word = 0x2000_1234;
+    }
+    stack
+}
+
+#[test]
+fn measure() {
+    let usage = measure_slice(&stack(256, 64));
+    assert_eq!(
+        usage,
+        Usage {
+            peak: 256,
+            size: 1024
+        }
+    );
+    assert_eq!(usage.percent(), 25);
+    assert!(!usage.overflowed());
+    assert_eq!(usage.to_string(), "256 of 1024 bytes (25%)");
+
+    // A word which happens to be the paint is taken for unused.
+    let mut skipped = stack(256, 64);
+    skipped[200] = PAINT;
+    assert_eq!(measure_slice(&skipped).peak, 256);
+
+    assert_eq!(measure_slice(&stack(256, 0)).peak, 0);
+}
+
+#[test]
+fn guard() {
+    assert!(!measure_slice(&stack(256, 256 - GUARD_WORDS)).overflowed());
+    assert!(measure_slice(&stack(256, 256 - GUARD_WORDS + 1)).overflowed());
+    assert!(measure_slice(&stack(256, 256)).overflowed());
+}
+
+#[test]
+fn warns_once() {
+    let mut watch = Watch::new(75);
+    assert!(!watch.check(measure_slice(&stack(100, 74))));
+    assert!(watch.check(measure_slice(&stack(100, 75))));
+    assert!(!watch.check(measure_slice(&stack(100, 90))));
+}
@@ -0,0 +1,108 @@
+//! Checks of the type K conversion against the NIST ITS-90 reference table,
+//! which gives the voltage in microvolts at whole degrees.
+
+use pinecil_core::thermocouple::{self, MAX_TEMP, MIN_TEMP};
+
+/// (°C, µV) from the NIST ITS-90 table for type K thermocouples, including
+/// points between the 10 °C steps of the lookup table.
+const NIST: &[(i32, i32)] = &[
+    (-50, -1889),
+    (-20, -778),
+    (-5, -197),
+    (0, 0),
+    (5, 198),
+    (10, 397),
+    (20, 798),
+    (25, 1000),
+    (30, 1203),
+    (37, 1489),
+    (50, 2023),
+    (75, 3059),
+    (100, 4096),
+    (125, 5124),
+    (150, 6138),
+    (200, 8138),
+    (250, 10153),
+    (300, 12209),
+    (320, 13040),
+    (350, 14293),
+    (400, 16397),
+    (420, 17243),
+    (450, 18516),
+    (480, 19792),
+    (500, 20644),
+    (600, 24905),
+    (800, 33275),
+    (1000, 41276),
+    (1200, 48838),
+    (1370, 54819),
+];
+
+#[test]
+fn emf_matches_reference() {
+    for &(temp, uv) in NIST {
+        let emf = thermocouple::emf_uv(temp * 10);
+        // The table is rounded to whole microvolts, and interpolation adds a
+        // little more error between the steps.
+        assert!(
+            (emf - uv).abs() <= 2,
+            "{} °C: {} µV, expected {}",
+            temp,
+            emf,
+            uv
+        );
+    }
+}
+
+#[test]
+fn temperature_matches_reference() {
+    for &(temp, uv) in NIST {
+        let t = thermocouple::temperature(uv).unwrap();
+        // About 40 µV per degree, so 1 µV of rounding in the table is
+        // already 0.025 °C.
+        assert!(
+            (t - temp * 10).abs() <= 1,
+            "{} µV: {} /10 °C, expected {} °C",
+            uv,
+            t,
+            temp
+        );
+    }
+}
+
+#[test]
+fn round_trip() {
+    for temp in (MIN_TEMP..=MAX_TEMP).step_by(7) {
+        let t = thermocouple::temperature(thermocouple::emf_uv(temp)).unwrap();
+        assert!((t - temp).abs() <= 1, "{} /10 °C came back as {}", temp, t);
+    }
+}
+
+#[test]
+fn out_of_range() {
+    assert_eq!(thermocouple::temperature(-1890), None);
+    assert_eq!(thermocouple::temperature(54_820), None);
+    assert_eq!(thermocouple::temperature(i32::MAX / 1000), None);
+    assert_eq!(thermocouple::emf_uv(-2000), thermocouple::emf_uv(MIN_TEMP));
+    assert_eq!(thermocouple::emf_uv(20000), thermocouple::emf_uv(MAX_TEMP));
+}
+
+#[test]
+fn cold_junction_compensation() {
+    // A tip at 320 °C with the handle at 25 °C only sees the difference of
+    // the voltages, 13.040 mV - 1.000 mV.
+    assert_eq!(
+        thermocouple::tip_temperature(13_040 - 1000, 250),
+        Some(3200)
+    );
+    // With the handle at 0 °C, nothing needs to be added.
+    assert_eq!(thermocouple::tip_temperature(4096, 0), Some(1000));
+}
+
+#[test]
+fn cold_junction_sensors() {
+    assert_eq!(thermocouple::tmp36_temperature(750_000), 250);
+    assert_eq!(thermocouple::tmp36_temperature(400_000), -100);
+    assert_eq!(thermocouple::bma223_temperature(0), 230);
+    assert_eq!(thermocouple::bma223_temperature(-6), 200);
+}
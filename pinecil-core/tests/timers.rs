@@ -0,0 +1,165 @@
+use pinecil_core::timers::{Full, Timers, SLOTS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Job {
+    Redraw,
+    Sample,
+    Beep,
+}
+
+/// Everything which expires by `now_ms`, in order.
+fn poll_all<const N: usize>(timers: &mut Timers<Job, N>, now_ms: u32) -> Vec<Job> {
+    let mut jobs = Vec::new();
+    while let Some(job) = timers.poll(now_ms) {
+        jobs.push(job);
+    }
+    jobs
+}
+
+/// Polls every millisecond from `from_ms` to `to_ms`, and returns when each
+/// job expired.
+fn run<const N: usize>(timers: &mut Timers<Job, N>, from_ms: u32, to_ms: u32) -> Vec<(u32, Job)> {
+    let mut expired = Vec::new();
+    for ms in from_ms..=to_ms {
+        for job in poll_all(timers, ms) {
+            expired.push((ms, job));
+        }
+    }
+    expired
+}
+
+#[test]
+fn one_shot_and_periodic() {
+    let mut timers: Timers<Job, 4> = Timers::new(0);
+    let redraw = timers.start_periodic(25, Job::Redraw).unwrap();
+    let sample = timers.start_once(60, Job::Sample).unwrap();
+    assert_eq!(
+        run(&mut timers, 1, 100),
+        [
+            (25, Job::Redraw),
+            (50, Job::Redraw),
+            (60, Job::Sample),
+            (75, Job::Redraw),
+            (100, Job::Redraw),
+        ]
+    );
+    assert!(timers.is_running(redraw));
+    assert!(!timers.is_running(sample));
+    assert_eq!(timers.cancel(sample), None);
+}
+
+#[test]
+fn longer_than_the_wheel() {
+    let mut timers: Timers<Job, 4> = Timers::new(0);
+    timers.start_once(SLOTS as u32 * 3 + 5, Job::Beep).unwrap();
+    timers.start_periodic(1000, Job::Sample).unwrap();
+    assert_eq!(
+        run(&mut timers, 1, 2500),
+        [
+            (SLOTS as u32 * 3 + 5, Job::Beep),
+            (1000, Job::Sample),
+            (2000, Job::Sample),
+        ]
+    );
+}
+
+#[test]
+fn late_polls() {
+    let mut timers: Timers<Job, 4> = Timers::new(0);
+    timers.start_periodic(10, Job::Redraw).unwrap();
+    timers.start_once(15, Job::Beep).unwrap();
+    // Polled late, both come back once, and missed periods are skipped.
+    let mut jobs = poll_all(&mut timers, 1000);
+    jobs.sort_by_key(|&j| j as u8);
+    assert_eq!(jobs, [Job::Redraw, Job::Beep]);
+    // The period keeps to its rhythm.
+    assert_eq!(
+        run(&mut timers, 1001, 1020),
+        [(1010, Job::Redraw), (1020, Job::Redraw)]
+    );
+    assert_eq!(poll_all(&mut timers, 1025), []);
+    assert_eq!(poll_all(&mut timers, 1033), [Job::Redraw]);
+}
+
+#[test]
+fn zero_delay() {
+    let mut timers: Timers<Job, 2> = Timers::new(500);
+    timers.start_once(0, Job::Beep).unwrap();
+    assert_eq!(poll_all(&mut timers, 500), []);
+    assert_eq!(poll_all(&mut timers, 501), [Job::Beep]);
+    timers.start_periodic(0, Job::Redraw).unwrap();
+    assert_eq!(run(&mut timers, 502, 504).len(), 3);
+}
+
+#[test]
+fn start_from_last_poll() {
+    let mut timers: Timers<Job, 2> = Timers::new(0);
+    poll_all(&mut timers, 40);
+    timers.start_once(10, Job::Sample).unwrap();
+    assert_eq!(timers.next_deadline(), Some(50));
+    assert_eq!(run(&mut timers, 41, 60), [(50, Job::Sample)]);
+    assert_eq!(timers.next_deadline(), None);
+}
+
+#[test]
+fn restart_from_poll_loop() {
+    let mut timers: Timers<Job, 2> = Timers::new(0);
+    timers.start_once(5, Job::Beep).unwrap();
+    let mut expired = Vec::new();
+    for ms in 1..=30 {
+        while let Some(job) = timers.poll(ms) {
+            expired.push(ms);
+            // Beeps again a little later.
+            timers.start_once(7, job).unwrap();
+        }
+    }
+    assert_eq!(expired, [5, 12, 19, 26]);
+}
+
+#[test]
+fn cancel() {
+    let mut timers: Timers<Job, 3> = Timers::new(0);
+    // In the same slot, so that cancelling has to unlink from the middle.
+    let a = timers.start_once(10, Job::Redraw).unwrap();
+    let b = timers.start_once(10 + SLOTS as u32, Job::Sample).unwrap();
+    let c = timers.start_once(10 + 2 * SLOTS as u32, Job::Beep).unwrap();
+    assert_eq!(timers.cancel(b), Some(Job::Sample));
+    assert_eq!(timers.cancel(b), None);
+    assert_eq!(timers.next_deadline(), Some(10));
+    assert_eq!(
+        run(&mut timers, 1, 100),
+        [(10, Job::Redraw), (10 + 2 * SLOTS as u32, Job::Beep)]
+    );
+
+    // An old id does not cancel a new timer in the same entry.
+    let d = timers.start_once(5, Job::Sample).unwrap();
+    assert_eq!(timers.cancel(a), None);
+    assert_eq!(timers.cancel(c), None);
+    assert!(timers.is_running(d));
+}
+
+#[test]
+fn full() {
+    let mut timers: Timers<Job, 2> = Timers::new(0);
+    timers.start_once(1, Job::Redraw).unwrap();
+    timers.start_once(1, Job::Sample).unwrap();
+    assert_eq!(timers.start_once(1, Job::Beep), Err(Full));
+    poll_all(&mut timers, 1);
+    assert!(timers.start_once(1, Job::Beep).is_ok());
+}
+
+#[test]
+fn clock_wraps_around() {
+    let start = u32::MAX - 20;
+    let mut timers: Timers<Job, 2> = Timers::new(start);
+    timers.start_periodic(15, Job::Redraw).unwrap();
+    let mut expired = Vec::new();
+    let mut ms = start;
+    for _ in 0..50 {
+        ms = ms.wrapping_add(1);
+        if !poll_all(&mut timers, ms).is_empty() {
+            expired.push(ms);
+        }
+    }
+    assert_eq!(expired, [u32::MAX - 5, 9, 24]);
+}
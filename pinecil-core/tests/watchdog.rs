@@ -0,0 +1,54 @@
+use pinecil_core::watchdog::{BadTimeout, Prescale, ResetCause, MAX_RELOAD, MAX_TIMEOUT_MS};
+
+#[test]
+fn prescale() {
+    assert_eq!(
+        Prescale::for_timeout(100),
+        Ok(Prescale {
+            psc: 0,
+            reload: 999
+        })
+    );
+    // 40000 ticks only fit in 12 bits from a divider of 16 up.
+    assert_eq!(
+        Prescale::for_timeout(1000),
+        Ok(Prescale {
+            psc: 2,
+            reload: 2499
+        })
+    );
+    // 26214.4 ms, rounded down.
+    assert_eq!(MAX_TIMEOUT_MS, 26_214);
+    assert_eq!(
+        Prescale::for_timeout(MAX_TIMEOUT_MS),
+        Ok(Prescale {
+            psc: 6,
+            reload: MAX_RELOAD - 1
+        })
+    );
+    assert_eq!(Prescale::for_timeout(0), Err(BadTimeout));
+    assert_eq!(Prescale::for_timeout(MAX_TIMEOUT_MS + 1), Err(BadTimeout));
+}
+
+#[test]
+fn timeout_is_never_longer() {
+    for timeout_ms in (1..MAX_TIMEOUT_MS).step_by(7) {
+        let prescale = Prescale::for_timeout(timeout_ms).unwrap();
+        assert!(prescale.reload <= MAX_RELOAD);
+        assert!(prescale.timeout_ms() <= timeout_ms.max(1), "{}", timeout_ms);
+    }
+}
+
+#[test]
+fn reset_cause() {
+    // What a power on sets, and then what a reset from within the chip
+    // sets, which always comes with the pin.
+    assert_eq!(ResetCause::from_flags(0x0c00_0000), ResetCause::PowerOn);
+    assert_eq!(ResetCause::from_flags(0x2400_0000), ResetCause::Watchdog);
+    assert_eq!(ResetCause::from_flags(0x1400_0000), ResetCause::Software);
+    assert_eq!(ResetCause::from_flags(0x0400_0000), ResetCause::Pin);
+    assert_eq!(ResetCause::from_flags(0x8400_0000), ResetCause::LowPower);
+    // The oscillator bits below do not matter.
+    assert_eq!(ResetCause::from_flags(0x0000_0003), ResetCause::Unknown);
+    assert_eq!(ResetCause::Watchdog.to_string(), "watchdog");
+}
@@ -0,0 +1,77 @@
+//! Busy-wait delays timed by the `mcycle` counter, for the demos which use
+//! the PAC (or no crate at all) instead of `gd32vf103xx-hal`.
+//!
+//! A countdown loop takes as long as the compiler makes it, so its length
+//! changes with the optimisation level and the clock. `mcycle` counts the
+//! cycles of the core instead, and [`Delay`] turns them into time with the
+//! core clock, either given or read from the RCU.
+
+#![no_std]
+
+pub use pinecil_core::clock;
+use riscv::register::{mcycle, mcycleh};
+
+/// The clock configuration registers, see `clock::core_hz`.
+const RCU_CFG0: *const u32 = (0x4002_1000 + 0x04) as *const u32;
+const RCU_CFG1: *const u32 = (0x4002_1000 + 0x2c) as *const u32;
+
+/// The number of cycles since reset.
+///
+/// The counter is 64 bits, but is read in two halves. If the low half wraps
+/// around between the two reads, the high half is read again.
+pub fn cycles() -> u64 {
+    loop {
+        let high = mcycleh::read() as u32;
+        let low = mcycle::read() as u32;
+        if mcycleh::read() as u32 == high {
+            return (high as u64) << 32 | low as u64;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Delay {
+    core_hz: u32,
+}
+
+impl Delay {
+    /// For a core running at `core_hz`, e.g. [`clock::IRC8M_HZ`] after
+    /// reset.
+    pub const fn new(core_hz: u32) -> Self {
+        Delay { core_hz }
+    }
+
+    /// For the core clock as the RCU is currently set up. Set up the clocks
+    /// first, as a change afterwards is not noticed.
+    ///
+    /// Falls back to the clock after reset if the RCU is set to something
+    /// reserved.
+    pub fn from_rcu() -> Self {
+        // Only reads, which have no side effects.
+        let (cfg0, cfg1) = unsafe {
+            (
+                core::ptr::read_volatile(RCU_CFG0),
+                core::ptr::read_volatile(RCU_CFG1),
+            )
+        };
+        Delay::new(clock::core_hz(cfg0, cfg1).unwrap_or(clock::IRC8M_HZ))
+    }
+
+    pub fn core_hz(&self) -> u32 {
+        self.core_hz
+    }
+
+    /// Waits for at least `count` cycles.
+    pub fn delay_cycles(&self, count: u64) {
+        let start = cycles();
+        while cycles().wrapping_sub(start) < count {}
+    }
+
+    pub fn delay_us(&self, us: u32) {
+        self.delay_cycles((self.core_hz as u64 * us as u64 + 999_999) / 1_000_000);
+    }
+
+    pub fn delay_ms(&self, ms: u32) {
+        self.delay_cycles((self.core_hz as u64 * ms as u64 + 999) / 1_000);
+    }
+}
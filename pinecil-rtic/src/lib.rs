@@ -0,0 +1,225 @@
+//! A task model in the style of RTIC, run by the ECLIC.
+//!
+//! The firmware is made of tasks, each with a priority from 1 to
+//! [`PRIORITIES`], which the ECLIC runs as interrupts of that level: a task
+//! preempts the tasks of lower priority, and runs to the end before those of
+//! the same priority. `main` sets everything up with [`init`], then turns
+//! into the idle loop at priority 0 with [`idle`].
+//!
+//! - Hardware tasks are bound to the interrupt of a peripheral with
+//!   [`bind`].
+//! - Software tasks ([`SoftwareTask`]) are spawned by other tasks, or by
+//!   `main`. Each priority has an interrupt source the Pinecil does not use
+//!   as its dispatcher: spawning a task queues it and pends the dispatcher,
+//!   which then runs the queued tasks in turn.
+//! - Data shared between tasks is kept in a [`Resource`] with a ceiling, the
+//!   highest priority of the tasks which use it. Locking it raises the ECLIC
+//!   threshold to the ceiling, so no other task which uses it can start
+//!   until it is unlocked, while the tasks above the ceiling still run. This
+//!   is the stack resource policy of RTIC, and it can't deadlock.
+//!
+//! Unlike RTIC, nothing is checked at build time: a resource locked by a
+//! task above its ceiling, or locked again while it is locked, panics.
+
+#![no_std]
+
+use core::{
+    cell::{RefCell, UnsafeCell},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use critical_section::Mutex;
+use pinecil_bsp::eclic::{self, Trigger};
+
+pub use pinecil_bsp::eclic::Irq;
+
+/// The highest priority. Each priority takes one of [`DISPATCHERS`].
+pub const PRIORITIES: u8 = 3;
+
+/// The interrupt sources which run the software tasks of each priority,
+/// from 1 up.
+pub const DISPATCHERS: [Irq; PRIORITIES as usize] = [Irq::Spi2, Irq::Uart3, Irq::Uart4];
+
+/// How many software tasks of the same priority can be waiting to run.
+pub const QUEUE_LEN: usize = 8;
+
+/// The task is already waiting to run, or the queue of its priority is
+/// full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Busy;
+
+/// The software tasks waiting to run at one priority, in the order they
+/// were spawned.
+struct Queue {
+    tasks: [Option<&'static SoftwareTask>; QUEUE_LEN],
+    head: usize,
+    len: usize,
+}
+
+impl Queue {
+    const fn new() -> Self {
+        Queue {
+            tasks: [None; QUEUE_LEN],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, task: &'static SoftwareTask) -> Result<(), Busy> {
+        if self.len == QUEUE_LEN {
+            return Err(Busy);
+        }
+        self.tasks[(self.head + self.len) % QUEUE_LEN] = Some(task);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<&'static SoftwareTask> {
+        if self.len == 0 {
+            return None;
+        }
+        let task = self.tasks[self.head].take();
+        self.head = (self.head + 1) % QUEUE_LEN;
+        self.len -= 1;
+        task
+    }
+}
+
+static QUEUES: Mutex<RefCell<[Queue; PRIORITIES as usize]>> =
+    Mutex::new(RefCell::new([Queue::new(), Queue::new(), Queue::new()]));
+
+/// Switches the ECLIC on, with all of its bits as the level, and sets up
+/// the dispatchers. Interrupts stay off until [`idle`].
+pub fn init() {
+    eclic::init();
+    let handlers: [fn(); PRIORITIES as usize] = [|| dispatch(1), || dispatch(2), || dispatch(3)];
+    for (priority, (&irq, &handler)) in (1..).zip(DISPATCHERS.iter().zip(&handlers)) {
+        // Only an edge-triggered source can be pended by software.
+        eclic::set_trigger(irq, Trigger::RisingEdge);
+        eclic::set_level(irq, priority);
+        eclic::set_handler(irq, handler);
+        eclic::enable(irq);
+    }
+}
+
+/// Makes `handler` a task of `priority`, run by the interrupt `irq`.
+///
+/// # Panics
+///
+/// If `priority` is 0 or above [`PRIORITIES`].
+pub fn bind(irq: Irq, priority: u8, handler: fn()) {
+    assert!((1..=PRIORITIES).contains(&priority), "no such priority");
+    eclic::set_handler(irq, handler);
+    eclic::set_level(irq, priority);
+    eclic::enable(irq);
+}
+
+/// Turns interrupts on, which starts the tasks, and sleeps whenever none is
+/// running.
+pub fn idle() -> ! {
+    unsafe { riscv::register::mstatus::set_mie() };
+    loop {
+        unsafe { riscv::asm::wfi() };
+    }
+}
+
+/// The priority of the running task, 0 for `main` and idle.
+pub fn current_priority() -> u8 {
+    eclic::current_level()
+}
+
+fn dispatch(priority: u8) {
+    let queue = priority as usize - 1;
+    while let Some(task) = critical_section::with(|cs| QUEUES.borrow_ref_mut(cs)[queue].pop()) {
+        // Cleared first, so that the task can spawn itself again.
+        task.spawned.store(false, Ordering::Release);
+        (task.run)();
+    }
+}
+
+/// A task run on request, at its priority.
+pub struct SoftwareTask {
+    priority: u8,
+    run: fn(),
+    spawned: AtomicBool,
+}
+
+impl SoftwareTask {
+    /// # Panics
+    ///
+    /// If `priority` is 0 or above [`PRIORITIES`], on spawning.
+    pub const fn new(priority: u8, run: fn()) -> Self {
+        SoftwareTask {
+            priority,
+            run,
+            spawned: AtomicBool::new(false),
+        }
+    }
+
+    /// Queues the task to run once the tasks of its priority and above are
+    /// done. It runs once however many times it is spawned before then, so
+    /// spawning it again is an error.
+    pub fn spawn(&'static self) -> Result<(), Busy> {
+        assert!(
+            (1..=PRIORITIES).contains(&self.priority),
+            "no such priority"
+        );
+        if self.spawned.swap(true, Ordering::AcqRel) {
+            return Err(Busy);
+        }
+        let queue = self.priority as usize - 1;
+        let queued = critical_section::with(|cs| QUEUES.borrow_ref_mut(cs)[queue].push(self));
+        if queued.is_err() {
+            self.spawned.store(false, Ordering::Release);
+            return queued;
+        }
+        eclic::pend(DISPATCHERS[queue]);
+        Ok(())
+    }
+}
+
+/// Data shared by the tasks up to priority `ceiling`.
+pub struct Resource<T> {
+    ceiling: u8,
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+// Only one task at a time gets to the value, see `lock`.
+unsafe impl<T: Send> Sync for Resource<T> {}
+
+impl<T> Resource<T> {
+    pub const fn new(ceiling: u8, value: T) -> Self {
+        Resource {
+            ceiling,
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn ceiling(&self) -> u8 {
+        self.ceiling
+    }
+
+    /// Runs `f` on the value with the tasks up to the ceiling held back.
+    ///
+    /// # Panics
+    ///
+    /// If the running task is above the ceiling, or the resource is already
+    /// locked, which could only be by the running task.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        assert!(
+            current_priority() <= self.ceiling,
+            "resource locked above its ceiling"
+        );
+        eclic::with_threshold(self.ceiling, || {
+            assert!(
+                !self.locked.swap(true, Ordering::Acquire),
+                "resource locked twice"
+            );
+            let result = f(unsafe { &mut *self.value.get() });
+            self.locked.store(false, Ordering::Release);
+            result
+        })
+    }
+}
@@ -0,0 +1,161 @@
+//! Reader for the parts of BDF (Glyph Bitmap Distribution Format) files
+//! which describe the glyphs, as used by the X11 bitmap fonts.
+
+use std::{collections::BTreeMap, convert::TryFrom, error::Error, fmt};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glyph {
+    /// Size of the bitmap.
+    pub width: u32,
+    pub height: u32,
+    /// Offset of the bottom left corner of the bitmap from the pen position
+    /// on the baseline, with y pointing up.
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub advance: u32,
+    /// Row-major, from the top.
+    pub pixels: Vec<bool>,
+}
+
+impl Glyph {
+    pub fn pixel(&self, x: u32, y: u32) -> bool {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Font {
+    /// Rows above the baseline.
+    pub ascent: u32,
+    /// Rows below the baseline.
+    pub descent: u32,
+    /// By code point.
+    pub glyphs: BTreeMap<u32, Glyph>,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+fn numbers<T: std::str::FromStr>(words: &[&str], count: usize) -> Result<Vec<T>, String> {
+    let values: Vec<T> = words.iter().filter_map(|w| w.parse().ok()).collect();
+    if words.len() != count || values.len() != count {
+        return Err(format!("expected {} numbers", count));
+    }
+    Ok(values)
+}
+
+#[derive(Default)]
+struct PendingGlyph {
+    encoding: Option<u32>,
+    advance: Option<u32>,
+    bbx: Option<(u32, u32, i32, i32)>,
+}
+
+pub fn parse(bdf: &str) -> Result<Font, ParseError> {
+    let mut ascent = None;
+    let mut descent = None;
+    let mut bounding_box = None;
+    let mut default_advance = None;
+    let mut glyphs = BTreeMap::new();
+    let mut glyph: Option<PendingGlyph> = None;
+    let mut lines = bdf.lines().enumerate();
+
+    while let Some((i, line)) = lines.next() {
+        let error = |message: String| ParseError {
+            line: i + 1,
+            message,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (keyword, args) = match words.split_first() {
+            Some((keyword, args)) => (*keyword, args),
+            None => continue,
+        };
+        match (keyword, glyph.as_mut()) {
+            ("FONT_ASCENT", None) => ascent = Some(numbers(args, 1).map_err(error)?[0]),
+            ("FONT_DESCENT", None) => descent = Some(numbers(args, 1).map_err(error)?[0]),
+            ("FONTBOUNDINGBOX", None) => {
+                let n: Vec<i32> = numbers(args, 4).map_err(error)?;
+                bounding_box = Some((n[1], n[3]));
+            }
+            ("DWIDTH", None) => default_advance = Some(numbers(args, 2).map_err(error)?[0]),
+            ("STARTCHAR", None) => glyph = Some(PendingGlyph::default()),
+            ("ENCODING", Some(g)) => {
+                // -1 means that the glyph has no standard encoding.
+                let n: Vec<i64> = numbers(args.get(..1).unwrap_or(&[]), 1).map_err(error)?;
+                g.encoding = u32::try_from(n[0]).ok();
+            }
+            ("DWIDTH", Some(g)) => g.advance = Some(numbers(args, 2).map_err(error)?[0]),
+            ("BBX", Some(g)) => {
+                let n: Vec<i32> = numbers(args, 4).map_err(error)?;
+                if n[0] < 0 || n[1] < 0 {
+                    return Err(error("negative bitmap size".to_owned()));
+                }
+                g.bbx = Some((n[0] as u32, n[1] as u32, n[2], n[3]));
+            }
+            ("BITMAP", Some(g)) => {
+                let (width, height, x_offset, y_offset) = g
+                    .bbx
+                    .ok_or_else(|| error("BITMAP without BBX".to_owned()))?;
+                let mut pixels = Vec::with_capacity((width * height) as usize);
+                for _ in 0..height {
+                    let (i, row) = lines
+                        .next()
+                        .ok_or_else(|| error("bitmap is cut short".to_owned()))?;
+                    let row = row.trim();
+                    let bits = row.len() as u32 * 4;
+                    let value = u64::from_str_radix(row, 16)
+                        .ok()
+                        .filter(|_| bits >= width && bits <= 64)
+                        .ok_or_else(|| ParseError {
+                            line: i + 1,
+                            message: format!("bad bitmap row '{}'", row),
+                        })?;
+                    pixels.extend((0..width).map(|x| value & (1 << (bits - 1 - x)) != 0));
+                }
+                let advance = g
+                    .advance
+                    .or(default_advance)
+                    .ok_or_else(|| error("glyph without DWIDTH".to_owned()))?;
+                if let Some(encoding) = g.encoding {
+                    glyphs.insert(
+                        encoding,
+                        Glyph {
+                            width,
+                            height,
+                            x_offset,
+                            y_offset,
+                            advance,
+                            pixels,
+                        },
+                    );
+                }
+            }
+            ("ENDCHAR", Some(_)) => glyph = None,
+            _ => {}
+        }
+    }
+
+    // The properties are optional, the bounding box is not.
+    let (height, bottom) = bounding_box.ok_or(ParseError {
+        line: 0,
+        message: "no FONTBOUNDINGBOX".to_owned(),
+    })?;
+    let ascent = ascent.unwrap_or_else(|| (height + bottom).max(0) as u32);
+    let descent = descent.unwrap_or_else(|| (-bottom).max(0) as u32);
+    Ok(Font {
+        ascent,
+        descent,
+        glyphs,
+    })
+}
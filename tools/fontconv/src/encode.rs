@@ -0,0 +1,172 @@
+//! Encoder for the bitmap font format of `pinecil_core::text`.
+
+use std::convert::TryFrom;
+
+use pinecil_core::text::{HEADER_LEN, MAGIC};
+
+use crate::bdf;
+
+/// Printable ASCII, Latin-1 and Latin Extended-A, which cover the languages
+/// of western and central Europe.
+pub const LATIN: &[(u32, u32)] = &[(0x20, 0x7e), (0xa0, 0x17f)];
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Inclusive ranges of code points to convert. Code points which the
+    /// font does not have are skipped.
+    pub chars: Vec<(u32, u32)>,
+    /// Make the advance of each glyph the width of its ink plus `spacing`,
+    /// instead of keeping the advance of the font.
+    pub proportional: bool,
+    pub spacing: u32,
+}
+
+/// Parses ranges like `0x20-0x7e,0xa0-0x17f`, in hex or decimal.
+pub fn parse_chars(s: &str) -> Result<Vec<(u32, u32)>, String> {
+    let number = |n: &str| {
+        let n = n.trim();
+        let value = match n.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => n.parse(),
+        };
+        value.map_err(|_| format!("bad code point '{}'", n))
+    };
+    s.split(',')
+        .map(|range| {
+            let (first, last) = match range.split_once('-') {
+                Some((first, last)) => (number(first)?, number(last)?),
+                None => (number(range)?, number(range)?),
+            };
+            if first > last {
+                return Err(format!("empty range '{}'", range));
+            }
+            Ok((first, last))
+        })
+        .collect()
+}
+
+/// A glyph cropped to its ink, in the coordinates of the file.
+struct Glyph {
+    width: u32,
+    height: u32,
+    x_offset: i32,
+    /// From the top of the line, down.
+    y_offset: i32,
+    advance: u32,
+    pixels: Vec<bool>,
+}
+
+fn crop(glyph: &bdf::Font, code: u32, options: &Options) -> Glyph {
+    let g = &glyph.glyphs[&code];
+    let lit: Vec<(u32, u32)> = (0..g.height)
+        .flat_map(|y| (0..g.width).map(move |x| (x, y)))
+        .filter(|&(x, y)| g.pixel(x, y))
+        .collect();
+    let empty_advance = if options.proportional {
+        (g.advance + 1) / 2
+    } else {
+        g.advance
+    };
+    let (left, right) = match (lit.iter().map(|p| p.0).min(), lit.iter().map(|p| p.0).max()) {
+        (Some(left), Some(right)) => (left, right),
+        _ => {
+            return Glyph {
+                width: 0,
+                height: 0,
+                x_offset: 0,
+                y_offset: 0,
+                advance: empty_advance,
+                pixels: Vec::new(),
+            }
+        }
+    };
+    let top = lit.iter().map(|p| p.1).min().unwrap();
+    let bottom = lit.iter().map(|p| p.1).max().unwrap();
+    let (width, height) = (right - left + 1, bottom - top + 1);
+    let pixels = (top..=bottom)
+        .flat_map(|y| (left..=right).map(move |x| g.pixel(x, y)))
+        .collect();
+
+    // The top row of the BDF bitmap is `y_offset + height - 1` rows above the
+    // baseline, and the baseline is `ascent` rows below the top of the line.
+    let y_offset = glyph.ascent as i32 - (g.y_offset + g.height as i32) + top as i32;
+    let (x_offset, advance) = if options.proportional {
+        (0, width + options.spacing)
+    } else {
+        (g.x_offset + left as i32, g.advance)
+    };
+    Glyph {
+        width,
+        height,
+        x_offset,
+        y_offset,
+        advance,
+        pixels,
+    }
+}
+
+fn field<T: TryFrom<i64>>(value: i64, what: &str, code: u32) -> Result<T, String> {
+    T::try_from(value).map_err(|_| format!("{} of U+{:04X} is out of range: {}", what, code, value))
+}
+
+pub fn encode(font: &bdf::Font, options: &Options) -> Result<Vec<u8>, String> {
+    let codes: Vec<u32> = font
+        .glyphs
+        .keys()
+        .copied()
+        .filter(|&c| options.chars.iter().any(|&(f, l)| (f..=l).contains(&c)))
+        .collect();
+    if !codes.contains(&('?' as u32)) {
+        return Err("the font needs a '?', which replaces missing characters".to_owned());
+    }
+    if let Some(&code) = codes.iter().find(|&&c| c > 0xffff) {
+        return Err(format!(
+            "U+{:04X} is outside of the Basic Multilingual Plane",
+            code
+        ));
+    }
+
+    // Consecutive code points share a range.
+    let mut ranges: Vec<(u32, u32, usize)> = Vec::new();
+    for (index, &code) in codes.iter().enumerate() {
+        match ranges.last_mut() {
+            Some((first, count, _)) if *first + *count == code => *count += 1,
+            _ => ranges.push((code, 1, index)),
+        }
+    }
+
+    let mut glyph_table = Vec::new();
+    let mut bitmaps = Vec::new();
+    for &code in &codes {
+        let g = crop(font, code, options);
+        let offset: u16 = field(bitmaps.len() as i64, "bitmap offset", code)?;
+        glyph_table.extend_from_slice(&offset.to_le_bytes());
+        glyph_table.push(field(g.width as i64, "width", code)?);
+        glyph_table.push(field(g.height as i64, "height", code)?);
+        glyph_table.push(field::<i8>(g.x_offset as i64, "x offset", code)? as u8);
+        glyph_table.push(field::<i8>(g.y_offset as i64, "y offset", code)? as u8);
+        glyph_table.push(field(g.advance as i64, "advance", code)?);
+
+        let mut bits = vec![0u8; (g.pixels.len() + 7) / 8];
+        for (i, _) in g.pixels.iter().enumerate().filter(|(_, &on)| on) {
+            bits[i / 8] |= 0x80 >> (i % 8);
+        }
+        bitmaps.extend_from_slice(&bits);
+    }
+
+    let line_height = font.ascent + font.descent;
+    let mut out = Vec::with_capacity(HEADER_LEN);
+    out.extend_from_slice(MAGIC);
+    out.push(field(line_height as i64, "line height", 0)?);
+    out.push(field(font.ascent as i64, "baseline", 0)?);
+    out.extend_from_slice(&(ranges.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(codes.len() as u16).to_le_bytes());
+    for (first, count, index) in ranges {
+        out.extend_from_slice(&(first as u16).to_le_bytes());
+        out.extend_from_slice(&(count as u16).to_le_bytes());
+        out.extend_from_slice(&(index as u16).to_le_bytes());
+    }
+    out.extend_from_slice(&glyph_table);
+    out.extend_from_slice(&bitmaps);
+    Ok(out)
+}
@@ -0,0 +1,5 @@
+//! Conversion of BDF fonts into the bitmap font format of
+//! `pinecil_core::text`.
+
+pub mod bdf;
+pub mod encode;
@@ -0,0 +1,90 @@
+use std::{env, error::Error, fs, path::PathBuf, process};
+
+use fontconv::{bdf, encode};
+use pinecil_core::text::Font;
+
+const USAGE: &str = "\
+Usage: fontconv [options] <font.bdf> -o <font.pfnt>
+
+Converts a BDF font into the bitmap font format of `pinecil_core::text`.
+
+Options:
+    -o, --output <file>     Where to write the converted font
+    --chars <ranges>        Code points to convert, e.g. 0x20-0x7e,0xa0-0xff
+                            (default: ASCII, Latin-1 and Latin Extended-A)
+    --proportional          Crop each glyph to its ink, for proportional text
+    --spacing <px>          Gap after each glyph with --proportional
+                            (default: 1)
+";
+
+struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    options: encode::Options,
+}
+
+fn parse_args() -> Result<Args, Box<dyn Error>> {
+    let mut input = None;
+    let mut output = None;
+    let mut options = encode::Options {
+        chars: encode::LATIN.to_vec(),
+        proportional: false,
+        spacing: 1,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(value()?.into()),
+            "--chars" => options.chars = encode::parse_chars(&value()?)?,
+            "--proportional" => options.proportional = true,
+            "--spacing" => options.spacing = value()?.parse()?,
+            "-h" | "--help" => return Err("".into()),
+            a if a.starts_with('-') => return Err(format!("unknown option {}", a).into()),
+            _ if input.is_some() => return Err("only one input font is supported".into()),
+            _ => input = Some(arg.into()),
+        }
+    }
+    match (input, output) {
+        (Some(input), Some(output)) => Ok(Args {
+            input,
+            output,
+            options,
+        }),
+        _ => Err("".into()),
+    }
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.to_string().is_empty() {
+                eprintln!("error: {}\n", e);
+            }
+            eprint!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let font = bdf::parse(&fs::read_to_string(&args.input)?)
+        .map_err(|e| format!("{}: {}", args.input.display(), e))?;
+    let data = encode::encode(&font, &args.options)?;
+    // Same check as on the device, to catch a broken encoder right away.
+    let parsed = Font::parse(&data).map_err(|e| format!("bad output: {:?}", e))?;
+    fs::write(&args.output, &data)?;
+    println!(
+        "Wrote {}: {} glyphs, line height {}, {} bytes",
+        args.output.display(),
+        u16::from_le_bytes([data[8], data[9]]),
+        parsed.line_height(),
+        data.len()
+    );
+    Ok(())
+}
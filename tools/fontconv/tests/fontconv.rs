@@ -0,0 +1,180 @@
+//! Tests of the BDF converter against the firmware font engine.
+
+use std::{fs, path::Path};
+
+use fontconv::{bdf, encode};
+use pinecil_core::{
+    display::FrameBuffer,
+    text::{self, Error, Font},
+};
+
+fn fixture() -> bdf::Font {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tiny.bdf");
+    bdf::parse(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn convert(proportional: bool) -> Vec<u8> {
+    let options = encode::Options {
+        chars: encode::LATIN.to_vec(),
+        proportional,
+        spacing: 1,
+    };
+    encode::encode(&fixture(), &options).unwrap()
+}
+
+/// The lit pixels of `glyph`, as rows of `#` and `.`.
+fn rows(glyph: text::Glyph) -> Vec<String> {
+    (0..glyph.height)
+        .map(|y| {
+            (0..glyph.width)
+                .map(|x| if glyph.pixel(x, y) { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn parses_bdf() {
+    let font = fixture();
+    assert_eq!((font.ascent, font.descent), (5, 1));
+    assert_eq!(
+        font.glyphs.keys().copied().collect::<Vec<_>>(),
+        [32, 63, 73, 196]
+    );
+    let i = &font.glyphs[&73];
+    assert_eq!(
+        (i.width, i.height, i.x_offset, i.y_offset, i.advance),
+        (4, 6, 0, -1, 4)
+    );
+    assert!(i.pixel(1, 0) && !i.pixel(0, 0) && !i.pixel(1, 5));
+}
+
+#[test]
+fn monospace_round_trip() {
+    let data = convert(false);
+    let font = Font::parse(&data).unwrap();
+    assert_eq!((font.line_height(), font.baseline()), (6, 5));
+
+    let i = font.glyph('I').unwrap();
+    assert_eq!(
+        (i.width, i.height, i.x_offset, i.y_offset, i.advance),
+        (1, 5, 1, 0, 4)
+    );
+    assert_eq!(rows(i), ["#"; 5]);
+
+    let a = font.glyph('Ä').unwrap();
+    assert_eq!((a.x_offset, a.y_offset, a.advance), (0, 0, 4));
+    assert_eq!(rows(a), ["#.#.", "....", ".##.", "#..#", "####", "#..#"]);
+
+    let space = font.glyph(' ').unwrap();
+    assert_eq!((space.width, space.height, space.advance), (0, 0, 4));
+    assert_eq!(font.measure("I Ä"), 12);
+}
+
+#[test]
+fn proportional_widths() {
+    let data = convert(true);
+    let font = Font::parse(&data).unwrap();
+    let i = font.glyph('I').unwrap();
+    assert_eq!((i.x_offset, i.advance), (0, 2));
+    assert_eq!(font.glyph('?').unwrap().advance, 5);
+    assert_eq!(font.glyph(' ').unwrap().advance, 2);
+    assert_eq!(font.measure("I I"), 6);
+}
+
+#[test]
+fn missing_characters_are_replaced() {
+    let data = convert(true);
+    let font = Font::parse(&data).unwrap();
+    assert_eq!(font.glyph('x'), None);
+    assert_eq!(font.glyph('€'), None);
+    assert_eq!(font.measure("x€"), font.measure("??"));
+
+    let mut replaced = FrameBuffer::new();
+    let mut question = FrameBuffer::new();
+    font.draw("x", Default::default(), &mut replaced).unwrap();
+    font.draw("?", Default::default(), &mut question).unwrap();
+    assert!(replaced.as_bytes() == question.as_bytes());
+}
+
+#[test]
+fn draws_centred_text() {
+    let data = convert(true);
+    let font = Font::parse(&data).unwrap();
+    let mut fb = FrameBuffer::new();
+    // "II" is 4 pixels wide, with the ink in columns 0 and 2.
+    let end = font.draw_centred("II", 10, 3, &mut fb).unwrap();
+    assert_eq!((end.x, end.y), (12, 3));
+    for y in 0..16 {
+        for x in 0..96 {
+            let lit = (x == 8 || x == 10) && (3..8).contains(&y);
+            assert_eq!(fb.pixel(x, y), lit, "pixel ({}, {})", x, y);
+        }
+    }
+
+    // Glyphs which hang off the edges are clipped.
+    let mut fb = FrameBuffer::new();
+    font.draw_centred("ÄÄ", 0, -2, &mut fb).unwrap();
+    // The second Ä starts at column 0, and its dots are above row 0.
+    assert!(fb.pixel(1, 0) && fb.pixel(0, 1) && fb.pixel(3, 2));
+    assert!(!fb.pixel(0, 0) && !fb.pixel(0, 4));
+}
+
+#[test]
+fn rejects_bad_fonts() {
+    let data = convert(true);
+    assert_eq!(Font::parse(b"PFN").unwrap_err(), Error::BadMagic);
+    assert_eq!(
+        Font::parse(b"FONT\x06\x05\0\0\0\0").unwrap_err(),
+        Error::BadMagic
+    );
+    assert_eq!(Font::parse(&data[..9]).unwrap_err(), Error::Truncated);
+    assert_eq!(Font::parse(&data[..20]).unwrap_err(), Error::Truncated);
+
+    // The ranges must be sorted, and point at glyphs.
+    let mut unsorted = data.clone();
+    unsorted[text::HEADER_LEN + text::RANGE_LEN] = 0;
+    assert_eq!(Font::parse(&unsorted).unwrap_err(), Error::BadRange);
+    let mut past_end = data.clone();
+    past_end[text::HEADER_LEN + 4] = 200;
+    assert_eq!(Font::parse(&past_end).unwrap_err(), Error::BadRange);
+
+    // So must the bitmaps.
+    let last_bitmap = data.len() - 1;
+    assert_eq!(
+        Font::parse(&data[..last_bitmap]).unwrap_err(),
+        Error::BadGlyph
+    );
+}
+
+#[test]
+fn needs_a_replacement_glyph() {
+    let options = encode::Options {
+        chars: encode::parse_chars("0x49,196").unwrap(),
+        proportional: false,
+        spacing: 1,
+    };
+    assert!(encode::encode(&fixture(), &options).is_err());
+    assert_eq!(
+        encode::parse_chars("0x20-0x7e, 160").unwrap(),
+        [(0x20, 0x7e), (160, 160)]
+    );
+    assert!(encode::parse_chars("0x7e-0x20").is_err());
+}
+
+#[test]
+fn checked_in_fonts_are_up_to_date() {
+    let fonts = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../pinecil-core/fonts");
+    for (bdf, pfnt) in [("5x8.bdf", text::SMALL), ("6x13B.bdf", text::MEDIUM)] {
+        let font = bdf::parse(&fs::read_to_string(fonts.join(bdf)).unwrap()).unwrap();
+        let options = encode::Options {
+            chars: encode::LATIN.to_vec(),
+            proportional: true,
+            spacing: 1,
+        };
+        let data = encode::encode(&font, &options).unwrap();
+        assert!(data == pfnt, "{} is out of date, see fonts/README.md", bdf);
+        let font = Font::parse(pfnt).unwrap();
+        assert!("ÀÉÎõüßąčęłńőřšžŻ°".chars().all(|c| font.glyph(c).is_some()));
+    }
+}
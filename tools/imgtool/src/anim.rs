@@ -0,0 +1,101 @@
+//! Encoder for the compressed animation format of `pinecil_core::anim`.
+
+use pinecil_core::anim::{FrameKind, HEADER_LEN, MAGIC, MAX_LITERAL, MAX_RUN};
+
+/// Rearranges row-major pixels into SSD1306 pages: one byte per column of 8
+/// rows, top pixel in the least significant bit. The height has to be a
+/// whole number of pages.
+pub fn to_pages(lit: &[bool], width: usize, height: usize) -> Result<Vec<u8>, String> {
+    if height % 8 != 0 {
+        return Err(format!("height {} is not a multiple of 8", height));
+    }
+    let mut pages = vec![0; width * height / 8];
+    for (i, _) in lit.iter().enumerate().filter(|(_, &on)| on) {
+        let (x, y) = (i % width, i / width);
+        pages[y / 8 * width + x] |= 1 << (y % 8);
+    }
+    Ok(pages)
+}
+
+/// Run-length encodes `data`. Runs shorter than 3 bytes are not worth it and
+/// are stored as literals.
+pub fn rle_encode(data: &[u8]) -> Vec<u8> {
+    fn flush(out: &mut Vec<u8>, literal: &[u8]) {
+        for chunk in literal.chunks(MAX_LITERAL) {
+            out.push((chunk.len() - 1) as u8);
+            out.extend_from_slice(chunk);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == data[i])
+            .count();
+        if run >= 3 {
+            flush(&mut out, &data[literal_start..i]);
+            out.push(0x80 | (run - 2) as u8);
+            out.push(data[i]);
+            i += run;
+            literal_start = i;
+        } else {
+            i += 1;
+        }
+    }
+    flush(&mut out, &data[literal_start..]);
+    out
+}
+
+pub struct Frame {
+    /// Frame in SSD1306 page layout, see `to_pages`.
+    pub pages: Vec<u8>,
+    pub delay_ms: u16,
+}
+
+/// Encodes the frames, using XOR against the previous frame whenever that
+/// comes out smaller.
+pub fn encode(width: u16, height: u16, frames: &[Frame]) -> Result<Vec<u8>, String> {
+    if height % 8 != 0 {
+        return Err(format!("height {} is not a multiple of 8", height));
+    }
+    let frame_len = width as usize * height as usize / 8;
+    if frames.iter().any(|f| f.pages.len() != frame_len) {
+        return Err("all frames must have the same size".to_owned());
+    }
+
+    let mut out = Vec::with_capacity(HEADER_LEN);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.extend_from_slice(&(frames.len() as u16).to_le_bytes());
+
+    let mut previous: Option<&[u8]> = None;
+    for frame in frames {
+        let key = rle_encode(&frame.pages);
+        let (kind, data) = match previous {
+            Some(prev) => {
+                let xor: Vec<u8> = prev.iter().zip(&frame.pages).map(|(a, b)| a ^ b).collect();
+                let xor = rle_encode(&xor);
+                if xor.len() < key.len() {
+                    (FrameKind::Xor, xor)
+                } else {
+                    (FrameKind::Key, key)
+                }
+            }
+            None => (FrameKind::Key, key),
+        };
+        if data.len() > u16::MAX as usize {
+            return Err("frame too large".to_owned());
+        }
+        out.push(kind.to_byte());
+        out.extend_from_slice(&frame.delay_ms.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        out.extend_from_slice(&data);
+        previous = Some(&frame.pages);
+    }
+    Ok(out)
+}
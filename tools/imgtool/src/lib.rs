@@ -0,0 +1,5 @@
+//! Conversion of images into the formats used by the OLED demos.
+
+pub mod anim;
+pub mod load;
+pub mod mono;
@@ -0,0 +1,108 @@
+//! Decoding of PNG images and GIF animations into RGBA frames.
+
+use std::{error::Error, fs::File, io::BufReader, path::Path};
+
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// 8-bit RGBA, row-major.
+    pub rgba: Vec<u8>,
+    /// How long the frame is shown, if the file says so.
+    pub delay_ms: Option<u16>,
+}
+
+pub fn load(path: &Path) -> Result<Vec<Frame>, Box<dyn Error>> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("png") => Ok(vec![load_png(path)?]),
+        Some("gif") => load_gif(path),
+        _ => Err(format!("{}: expected a .png or .gif file", path.display()).into()),
+    }
+}
+
+fn load_png(path: &Path) -> Result<Frame, Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    // Expand palettes and low bit depths, and strip 16-bit down to 8-bit.
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let buf = &buf[..info.buffer_size()];
+
+    let rgba = match info.color_type {
+        png::ColorType::Grayscale => buf.iter().flat_map(|&l| [l, l, l, 0xff]).collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 0xff])
+            .collect(),
+        png::ColorType::Rgba => buf.to_vec(),
+        png::ColorType::Indexed => unreachable!("palette should have been expanded"),
+    };
+    Ok(Frame {
+        width: info.width,
+        height: info.height,
+        rgba,
+        delay_ms: None,
+    })
+}
+
+/// Decodes all frames of a GIF, composing each frame onto the previous ones
+/// according to its disposal method.
+fn load_gif(path: &Path) -> Result<Vec<Frame>, Box<dyn Error>> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(BufReader::new(File::open(path)?))?;
+    let width = decoder.width() as usize;
+    let height = decoder.height() as usize;
+
+    let mut canvas = vec![0; width * height * 4];
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame()? {
+        let saved = match frame.dispose {
+            gif::DisposalMethod::Previous => Some(canvas.clone()),
+            _ => None,
+        };
+
+        let (left, top) = (frame.left as usize, frame.top as usize);
+        let rows = (frame.height as usize).min(height.saturating_sub(top));
+        let cols = (frame.width as usize).min(width.saturating_sub(left));
+        for y in 0..rows {
+            for x in 0..cols {
+                let src = (y * frame.width as usize + x) * 4;
+                let pixel = &frame.buffer[src..src + 4];
+                // Transparent pixels let the previous frame show through.
+                if pixel[3] != 0 {
+                    let dst = ((top + y) * width + left + x) * 4;
+                    canvas[dst..dst + 4].copy_from_slice(pixel);
+                }
+            }
+        }
+
+        frames.push(Frame {
+            width: width as u32,
+            height: height as u32,
+            rgba: canvas.clone(),
+            // GIF delays are in units of 10 ms.
+            delay_ms: Some(frame.delay.saturating_mul(10)),
+        });
+
+        match frame.dispose {
+            gif::DisposalMethod::Background => {
+                for y in 0..rows {
+                    let dst = ((top + y) * width + left) * 4;
+                    canvas[dst..dst + cols * 4].fill(0);
+                }
+            }
+            gif::DisposalMethod::Previous => canvas = saved.unwrap(),
+            _ => {}
+        }
+    }
+    Ok(frames)
+}
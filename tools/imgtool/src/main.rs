@@ -0,0 +1,229 @@
+use std::{
+    env,
+    error::Error,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use imgtool::{anim, load, mono};
+
+const USAGE: &str = "\
+Usage: imgtool [options] <input>...
+
+Converts PNG images and GIF animations into raw 1-bit frames which can be
+loaded with `ImageRaw::<BinaryColor>::new(include_bytes!(...), width, height)`.
+`frame.png` becomes `frame.raw`, and the frames of `anim.gif` become
+`anim0.raw`, `anim1.raw` and so on.
+
+With `--anim`, all frames are written into one compressed animation for
+`pinecil_core::anim` instead.
+
+Options:
+    -o, --out-dir <dir>     Write the output here instead of next to the input
+    --threshold <0-255>     Light up pixels at least this bright (default: 128)
+    --dither                Use Floyd-Steinberg dithering instead of a threshold
+    --invert                Light up the dark pixels instead
+    --rust <file.rs>        Also write a Rust module listing all the frames,
+                            placed in the output directory of the first input
+    --anim <file.anim>      Write a compressed animation of all the frames
+                            instead of raw frames, placed in the output
+                            directory of the first input
+    --delay <ms>            Frame delay for the Rust module and the animation
+                            when the input has none, e.g. PNG files
+                            (default: 250)
+";
+
+struct Args {
+    inputs: Vec<PathBuf>,
+    out_dir: Option<PathBuf>,
+    options: mono::Options,
+    rust_module: Option<String>,
+    anim: Option<String>,
+    default_delay_ms: u16,
+}
+
+fn parse_args() -> Result<Args, Box<dyn Error>> {
+    let mut args = Args {
+        inputs: Vec::new(),
+        out_dir: None,
+        options: mono::Options {
+            mode: mono::Mode::Threshold(128),
+            invert: false,
+        },
+        rust_module: None,
+        anim: None,
+        default_delay_ms: 250,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "-o" | "--out-dir" => args.out_dir = Some(value()?.into()),
+            "--threshold" => args.options.mode = mono::Mode::Threshold(value()?.parse()?),
+            "--dither" => args.options.mode = mono::Mode::Dither,
+            "--invert" => args.options.invert = true,
+            "--rust" => args.rust_module = Some(value()?),
+            "--anim" => args.anim = Some(value()?),
+            "--delay" => args.default_delay_ms = value()?.parse()?,
+            "-h" | "--help" => return Err("".into()),
+            a if a.starts_with('-') => return Err(format!("unknown option {}", a).into()),
+            _ => args.inputs.push(arg.into()),
+        }
+    }
+    if args.inputs.is_empty() {
+        return Err("".into());
+    }
+    Ok(args)
+}
+
+struct Output {
+    file_name: String,
+    source: String,
+    width: u32,
+    height: u32,
+    delay_ms: u16,
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.to_string().is_empty() {
+                eprintln!("error: {}\n", e);
+            }
+            eprint!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    if let Some(anim) = &args.anim {
+        return write_anim(args, anim);
+    }
+
+    let mut outputs = Vec::new();
+    let mut module_dir = None;
+    for input in &args.inputs {
+        let frames = load::load(input)?;
+        let out_dir = match &args.out_dir {
+            Some(dir) => dir.clone(),
+            None => input.parent().unwrap_or_else(|| Path::new("")).to_owned(),
+        };
+        fs::create_dir_all(&out_dir)?;
+        let stem = input.file_stem().unwrap().to_string_lossy();
+
+        for (i, frame) in frames.iter().enumerate() {
+            let file_name = if frames.len() == 1 {
+                format!("{}.raw", stem)
+            } else {
+                format!("{}{}.raw", stem, i)
+            };
+            let lit = mono::to_mono(frame, args.options);
+            fs::write(
+                out_dir.join(&file_name),
+                mono::pack(&lit, frame.width as usize),
+            )?;
+            println!("{} -> {}", input.display(), file_name);
+            outputs.push(Output {
+                file_name,
+                source: input.file_name().unwrap().to_string_lossy().into_owned(),
+                width: frame.width,
+                height: frame.height,
+                delay_ms: frame.delay_ms.unwrap_or(args.default_delay_ms),
+            });
+        }
+        module_dir.get_or_insert(out_dir);
+    }
+
+    if let (Some(name), Some(dir)) = (&args.rust_module, module_dir) {
+        let path = dir.join(name);
+        fs::write(&path, rust_module(&outputs)?)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn write_anim(args: &Args, file_name: &str) -> Result<(), Box<dyn Error>> {
+    let mut frames = Vec::new();
+    let mut size = None;
+    for input in &args.inputs {
+        for frame in load::load(input)? {
+            let (width, height) = *size.get_or_insert((frame.width, frame.height));
+            if (frame.width, frame.height) != (width, height) {
+                return Err(
+                    format!("{}: all frames must have the same size", input.display()).into(),
+                );
+            }
+            let lit = mono::to_mono(&frame, args.options);
+            let pages = anim::to_pages(&lit, width as usize, height as usize)
+                .map_err(|e| format!("{}: {}", input.display(), e))?;
+            frames.push(anim::Frame {
+                pages,
+                delay_ms: frame.delay_ms.unwrap_or(args.default_delay_ms),
+            });
+        }
+    }
+    let (width, height) = size.unwrap();
+    let data = anim::encode(width as u16, height as u16, &frames)?;
+
+    let out_dir = match &args.out_dir {
+        Some(dir) => dir.clone(),
+        None => args.inputs[0]
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_owned(),
+    };
+    fs::create_dir_all(&out_dir)?;
+    let path = out_dir.join(file_name);
+    fs::write(&path, &data)?;
+    println!(
+        "Wrote {}: {} frames, {} bytes ({} bytes uncompressed)",
+        path.display(),
+        frames.len(),
+        data.len(),
+        frames.len() * width as usize * height as usize / 8
+    );
+    Ok(())
+}
+
+fn rust_module(outputs: &[Output]) -> Result<String, Box<dyn Error>> {
+    let (width, height) = (outputs[0].width, outputs[0].height);
+    if outputs
+        .iter()
+        .any(|o| (o.width, o.height) != (width, height))
+    {
+        return Err("all frames must have the same size for the Rust module".into());
+    }
+
+    let mut sources: Vec<&str> = outputs.iter().map(|o| o.source.as_str()).collect();
+    sources.dedup();
+
+    let mut s = String::new();
+    writeln!(s, "// Generated by `imgtool` from {}.", sources.join(", "))?;
+    writeln!(s, "// Do not edit by hand.")?;
+    writeln!(s)?;
+    writeln!(s, "pub const WIDTH: u32 = {};", width)?;
+    writeln!(s, "pub const HEIGHT: u32 = {};", height)?;
+    writeln!(s)?;
+    writeln!(s, "/// Raw 1-bit frames for `ImageRaw<BinaryColor>`.")?;
+    writeln!(s, "pub const FRAMES: [&[u8]; {}] = [", outputs.len())?;
+    for o in outputs {
+        writeln!(s, "    include_bytes!(\"{}\"),", o.file_name)?;
+    }
+    writeln!(s, "];")?;
+    writeln!(s)?;
+    writeln!(s, "/// How long each frame is shown, in milliseconds.")?;
+    writeln!(s, "pub const DELAYS_MS: [u16; {}] = [", outputs.len())?;
+    for o in outputs {
+        writeln!(s, "    {},", o.delay_ms)?;
+    }
+    writeln!(s, "];")?;
+    Ok(s)
+}
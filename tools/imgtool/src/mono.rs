@@ -0,0 +1,88 @@
+//! Conversion of RGBA frames into the 1-bit format of
+//! `embedded_graphics::image::ImageRaw<BinaryColor>`.
+//!
+//! That format is row-major with the most significant bit first, each row
+//! padded to a whole byte, and a set bit for `BinaryColor::On`.
+
+use crate::load::Frame;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Mode {
+    /// Pixels at least as bright as the threshold are lit.
+    Threshold(u8),
+    /// Floyd-Steinberg error diffusion.
+    Dither,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    pub mode: Mode,
+    /// Light up the dark pixels instead.
+    pub invert: bool,
+}
+
+/// Brightness of each pixel. Transparent pixels count as black, which is what
+/// an unlit OLED pixel looks like.
+fn luma(frame: &Frame) -> Vec<i16> {
+    frame
+        .rgba
+        .chunks_exact(4)
+        .map(|p| {
+            if p[3] < 0x80 {
+                return 0;
+            }
+            let (r, g, b) = (p[0] as u32, p[1] as u32, p[2] as u32);
+            ((r * 299 + g * 587 + b * 114) / 1000) as i16
+        })
+        .collect()
+}
+
+/// Returns whether each pixel is lit.
+pub fn to_mono(frame: &Frame, options: Options) -> Vec<bool> {
+    let mut luma = luma(frame);
+    if options.invert {
+        luma.iter_mut().for_each(|l| *l = 255 - *l);
+    }
+    match options.mode {
+        Mode::Threshold(threshold) => luma.iter().map(|&l| l >= threshold as i16).collect(),
+        Mode::Dither => dither(&mut luma, frame.width as usize, frame.height as usize),
+    }
+}
+
+fn dither(luma: &mut [i16], width: usize, height: usize) -> Vec<bool> {
+    let mut lit = vec![false; luma.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let on = luma[i] >= 128;
+            lit[i] = on;
+            let err = luma[i] - if on { 255 } else { 0 };
+
+            let mut spread = |dx: isize, dy: usize, weight: i16| {
+                let nx = x as isize + dx;
+                if nx >= 0 && (nx as usize) < width && y + dy < height {
+                    luma[(y + dy) * width + nx as usize] += err * weight / 16;
+                }
+            };
+            spread(1, 0, 7);
+            spread(-1, 1, 3);
+            spread(0, 1, 5);
+            spread(1, 1, 1);
+        }
+    }
+    lit
+}
+
+/// Packs the pixels for `ImageRaw<BinaryColor>`.
+pub fn pack(lit: &[bool], width: usize) -> Vec<u8> {
+    let stride = (width + 7) / 8;
+    let mut out = Vec::with_capacity(stride * lit.len() / width.max(1));
+    for row in lit.chunks(width) {
+        let mut bytes = vec![0; stride];
+        for (x, _) in row.iter().enumerate().filter(|(_, &on)| on) {
+            bytes[x / 8] |= 0x80 >> (x % 8);
+        }
+        out.extend_from_slice(&bytes);
+    }
+    out
+}
@@ -0,0 +1,186 @@
+//! Round-trip tests of the animation encoder against the firmware decoder.
+
+use imgtool::anim::{self, Frame};
+use pinecil_core::anim::{Animation, Error, FrameKind};
+
+const WIDTH: u16 = 96;
+const HEIGHT: u16 = 16;
+const FRAME_LEN: usize = WIDTH as usize * HEIGHT as usize / 8;
+
+/// Some deterministic noise, which compresses badly.
+fn noise(seed: u32) -> Vec<u8> {
+    let mut x = seed.wrapping_mul(2_654_435_761) | 1;
+    (0..FRAME_LEN)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        })
+        .collect()
+}
+
+fn frames() -> Vec<Frame> {
+    let mut moving = vec![0; FRAME_LEN];
+    moving[10..20].fill(0xff);
+    let mut moved = vec![0; FRAME_LEN];
+    moved[12..22].fill(0xff);
+    let mut small_change = noise(1);
+    small_change[100] ^= 0x55;
+    vec![
+        Frame {
+            pages: vec![0; FRAME_LEN],
+            delay_ms: 100,
+        },
+        Frame {
+            pages: moving,
+            delay_ms: 250,
+        },
+        Frame {
+            pages: moved,
+            delay_ms: 250,
+        },
+        Frame {
+            pages: noise(1),
+            delay_ms: 40,
+        },
+        Frame {
+            pages: small_change,
+            delay_ms: 40,
+        },
+        Frame {
+            pages: noise(2),
+            delay_ms: 40,
+        },
+        Frame {
+            pages: vec![0xff; FRAME_LEN],
+            delay_ms: 1000,
+        },
+    ]
+}
+
+fn decode_all(data: &[u8]) -> Vec<(FrameKind, u16, Vec<u8>)> {
+    let anim = Animation::parse(data).unwrap();
+    assert_eq!((anim.width(), anim.height()), (WIDTH, HEIGHT));
+    let mut buf = vec![0xaa; anim.frame_len()];
+    anim.frames()
+        .map(|frame| {
+            let frame = frame.unwrap();
+            frame.decode_into(&mut buf).unwrap();
+            (frame.kind, frame.delay_ms, buf.clone())
+        })
+        .collect()
+}
+
+#[test]
+fn round_trip() {
+    let frames = frames();
+    let data = anim::encode(WIDTH, HEIGHT, &frames).unwrap();
+    let decoded = decode_all(&data);
+    assert_eq!(decoded.len(), frames.len());
+    for (frame, (_, delay_ms, pages)) in frames.iter().zip(&decoded) {
+        assert_eq!(*delay_ms, frame.delay_ms);
+        assert_eq!(pages, &frame.pages);
+    }
+    // Looping back to the start works, since the first frame is a key frame.
+    assert_eq!(decode_all(&data)[0].2, frames[0].pages);
+}
+
+#[test]
+fn picks_smaller_encoding() {
+    let data = anim::encode(WIDTH, HEIGHT, &frames()).unwrap();
+    let kinds: Vec<FrameKind> = decode_all(&data).into_iter().map(|(k, _, _)| k).collect();
+    assert_eq!(kinds[0], FrameKind::Key);
+    // A small change on top of noise is much cheaper as a delta...
+    assert_eq!(kinds[4], FrameKind::Xor);
+    // ...but not the next, unrelated noise frame.
+    assert_eq!(kinds[5], FrameKind::Key);
+    assert!(data.len() < frames().len() * FRAME_LEN);
+}
+
+#[test]
+fn rle_round_trip() {
+    let mut data = vec![1, 2, 3];
+    data.extend(std::iter::repeat_n(7, 300));
+    data.extend_from_slice(&[7, 8, 8, 9]);
+    data.extend(noise(3).into_iter().take(150));
+    let encoded = anim::rle_encode(&data);
+    assert!(encoded.len() < data.len());
+
+    let frame = anim::encode(
+        data.len() as u16,
+        8,
+        &[Frame {
+            pages: data.clone(),
+            delay_ms: 0,
+        }],
+    )
+    .unwrap();
+    assert_eq!(decode_all_sized(&frame), data);
+}
+
+fn decode_all_sized(data: &[u8]) -> Vec<u8> {
+    let anim = Animation::parse(data).unwrap();
+    let mut buf = vec![0; anim.frame_len()];
+    anim.frames()
+        .next()
+        .unwrap()
+        .unwrap()
+        .decode_into(&mut buf)
+        .unwrap();
+    buf
+}
+
+#[test]
+fn oled_demo_frames() {
+    use imgtool::{load, mono};
+
+    let frames: Vec<Frame> = ["../../06-oled/frame0.png", "../../06-oled/frame1.png"]
+        .iter()
+        .map(|path| {
+            let frame = load::load(path.as_ref()).unwrap().remove(0);
+            let options = mono::Options {
+                mode: mono::Mode::Threshold(128),
+                invert: false,
+            };
+            let lit = mono::to_mono(&frame, options);
+            Frame {
+                pages: anim::to_pages(&lit, frame.width as usize, frame.height as usize).unwrap(),
+                delay_ms: 250,
+            }
+        })
+        .collect();
+    let data = anim::encode(WIDTH, HEIGHT, &frames).unwrap();
+    assert_eq!(data, include_bytes!("../../../06-oled/src/frames.anim"));
+    for (frame, (_, _, pages)) in frames.iter().zip(decode_all(&data)) {
+        assert_eq!(pages, frame.pages);
+    }
+}
+
+#[test]
+fn rejects_bad_data() {
+    let data = anim::encode(WIDTH, HEIGHT, &frames()).unwrap();
+    assert_eq!(Animation::parse(&data[1..]).err(), Some(Error::BadMagic));
+    assert_eq!(Animation::parse(&data[..12]).err(), Some(Error::Truncated));
+
+    // Frame data which decodes to too few bytes.
+    let anim = Animation::parse(&data).unwrap();
+    let mut buf = vec![0; anim.frame_len() + 1];
+    let frame = anim.frames().next().unwrap().unwrap();
+    assert_eq!(frame.decode_into(&mut buf), Err(Error::BadData));
+    let mut buf = vec![0; anim.frame_len() - 1];
+    assert_eq!(frame.decode_into(&mut buf), Err(Error::BadData));
+
+    assert!(anim::encode(WIDTH, 12, &[]).is_err());
+}
+
+#[test]
+fn rejects_partial_pages() {
+    let lit = vec![true; WIDTH as usize * 12];
+    assert!(anim::to_pages(&lit, WIDTH as usize, 12).is_err());
+    let lit = vec![true; FRAME_LEN * 8];
+    assert_eq!(
+        anim::to_pages(&lit, WIDTH as usize, HEIGHT as usize),
+        Ok(vec![0xff; FRAME_LEN])
+    );
+}
@@ -0,0 +1,85 @@
+//! Tests of the conversion into 1-bit pixels.
+
+use imgtool::{
+    load::Frame,
+    mono::{self, Mode, Options},
+};
+
+const THRESHOLD: Options = Options {
+    mode: Mode::Threshold(128),
+    invert: false,
+};
+
+/// A frame of grey pixels, fully opaque unless `alpha` says otherwise.
+fn grey(width: u32, height: u32, luma: &[u8], alpha: u8) -> Frame {
+    assert_eq!(luma.len(), (width * height) as usize);
+    Frame {
+        width,
+        height,
+        rgba: luma.iter().flat_map(|&l| vec![l, l, l, alpha]).collect(),
+        delay_ms: None,
+    }
+}
+
+#[test]
+fn threshold() {
+    let frame = grey(4, 1, &[0, 127, 128, 255], 0xff);
+    assert_eq!(mono::to_mono(&frame, THRESHOLD), [false, false, true, true]);
+    let inverted = Options {
+        invert: true,
+        ..THRESHOLD
+    };
+    assert_eq!(mono::to_mono(&frame, inverted), [true, true, false, false]);
+}
+
+#[test]
+fn colours_and_transparency() {
+    // Pure green is bright, pure blue is dark.
+    let frame = Frame {
+        width: 3,
+        height: 1,
+        rgba: vec![0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 0],
+        delay_ms: None,
+    };
+    // The transparent white pixel counts as black.
+    assert_eq!(mono::to_mono(&frame, THRESHOLD), [true, false, false]);
+}
+
+#[test]
+fn dither() {
+    let dither = Options {
+        mode: Mode::Dither,
+        invert: false,
+    };
+    let (width, height) = (16, 16);
+    let lit = |l: u8| {
+        let frame = grey(width, height, &vec![l; (width * height) as usize], 0xff);
+        let lit = mono::to_mono(&frame, dither);
+        lit.iter().filter(|&&on| on).count()
+    };
+    assert_eq!(lit(0), 0);
+    assert_eq!(lit(255), 256);
+    // Mid grey lights about half of the pixels, where a threshold would
+    // light all or none.
+    let half = lit(128);
+    assert!((120..=136).contains(&half), "{}", half);
+    let quarter = lit(64);
+    assert!((56..=72).contains(&quarter), "{}", quarter);
+}
+
+#[test]
+fn pack() {
+    // Most significant bit first.
+    let lit = [true, false, false, false, false, false, true, true];
+    assert_eq!(mono::pack(&lit, 8), [0b1000_0011]);
+
+    // Each row is padded to a whole byte.
+    let mut lit = vec![false; 10 * 2];
+    lit[0] = true;
+    lit[9] = true;
+    lit[10 + 8] = true;
+    assert_eq!(
+        mono::pack(&lit, 10),
+        [0b1000_0000, 0b0100_0000, 0b0000_0000, 0b1000_0000]
+    );
+}
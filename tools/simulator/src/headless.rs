@@ -0,0 +1,178 @@
+//! Scripted runs without a terminal, for golden-image tests.
+//!
+//! A script has one command per line, and `#` starts a comment:
+//!
+//! ```text
+//! wait <ms>           let time pass, rounded up to whole ticks
+//! press <+|->         hold a button down
+//! release <+|->       let go of a button
+//! tap <+|->           press a button for one tick, then release it
+//! accel <x> <y> <z>   set the accelerometer reading, 64 counts per g
+//! accel none          disconnect the accelerometer
+//! vin <mV>            set the supply voltage, in millivolts
+//! vin none            make the supply voltage unknown
+//! tip <none|fitted>   remove or fit the tip (demo 08 only)
+//! dump <name>         save the screen as `<name>.png`
+//! ```
+
+use std::{error::Error, fmt};
+
+use pinecil_core::ui::{Accel, TICK_MS};
+
+use crate::{Sim, Ui};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Plus,
+    Minus,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Wait(u32),
+    Press(Button),
+    Release(Button),
+    Tap(Button),
+    Accel(Option<Accel>),
+    Vin(Option<u32>),
+    Tip(bool),
+    Dump(String),
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+fn parse_button(arg: Option<&str>) -> Result<Button, String> {
+    match arg {
+        Some("+") => Ok(Button::Plus),
+        Some("-") => Ok(Button::Minus),
+        _ => Err("expected '+' or '-'".to_owned()),
+    }
+}
+
+fn parse_line(line: &str) -> Result<Option<Command>, String> {
+    let line = line.split('#').next().unwrap();
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Ok(None),
+    };
+    let args: Vec<&str> = words.collect();
+    let arg = args.first().copied();
+    let command = match command {
+        "wait" => Command::Wait(
+            arg.and_then(|ms| ms.parse().ok())
+                .ok_or("expected a time in milliseconds")?,
+        ),
+        "press" => Command::Press(parse_button(arg)?),
+        "release" => Command::Release(parse_button(arg)?),
+        "tap" => Command::Tap(parse_button(arg)?),
+        "accel" if args == ["none"] => Command::Accel(None),
+        "accel" => {
+            let axes: Vec<i8> = args.iter().filter_map(|a| a.parse().ok()).collect();
+            match axes[..] {
+                [x, y, z] if args.len() == 3 => Command::Accel(Some(Accel { x, y, z })),
+                _ => return Err("expected 'none' or three values from -128 to 127".to_owned()),
+            }
+        }
+        "vin" if arg == Some("none") => Command::Vin(None),
+        "vin" => Command::Vin(Some(
+            arg.and_then(|mv| mv.parse().ok())
+                .ok_or("expected 'none' or a voltage in millivolts")?,
+        )),
+        "tip" => match arg {
+            Some("none") => Command::Tip(false),
+            Some("fitted") => Command::Tip(true),
+            _ => return Err("expected 'none' or 'fitted'".to_owned()),
+        },
+        "dump" => Command::Dump(arg.ok_or("expected a name")?.to_owned()),
+        _ => return Err(format!("unknown command '{}'", command)),
+    };
+    if args.len() > 1 && !matches!(command, Command::Accel(_)) {
+        return Err("too many arguments".to_owned());
+    }
+    Ok(Some(command))
+}
+
+pub fn parse(script: &str) -> Result<Vec<Command>, ParseError> {
+    let mut commands = Vec::new();
+    for (i, line) in script.lines().enumerate() {
+        match parse_line(line) {
+            Ok(Some(command)) => commands.push(command),
+            Ok(None) => {}
+            Err(message) => {
+                return Err(ParseError {
+                    line: i + 1,
+                    message,
+                })
+            }
+        }
+    }
+    Ok(commands)
+}
+
+fn set_button<U: Ui>(sim: &mut Sim<U>, button: Button, down: bool) {
+    match button {
+        Button::Plus => sim.inputs.buttons.plus = down,
+        Button::Minus => sim.inputs.buttons.minus = down,
+    }
+}
+
+/// Runs the commands, calling `dump` with the name of each `dump` command.
+/// The screen is whatever was last flushed to it, just like on the OLED.
+pub fn run<U: Ui, E>(
+    sim: &mut Sim<U>,
+    commands: &[Command],
+    mut dump: impl FnMut(&str, &Sim<U>) -> Result<(), E>,
+) -> Result<(), E> {
+    for command in commands {
+        match *command {
+            Command::Wait(ms) => {
+                // Rounded up, without overflowing for waits close to u32::MAX.
+                let tick = TICK_MS as u32;
+                for _ in 0..ms / tick + (ms % tick != 0) as u32 {
+                    sim.tick();
+                }
+            }
+            Command::Press(button) => {
+                set_button(sim, button, true);
+                sim.tick();
+            }
+            Command::Release(button) => {
+                set_button(sim, button, false);
+                sim.tick();
+            }
+            Command::Tap(button) => {
+                set_button(sim, button, true);
+                sim.tick();
+                set_button(sim, button, false);
+                sim.tick();
+            }
+            Command::Accel(accel) => {
+                sim.inputs.accel = accel;
+                sim.tick();
+            }
+            Command::Vin(mv) => {
+                sim.inputs.supply_mv = mv;
+                sim.tick();
+            }
+            Command::Tip(fitted) => {
+                sim.ui.set_tip(fitted);
+                sim.tick();
+            }
+            Command::Dump(ref name) => dump(name, sim)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! The soldering UI of demo 08, with the heater, the tip and the idle sleep
+//! simulated around it.
+//!
+//! The control loop is the one of the firmware: once per 100 ms heater
+//! period the tip is measured, the PID controller updates the duty, and the
+//! tip model is heated with it. The tip starts cold, at 25 °C.
+
+use pinecil_core::{
+    iron::{IronUi, Status},
+    pid::{Pid, PINECIL_CONFIG},
+    power::Supply,
+    settings::Settings,
+    sleep::{self, Sleep},
+    tip_model::{TipModel, PINECIL_TIP},
+    ui::{Buttons, Inputs},
+};
+
+use crate::{Screen, Ui};
+
+/// The heater period of demo 08.
+const PERIOD_MS: u32 = 100;
+/// Room temperature, in tenths of a degree.
+const AMBIENT: i32 = 250;
+
+pub struct Iron {
+    pub ui: IronUi,
+    sleep: Sleep,
+    pid: Pid,
+    tip: TipModel,
+    tip_fitted: bool,
+    supply: Supply,
+    supply_mv: Option<u32>,
+    now_ms: u32,
+    /// Time into the current heater period.
+    period_ms: u32,
+    previous: Buttons,
+    /// Set while the buttons which woke the iron up are still held.
+    waking: bool,
+    status: Status,
+}
+
+impl Iron {
+    pub fn new(settings: Settings) -> Self {
+        Iron {
+            ui: IronUi::new(settings),
+            sleep: Sleep::new(sleep::DEFAULT_CONFIG, 0),
+            pid: Pid::new(PINECIL_CONFIG),
+            tip: TipModel::new(PINECIL_TIP, AMBIENT),
+            tip_fitted: true,
+            supply: Supply::new(),
+            supply_mv: None,
+            now_ms: 0,
+            period_ms: 0,
+            previous: Buttons::default(),
+            waking: false,
+            status: Status {
+                tip: None,
+                duty: 0,
+                supply_mv: None,
+                sleep: sleep::State::Active,
+            },
+        }
+    }
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// Runs one heater period.
+    fn heat(&mut self) {
+        let tip = Some(self.tip.temperature()).filter(|_| self.tip_fitted);
+        let target = self.sleep.target(self.ui.target());
+        match tip {
+            Some(tip) if target > 0 && self.supply.can_heat() => {
+                self.pid.update(target, tip);
+            }
+            _ => self.pid.reset(),
+        }
+        let mv = self.supply_mv.unwrap_or(0);
+        self.tip.step(self.pid.output(), mv, PERIOD_MS);
+    }
+}
+
+impl Ui for Iron {
+    fn update(&mut self, inputs: Inputs, elapsed_ms: u16) -> bool {
+        self.now_ms += elapsed_ms as u32;
+        let buttons = inputs.buttons;
+        let pressed =
+            (buttons.plus && !self.previous.plus) || (buttons.minus && !self.previous.minus);
+        self.previous = buttons;
+
+        // Like on the iron, a press which wakes it up does not reach the UI,
+        // and neither does holding both buttons.
+        if self.sleep.state() != sleep::State::Active && pressed {
+            self.waking = true;
+        }
+        self.sleep.update(inputs.accel, pressed, self.now_ms);
+        if !buttons.plus && !buttons.minus {
+            self.waking = false;
+        }
+        let ui_buttons = if self.waking || (buttons.plus && buttons.minus) {
+            Buttons::default()
+        } else {
+            buttons
+        };
+        let mut redraw = self.ui.update(ui_buttons, elapsed_ms);
+
+        self.supply_mv = inputs.supply_mv;
+        if let Some(mv) = inputs.supply_mv {
+            self.supply.update(mv);
+        }
+        self.period_ms += elapsed_ms as u32;
+        while self.period_ms >= PERIOD_MS {
+            self.period_ms -= PERIOD_MS;
+            self.heat();
+        }
+
+        let status = Status {
+            // The screen shows whole degrees.
+            tip: Some(self.tip.temperature() / 10 * 10).filter(|_| self.tip_fitted),
+            duty: self.pid.output(),
+            supply_mv: inputs.supply_mv,
+            sleep: self.sleep.state(),
+        };
+        if status != self.status {
+            self.status = status;
+            redraw = true;
+        }
+        redraw
+    }
+
+    fn draw(&self, screen: &mut Screen) {
+        let _ = self.ui.draw(screen, &self.status);
+    }
+
+    /// Demo 08 does not change the contrast, so this only matters for the
+    /// PNG files.
+    fn brightness(&self) -> u8 {
+        0xff
+    }
+
+    fn set_tip(&mut self, fitted: bool) {
+        self.tip_fitted = fitted;
+    }
+}
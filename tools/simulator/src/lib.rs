@@ -0,0 +1,134 @@
+//! Host-side stand-in for the Pinecil's OLED, buttons, accelerometer and
+//! supply voltage, so that the UIs in `pinecil_core` can be run and tested
+//! without flashing: the screens of demo 06 in `pinecil_core::ui`, and the
+//! soldering UI of demo 08 in `pinecil_core::iron`, see [`iron`].
+
+use std::convert::Infallible;
+
+use embedded_graphics::{drawable::Pixel, geometry::Size, pixelcolor::BinaryColor, DrawTarget};
+use pinecil_core::{
+    anim::Animation,
+    display::FrameBuffer,
+    lang::Language,
+    ui::{Accel, Inputs, OledDemo, TICK_MS},
+};
+
+pub mod headless;
+pub mod iron;
+pub mod render;
+
+/// A UI which the simulator can run.
+pub trait Ui {
+    /// Advances the UI by `elapsed_ms`. Returns whether the display needs to
+    /// be redrawn.
+    fn update(&mut self, inputs: Inputs, elapsed_ms: u16) -> bool;
+
+    fn draw(&self, screen: &mut Screen);
+
+    fn brightness(&self) -> u8;
+
+    /// Fits or removes the tip. Only the iron has one.
+    fn set_tip(&mut self, _fitted: bool) {}
+}
+
+impl Ui for OledDemo<'_> {
+    fn update(&mut self, inputs: Inputs, elapsed_ms: u16) -> bool {
+        OledDemo::update(self, inputs, elapsed_ms)
+    }
+
+    fn draw(&self, screen: &mut Screen) {
+        let _ = OledDemo::draw(self, screen);
+    }
+
+    fn brightness(&self) -> u8 {
+        OledDemo::brightness(self)
+    }
+}
+
+/// Stands in for the `GraphicsMode` of the `ssd1306` driver: the UI draws
+/// on a buffer, and `flush` shows it, like sending it to the display RAM.
+#[derive(Default)]
+pub struct Screen {
+    /// What was last flushed.
+    pub fb: FrameBuffer,
+    pub brightness: u8,
+    buffer: FrameBuffer,
+}
+
+impl Screen {
+    pub fn flush(&mut self) {
+        self.fb = self.buffer.clone();
+    }
+
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+}
+
+impl DrawTarget<BinaryColor> for Screen {
+    type Error = Infallible;
+
+    fn draw_pixel(&mut self, pixel: Pixel<BinaryColor>) -> Result<(), Self::Error> {
+        self.buffer.draw_pixel(pixel)
+    }
+
+    fn size(&self) -> Size {
+        self.buffer.size()
+    }
+}
+
+/// 1 g in BMA223 counts.
+pub const ONE_G: i8 = 64;
+
+/// An iron lying flat on the desk.
+pub const FLAT: Accel = Accel {
+    x: 0,
+    y: 0,
+    z: ONE_G,
+};
+
+/// A 20 V USB PD charger.
+pub const PD_20V: u32 = 20_000;
+
+/// The animation played by demo 06.
+pub const OLED_DEMO_ANIM: &[u8] = include_bytes!("../../../06-oled/src/frames.anim");
+
+pub fn oled_demo(language: Language) -> OledDemo<'static> {
+    OledDemo::new(Animation::parse(OLED_DEMO_ANIM).unwrap(), language)
+}
+
+/// Runs the UI the same way the firmware does, one tick at a time.
+pub struct Sim<U> {
+    pub ui: U,
+    pub inputs: Inputs,
+    pub screen: Screen,
+    /// Time since the start, in milliseconds.
+    pub now_ms: u64,
+}
+
+impl<U: Ui> Sim<U> {
+    pub fn new(ui: U) -> Self {
+        Sim {
+            ui,
+            inputs: Inputs {
+                accel: Some(FLAT),
+                supply_mv: Some(PD_20V),
+                ..Inputs::default()
+            },
+            screen: Screen::default(),
+            now_ms: 0,
+        }
+    }
+
+    /// Advances by one tick. Returns whether the screen was redrawn.
+    pub fn tick(&mut self) -> bool {
+        self.now_ms += TICK_MS as u64;
+        if !self.ui.update(self.inputs, TICK_MS) {
+            return false;
+        }
+        self.ui.draw(&mut self.screen);
+        self.screen.set_brightness(self.ui.brightness());
+        self.screen.flush();
+        true
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute, queue, terminal,
+};
+use pinecil_core::{lang::Language, settings::Settings, ui::TICK_MS};
+use simulator::{headless, iron::Iron, render, Sim, Ui, FLAT};
+
+const USAGE: &str = "\
+Usage: simulator [options]
+       simulator --headless <script> [-o <dir>] [--iron]
+
+Runs the UI of demo 06 with the OLED drawn in the terminal, or with --iron
+the soldering UI of demo 08 with a simulated tip.
+
+Keys:
+    + or =          '+' button
+    -               '-' button
+    arrow keys      tilt the fake accelerometer
+    space           lay the fake accelerometer flat again
+    v               switch to the next supply voltage
+    t               remove or fit the tip (--iron only)
+    q or Esc        quit
+
+Options:
+    --iron                  Run the soldering UI of demo 08
+    --lang <code>           Language of the UI, e.g. de for German
+                            (default: en)
+    --png <file>            Also write the screen to this PNG file whenever it
+                            changes, e.g. to watch it in an image viewer
+    --scale <n>             Pixel size in the PNG file (default: 4)
+    --headless <script>     Run the commands in the script instead of reading
+                            the keyboard, see `src/headless.rs`
+    -o, --out-dir <dir>     Where the headless mode dumps the frames
+                            (default: the current directory)
+";
+
+/// Terminals only report key presses (and their auto-repeat), so a button is
+/// held down for this long after each press.
+const HOLD_MS: u64 = 100;
+
+/// How far the arrow keys tilt the fake accelerometer.
+const TILT_STEP: i8 = 8;
+
+/// Supply voltages to cycle through, in millivolts: plain USB, the USB PD
+/// fixed voltages and a DC supply.
+const SUPPLIES_MV: [u32; 6] = [5_000, 9_000, 12_000, 15_000, 20_000, 24_000];
+
+struct Args {
+    iron: bool,
+    language: Language,
+    png: Option<PathBuf>,
+    scale: usize,
+    headless: Option<PathBuf>,
+    out_dir: PathBuf,
+}
+
+fn parse_args() -> Result<Args, Box<dyn Error>> {
+    let mut args = Args {
+        iron: false,
+        language: Language::English,
+        png: None,
+        scale: 4,
+        headless: None,
+        out_dir: PathBuf::new(),
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--iron" => args.iron = true,
+            "--lang" => {
+                let code = value()?;
+                args.language =
+                    Language::from_code(&code).ok_or(format!("unknown language {}", code))?;
+            }
+            "--png" => args.png = Some(value()?.into()),
+            "--scale" => args.scale = value()?.parse()?,
+            "--headless" => args.headless = Some(value()?.into()),
+            "-o" | "--out-dir" => args.out_dir = value()?.into(),
+            "-h" | "--help" => return Err("".into()),
+            a => return Err(format!("unexpected argument {}", a).into()),
+        }
+    }
+    if args.scale == 0 {
+        return Err("scale must be at least 1".into());
+    }
+    Ok(args)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.to_string().is_empty() {
+                eprintln!("error: {}\n", e);
+            }
+            eprint!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    let demo = || Sim::new(simulator::oled_demo(args.language));
+    let iron = || {
+        Sim::new(Iron::new(Settings {
+            language: args.language,
+            ..Settings::DEFAULT
+        }))
+    };
+    let result = match (&args.headless, args.iron) {
+        (Some(script), false) => run_headless(&args, script, demo()),
+        (Some(script), true) => run_headless(&args, script, iron()),
+        (None, false) => run_interactive(&args, demo()),
+        (None, true) => run_interactive(&args, iron()),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run_headless<U: Ui>(
+    args: &Args,
+    script: &PathBuf,
+    mut sim: Sim<U>,
+) -> Result<(), Box<dyn Error>> {
+    let commands = headless::parse(&fs::read_to_string(script)?)
+        .map_err(|e| format!("{}: {}", script.display(), e))?;
+    fs::create_dir_all(&args.out_dir)?;
+    headless::run(&mut sim, &commands, |name, sim| {
+        let path = args.out_dir.join(format!("{}.png", name));
+        fs::write(
+            &path,
+            render::to_png(&sim.screen.fb, sim.screen.brightness, 1)?,
+        )?;
+        println!("{} ms: {}", sim.now_ms, path.display());
+        Ok::<_, Box<dyn Error>>(())
+    })
+}
+
+fn run_interactive<U: Ui>(args: &Args, sim: Sim<U>) -> Result<(), Box<dyn Error>> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = interactive_loop(args, sim, &mut stdout);
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn interactive_loop<U: Ui>(
+    args: &Args,
+    mut sim: Sim<U>,
+    stdout: &mut io::Stdout,
+) -> Result<(), Box<dyn Error>> {
+    let mut tip_fitted = true;
+    let mut plus_until = Instant::now();
+    let mut minus_until = Instant::now();
+    let mut next_tick = Instant::now();
+    loop {
+        while event::poll(next_tick.saturating_duration_since(Instant::now()))? {
+            let key = match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+            let hold = Instant::now() + Duration::from_millis(HOLD_MS);
+            let accel = sim.inputs.accel.get_or_insert(FLAT);
+            match key.code {
+                KeyCode::Char('+') | KeyCode::Char('=') => plus_until = hold,
+                KeyCode::Char('-') => minus_until = hold,
+                KeyCode::Left => accel.x = accel.x.saturating_sub(TILT_STEP),
+                KeyCode::Right => accel.x = accel.x.saturating_add(TILT_STEP),
+                KeyCode::Up => accel.y = accel.y.saturating_add(TILT_STEP),
+                KeyCode::Down => accel.y = accel.y.saturating_sub(TILT_STEP),
+                KeyCode::Char(' ') => *accel = FLAT,
+                KeyCode::Char('v') => {
+                    let mv = sim.inputs.supply_mv.unwrap_or(0);
+                    let next = SUPPLIES_MV.iter().find(|&&s| s > mv);
+                    sim.inputs.supply_mv = Some(*next.unwrap_or(&SUPPLIES_MV[0]));
+                }
+                KeyCode::Char('t') => {
+                    tip_fitted = !tip_fitted;
+                    sim.ui.set_tip(tip_fitted);
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => {}
+            }
+        }
+
+        let now = Instant::now();
+        sim.inputs.buttons.plus = now < plus_until;
+        sim.inputs.buttons.minus = now < minus_until;
+        next_tick += Duration::from_millis(TICK_MS as u64);
+        if !sim.tick() {
+            continue;
+        }
+
+        let screen = &sim.screen;
+        queue!(stdout, cursor::MoveTo(0, 0))?;
+        write!(stdout, "{}", render::to_terminal(&screen.fb))?;
+        let accel = sim.inputs.accel.unwrap_or_default();
+        write!(
+            stdout,
+            "\r\nbrightness {:3}  accel x {:+4} y {:+4} z {:+4}  vin {:5} mV  (q to quit)",
+            screen.brightness,
+            accel.x,
+            accel.y,
+            accel.z,
+            sim.inputs.supply_mv.unwrap_or(0)
+        )?;
+        stdout.flush()?;
+        if let Some(png) = &args.png {
+            fs::write(
+                png,
+                render::to_png(&screen.fb, screen.brightness, args.scale)?,
+            )?;
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Rendering of the frame buffer to PNG images and the terminal.
+
+use std::error::Error;
+
+use pinecil_core::display::{FrameBuffer, HEIGHT, WIDTH};
+
+/// Renders the frame buffer as a grayscale PNG, with each pixel blown up to
+/// `scale` x `scale`. Lit pixels are drawn dimmer at low brightness, roughly
+/// like the OLED, but never so dim that they can't be seen.
+pub fn to_png(fb: &FrameBuffer, brightness: u8, scale: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    let on = 0x60 + (brightness as u16 * 0x9f / 0xff) as u8;
+    let (width, height) = (WIDTH * scale, HEIGHT * scale);
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            pixels.push(if fb.pixel(x / scale, y / scale) {
+                on
+            } else {
+                0
+            });
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(out)
+}
+
+/// Reads back an image written by `to_png` with a scale of 1. Any pixel
+/// which is not black counts as lit.
+pub fn from_png(data: &[u8]) -> Result<FrameBuffer, Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    if (info.width as usize, info.height as usize) != (WIDTH, HEIGHT) {
+        return Err(format!("expected a {}x{} image", WIDTH, HEIGHT).into());
+    }
+    let channels = info.color_type.samples();
+
+    let mut fb = FrameBuffer::new();
+    for y in 0..HEIGHT {
+        let row = &buf[y * info.line_size..][..info.line_size];
+        for x in 0..WIDTH {
+            let pixel = &row[x * channels..][..channels];
+            // Only look at the color, not at the alpha channel.
+            let lit = pixel.iter().take(channels.min(3)).any(|&c| c != 0);
+            fb.set_pixel(x, y, lit);
+        }
+    }
+    Ok(fb)
+}
+
+/// Renders the frame buffer with block characters, two pixel rows per line
+/// of text, each line ending in `\r\n` so that it also works in raw mode.
+pub fn to_terminal(fb: &FrameBuffer) -> String {
+    let mut out = String::new();
+    for y in (0..HEIGHT).step_by(2) {
+        for x in 0..WIDTH {
+            out.push(match (fb.pixel(x, y), fb.pixel(x, y + 1)) {
+                (false, false) => ' ',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (true, true) => '█',
+            });
+        }
+        out.push_str("\r\n");
+    }
+    out
+}
@@ -0,0 +1,93 @@
+//! Golden-image tests of the demo 06 UI and of the soldering UI of demo 08.
+//!
+//! Each script in `tests/golden` dumps frames, which are compared against the
+//! PNG files of the same name next to it. After an intended change to the UI,
+//! regenerate them with
+//!
+//! ```text
+//! cargo run -p simulator -- --headless simulator/tests/golden/oled-demo.txt -o simulator/tests/golden
+//! cargo run -p simulator -- --iron --headless simulator/tests/golden/iron.txt -o simulator/tests/golden
+//! cargo run -p simulator -- --iron --lang de --headless simulator/tests/golden/iron-de.txt -o simulator/tests/golden
+//! ```
+//!
+//! and check the new images before committing them.
+
+use std::{fs, path::Path};
+
+use pinecil_core::{lang::Language, settings::Settings};
+use simulator::{headless, iron::Iron, render, Sim, Ui};
+
+fn check_script<U: Ui>(name: &str, mut sim: Sim<U>) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let script = fs::read_to_string(dir.join(name)).unwrap();
+    let commands = headless::parse(&script).unwrap();
+
+    let mut dumps = 0;
+    headless::run(&mut sim, &commands, |dump, sim| {
+        let golden = fs::read(dir.join(format!("{}.png", dump))).unwrap();
+        let golden = render::from_png(&golden).unwrap();
+        assert!(
+            sim.screen.fb == golden,
+            "{} differs from the golden image at {} ms:\n{}",
+            dump,
+            sim.now_ms,
+            render::to_terminal(&sim.screen.fb)
+        );
+        dumps += 1;
+        Ok::<_, ()>(())
+    })
+    .unwrap();
+    assert!(dumps > 0);
+}
+
+#[test]
+fn oled_demo() {
+    check_script(
+        "oled-demo.txt",
+        Sim::new(simulator::oled_demo(Language::English)),
+    );
+}
+
+#[test]
+fn iron() {
+    check_script("iron.txt", Sim::new(Iron::new(Settings::DEFAULT)));
+}
+
+#[test]
+fn iron_german() {
+    let settings = Settings {
+        language: Language::German,
+        ..Settings::DEFAULT
+    };
+    check_script("iron-de.txt", Sim::new(Iron::new(settings)));
+}
+
+#[test]
+fn png_round_trip() {
+    let mut sim = Sim::new(simulator::oled_demo(Language::English));
+    sim.tick();
+    for brightness in [0, 0xff] {
+        let png = render::to_png(&sim.screen.fb, brightness, 1).unwrap();
+        assert!(render::from_png(&png).unwrap() == sim.screen.fb);
+    }
+}
+
+#[test]
+fn script_errors() {
+    let e = headless::parse("wait 10\n\npress x\n").unwrap_err();
+    assert_eq!(e.line, 3);
+    assert!(headless::parse("accel 1 2").is_err());
+    assert!(headless::parse("accel 1 2 300").is_err());
+    assert!(headless::parse("tap + -").is_err());
+    assert!(headless::parse("jump").is_err());
+    assert!(headless::parse("vin 5V").is_err());
+    assert!(headless::parse("tip out").is_err());
+    assert_eq!(
+        headless::parse("vin 9000").unwrap(),
+        [headless::Command::Vin(Some(9000))]
+    );
+    assert_eq!(
+        headless::parse("accel none # unplugged").unwrap(),
+        [headless::Command::Accel(None)]
+    );
+}
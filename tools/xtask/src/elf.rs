@@ -0,0 +1,161 @@
+//! Just enough of an ELF32 little-endian parser to pull out the loadable
+//! sections of a firmware image.
+
+use std::fmt;
+
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u32 = 0x2;
+const PT_LOAD: u32 = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    NotElf,
+    Unsupported(&'static str),
+    Truncated,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NotElf => write!(f, "not an ELF file"),
+            ParseError::Unsupported(what) => write!(f, "unsupported ELF file: {}", what),
+            ParseError::Truncated => write!(f, "truncated ELF file"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug)]
+pub struct Section<'a> {
+    pub name: &'a str,
+    /// Run-time address.
+    pub addr: u32,
+    /// Load address in flash, which differs from `addr` for `.data`.
+    pub load_addr: u32,
+    pub size: u32,
+    /// `None` for sections without contents in the file (`.bss` and `NOLOAD`).
+    pub data: Option<&'a [u8]>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    offset: u32,
+    vaddr: u32,
+    paddr: u32,
+    filesz: u32,
+}
+
+pub struct Elf<'a> {
+    pub entry: u32,
+    pub sections: Vec<Section<'a>>,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, ParseError> {
+    let b = data.get(offset..offset + 2).ok_or(ParseError::Truncated)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, ParseError> {
+    let b = data.get(offset..offset + 4).ok_or(ParseError::Truncated)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn str_at(data: &[u8], offset: usize) -> Result<&str, ParseError> {
+    let s = data.get(offset..).ok_or(ParseError::Truncated)?;
+    let len = s
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(ParseError::Truncated)?;
+    std::str::from_utf8(&s[..len]).map_err(|_| ParseError::Unsupported("section name"))
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ParseError> {
+        if data.get(0..4) != Some(b"\x7fELF") {
+            return Err(ParseError::NotElf);
+        }
+        let ident = data.get(4..6).ok_or(ParseError::Truncated)?;
+        if ident[0] != 1 {
+            return Err(ParseError::Unsupported("not 32-bit"));
+        }
+        if ident[1] != 1 {
+            return Err(ParseError::Unsupported("not little-endian"));
+        }
+
+        let entry = u32_at(data, 0x18)?;
+        let phoff = u32_at(data, 0x1c)? as usize;
+        let shoff = u32_at(data, 0x20)? as usize;
+        let phentsize = u16_at(data, 0x2a)? as usize;
+        let phnum = u16_at(data, 0x2c)? as usize;
+        let shentsize = u16_at(data, 0x2e)? as usize;
+        let shnum = u16_at(data, 0x30)? as usize;
+        let shstrndx = u16_at(data, 0x32)? as usize;
+
+        let mut segments = Vec::new();
+        for i in 0..phnum {
+            let ph = phoff + i * phentsize;
+            if u32_at(data, ph)? != PT_LOAD {
+                continue;
+            }
+            segments.push(Segment {
+                offset: u32_at(data, ph + 4)?,
+                vaddr: u32_at(data, ph + 8)?,
+                paddr: u32_at(data, ph + 12)?,
+                filesz: u32_at(data, ph + 16)?,
+            });
+        }
+
+        let strtab_offset = u32_at(data, shoff + shstrndx * shentsize + 16)? as usize;
+
+        let mut sections = Vec::new();
+        for i in 0..shnum {
+            let sh = shoff + i * shentsize;
+            let sh_type = u32_at(data, sh + 4)?;
+            let flags = u32_at(data, sh + 8)?;
+            if flags & SHF_ALLOC == 0 {
+                continue;
+            }
+            let name = str_at(data, strtab_offset + u32_at(data, sh)? as usize)?;
+            let addr = u32_at(data, sh + 12)?;
+            let offset = u32_at(data, sh + 16)?;
+            let size = u32_at(data, sh + 20)?;
+
+            let contents = if sh_type == SHT_NOBITS {
+                None
+            } else {
+                let start = offset as usize;
+                let end = start
+                    .checked_add(size as usize)
+                    .ok_or(ParseError::Truncated)?;
+                Some(data.get(start..end).ok_or(ParseError::Truncated)?)
+            };
+
+            // Same as `objcopy`: the load address comes from the segment which
+            // contains the section in the file.
+            let load_addr = match contents {
+                Some(_) => {
+                    let end = offset.checked_add(size).ok_or(ParseError::Truncated)?;
+                    segments
+                        .iter()
+                        .find(|s| {
+                            offset >= s.offset
+                                && matches!(s.offset.checked_add(s.filesz), Some(e) if end <= e)
+                        })
+                        .map_or(addr, |s| addr.wrapping_sub(s.vaddr).wrapping_add(s.paddr))
+                }
+                None => addr,
+            };
+
+            sections.push(Section {
+                name,
+                addr,
+                load_addr,
+                size,
+                data: contents,
+            });
+        }
+
+        Ok(Elf { entry, sections })
+    }
+}
@@ -0,0 +1,5 @@
+//! Build helpers for the Pinecil demos, run with `cargo xtask`.
+
+pub mod elf;
+pub mod package;
+pub mod size;
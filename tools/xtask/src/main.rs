@@ -0,0 +1,136 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use pinecil_core::image::{self, DFU_PID, DFU_VID};
+use xtask::{elf::Elf, package, size};
+
+const TARGET: &str = "riscv32imac-unknown-none-elf";
+
+const USAGE: &str = "\
+Usage: cargo xtask <command>
+
+Commands:
+    build <demo> [--debug] [--for-bootloader]
+        Build a demo (e.g. `06-oled`, `demo-06-oled` or `bootloader`) and
+        write `<name>.bin` and `<name>.dfu` to `target/firmware`.
+    package <elf> <out.bin> [--for-bootloader]
+        Write the raw binary and a `.dfu` file next to it.
+    size <elf>
+        Print the section sizes.
+
+With `--for-bootloader`, the image is linked behind the resident bootloader
+and gets the image header it expects.
+";
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let flag = |name: &str| args.iter().any(|a| a == name);
+    let positional: Vec<&str> = args
+        .iter()
+        .filter(|a| !a.starts_with("--"))
+        .map(|a| a.as_str())
+        .collect();
+    let for_bootloader = flag("--for-bootloader");
+
+    let result = match positional.as_slice() {
+        ["build", demo] => build(demo, !flag("--debug"), for_bootloader),
+        ["package", elf, out] => package(Path::new(elf), Path::new(out), for_bootloader),
+        ["size", elf] => print_size(Path::new(elf)),
+        _ => {
+            eprint!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .ancestors()
+        .nth(2)
+        .unwrap()
+        .to_owned()
+}
+
+fn target_dir() -> PathBuf {
+    env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| workspace_root().join("target"))
+}
+
+fn package_name(demo: &str) -> String {
+    match demo {
+        "bootloader" => "pinecil-bootloader".to_owned(),
+        d if d.starts_with(|c: char| c.is_ascii_digit()) => format!("demo-{}", d),
+        d => d.to_owned(),
+    }
+}
+
+fn build(demo: &str, release: bool, for_bootloader: bool) -> Result<()> {
+    let name = package_name(demo);
+
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut cmd = Command::new(cargo);
+    cmd.current_dir(workspace_root())
+        .args(["build", "-p", &name]);
+    if release {
+        cmd.arg("--release");
+    }
+    if for_bootloader {
+        // `RUSTFLAGS` replaces the flags from `.cargo/config`, so they need to
+        // be repeated, after any the user has set. The `--defsym` must come
+        // before `link.x`, otherwise the linker keeps the default `_stext`.
+        let mut flags = env::var("RUSTFLAGS").unwrap_or_default();
+        if !flags.trim().is_empty() {
+            flags.push(' ');
+        }
+        flags.push_str(&format!(
+            "-C link-arg=--defsym=_stext={:#010x} -C link-arg=-Tmemory.x -C link-arg=-Tlink.x",
+            image::APP_ENTRY
+        ));
+        cmd.env("RUSTFLAGS", flags);
+    }
+    if !cmd.status()?.success() {
+        return Err("cargo build failed".into());
+    }
+
+    let profile = if release { "release" } else { "debug" };
+    let elf = target_dir().join(TARGET).join(profile).join(&name);
+    let out_dir = target_dir().join("firmware");
+    fs::create_dir_all(&out_dir)?;
+    package(
+        &elf,
+        &out_dir.join(name).with_extension("bin"),
+        for_bootloader,
+    )
+}
+
+fn package(elf_path: &Path, out: &Path, for_bootloader: bool) -> Result<()> {
+    let elf_data = fs::read(elf_path)?;
+    let mut bin = package::package(&elf_data, for_bootloader)?;
+    fs::write(out, &bin)?;
+    println!("Wrote {}", out.display());
+
+    package::append_dfu_suffix(&mut bin, DFU_VID, DFU_PID);
+    let dfu = out.with_extension("dfu");
+    fs::write(&dfu, &bin)?;
+    println!("Wrote {}", dfu.display());
+
+    println!();
+    print!("{}", size::size_report(&Elf::parse(&elf_data)?));
+    Ok(())
+}
+
+fn print_size(elf_path: &Path) -> Result<()> {
+    let elf_data = fs::read(elf_path)?;
+    print!("{}", size::size_report(&Elf::parse(&elf_data)?));
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! Turns a firmware ELF into something `dfu-util` can flash.
+
+use std::{convert::TryFrom, fmt};
+
+use pinecil_core::image::{self, ImageHeader};
+
+use crate::elf::{Elf, ParseError};
+
+pub const DFU_SUFFIX_LEN: usize = 16;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PackageError {
+    Elf(ParseError),
+    Empty,
+    /// The application was not linked for the bootloader.
+    WrongBase(u32),
+    TooLarge(u32),
+    /// A section at this load address runs past the end of the address
+    /// space.
+    AddressOverflow(u32),
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackageError::Elf(e) => e.fmt(f),
+            PackageError::Empty => write!(f, "no loadable sections"),
+            PackageError::WrongBase(base) => write!(
+                f,
+                "image starts at {:#010x} but the bootloader expects {:#010x}",
+                base,
+                image::APP_ENTRY
+            ),
+            PackageError::TooLarge(size) => write!(
+                f,
+                "image is {} bytes, only {} bytes available",
+                size,
+                image::APP_MAX_SIZE
+            ),
+            PackageError::AddressOverflow(addr) => write!(
+                f,
+                "section at {:#010x} runs past the end of the address space",
+                addr
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PackageError {}
+
+impl From<ParseError> for PackageError {
+    fn from(e: ParseError) -> Self {
+        PackageError::Elf(e)
+    }
+}
+
+/// Raw contents of the flash, starting at `base`.
+#[derive(Debug)]
+pub struct Binary {
+    pub base: u32,
+    pub data: Vec<u8>,
+}
+
+/// Equivalent of `objcopy -O binary`: lays out all sections with contents at
+/// their load address, filling the gaps with zeroes.
+pub fn extract_binary(elf: &Elf) -> Result<Binary, PackageError> {
+    let loadable = || {
+        elf.sections
+            .iter()
+            .filter_map(|s| s.data.map(|d| (s.load_addr, d)))
+            .filter(|(_, d)| !d.is_empty())
+    };
+    let base = loadable()
+        .map(|(a, _)| a)
+        .min()
+        .ok_or(PackageError::Empty)?;
+    let mut end = base;
+    for (addr, d) in loadable() {
+        let section_end = u32::try_from(d.len())
+            .ok()
+            .and_then(|len| addr.checked_add(len))
+            .ok_or(PackageError::AddressOverflow(addr))?;
+        end = end.max(section_end);
+    }
+
+    let mut data = vec![0; (end - base) as usize];
+    for (addr, d) in loadable() {
+        let start = (addr - base) as usize;
+        data[start..start + d.len()].copy_from_slice(d);
+    }
+    Ok(Binary { base, data })
+}
+
+/// Prepends the image header checked by the resident bootloader.
+pub fn with_image_header(bin: &Binary) -> Result<Vec<u8>, PackageError> {
+    if bin.base != image::APP_ENTRY {
+        return Err(PackageError::WrongBase(bin.base));
+    }
+    if bin.data.len() as u32 > image::APP_MAX_SIZE {
+        return Err(PackageError::TooLarge(bin.data.len() as u32));
+    }
+    let mut out = vec![0xff; image::HEADER_SIZE as usize];
+    out[..image::HEADER_LEN].copy_from_slice(&ImageHeader::for_app(&bin.data).to_bytes());
+    out.extend_from_slice(&bin.data);
+    Ok(out)
+}
+
+/// Appends the DFU file suffix from the DFU 1.1 specification (appendix B).
+pub fn append_dfu_suffix(data: &mut Vec<u8>, vid: u16, pid: u16) {
+    data.extend_from_slice(&0xffff_u16.to_le_bytes()); // bcdDevice: any
+    data.extend_from_slice(&pid.to_le_bytes());
+    data.extend_from_slice(&vid.to_le_bytes());
+    data.extend_from_slice(&0x0100_u16.to_le_bytes()); // bcdDFU
+    data.extend_from_slice(b"UFD");
+    data.push(DFU_SUFFIX_LEN as u8);
+    // Unlike the usual CRC-32, the DFU suffix leaves out the final inversion.
+    let crc = image::crc32_update(!0, data);
+    data.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// Builds the raw binary from an ELF. With `for_bootloader`, the image header
+/// is added and the image must have been linked behind it.
+pub fn package(elf_data: &[u8], for_bootloader: bool) -> Result<Vec<u8>, PackageError> {
+    let elf = Elf::parse(elf_data)?;
+    let bin = extract_binary(&elf)?;
+    if for_bootloader {
+        with_image_header(&bin)
+    } else {
+        Ok(bin.data)
+    }
+}
@@ -0,0 +1,81 @@
+//! Section size report, similar to `size -A` with a summary of the flash and
+//! RAM usage.
+
+use std::fmt;
+
+use pinecil_core::image::{FLASH_BASE, FLASH_SIZE};
+
+use crate::elf::Elf;
+
+// Same as `memory.x`.
+pub const RAM_BASE: u32 = 0x2000_0000;
+pub const RAM_SIZE: u32 = 32 * 1024;
+
+pub struct SectionSize {
+    pub name: String,
+    pub addr: u32,
+    pub size: u32,
+}
+
+pub struct SizeReport {
+    pub sections: Vec<SectionSize>,
+    /// Bytes taken in flash, including the initial values of `.data`.
+    pub flash: u32,
+    /// Bytes of RAM statically allocated, not counting the stack.
+    pub ram: u32,
+}
+
+fn in_region(addr: u32, base: u32, size: u32) -> bool {
+    addr >= base && addr - base < size
+}
+
+pub fn size_report(elf: &Elf) -> SizeReport {
+    let mut report = SizeReport {
+        sections: Vec::new(),
+        flash: 0,
+        ram: 0,
+    };
+    for s in elf.sections.iter().filter(|s| s.size != 0) {
+        // Skip `NOLOAD` placeholders in flash such as `.text.dummy`.
+        if s.data.is_none() && !in_region(s.addr, RAM_BASE, RAM_SIZE) {
+            continue;
+        }
+        if s.data.is_some() && in_region(s.load_addr, FLASH_BASE, FLASH_SIZE) {
+            report.flash += s.size;
+        }
+        // `.stack` covers whatever RAM is left over.
+        if s.name != ".stack" && in_region(s.addr, RAM_BASE, RAM_SIZE) {
+            report.ram += s.size;
+        }
+        report.sections.push(SectionSize {
+            name: s.name.to_owned(),
+            addr: s.addr,
+            size: s.size,
+        });
+    }
+    report
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<16} {:>10} {:>8}", "section", "addr", "size")?;
+        for s in &self.sections {
+            writeln!(f, "{:<16} {:#010x} {:>8}", s.name, s.addr, s.size)?;
+        }
+        let percent = |used: u32, total: u32| used as f32 * 100.0 / total as f32;
+        writeln!(
+            f,
+            "Flash: {:>6} / {} bytes ({:.1}%)",
+            self.flash,
+            FLASH_SIZE,
+            percent(self.flash, FLASH_SIZE)
+        )?;
+        writeln!(
+            f,
+            "RAM:   {:>6} / {} bytes ({:.1}%), the rest is left for the stack",
+            self.ram,
+            RAM_SIZE,
+            percent(self.ram, RAM_SIZE)
+        )
+    }
+}
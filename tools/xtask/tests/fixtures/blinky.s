@@ -0,0 +1,29 @@
+# Minimal stand-in for a `riscv-rt` program, used to produce the fixture ELF
+# files. See `build.sh`.
+
+    .section .init, "ax"
+    .globl _start
+_start:
+    la sp, _stack_start
+    la a0, message
+    la a1, counter
+1:
+    lw t0, 0(a1)
+    addi t0, t0, 1
+    sw t0, 0(a1)
+    lbu t1, 0(a0)
+    j 1b
+
+    .section .rodata, "a"
+message:
+    .asciz "Hello world!"
+
+    .section .data, "aw"
+    .p2align 2
+counter:
+    .word 0x12345678
+
+    .section .bss, "aw", @nobits
+    .p2align 2
+buffer:
+    .space 64
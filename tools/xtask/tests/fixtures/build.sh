@@ -0,0 +1,21 @@
+#!/bin/sh
+# Regenerates the fixture ELF files and the reference binaries produced by
+# `objcopy`. Needs `llvm-mc`, `ld.lld` (or `rust-lld -flavor gnu`) and
+# `llvm-objcopy`.
+set -e
+cd "$(dirname "$0")"
+
+LD=${LD:-ld.lld}
+
+llvm-mc -triple=riscv32 -mattr=+m,+a,+c -filetype=obj blinky.s -o blinky.o
+
+# Linked at the start of the flash, like the demos.
+$LD -T link.x blinky.o -o blinky.elf
+llvm-objcopy -O binary blinky.elf blinky.bin
+
+# Linked behind the bootloader and the image header.
+# `--defsym` has to come before the script for `ld.lld` to pick it up.
+$LD --defsym=_stext=0x08004100 -T link.x blinky.o -o blinky-app.elf
+llvm-objcopy -O binary blinky-app.elf blinky-app.bin
+
+rm blinky.o
@@ -0,0 +1,46 @@
+/* Cut-down version of the `riscv-rt` 0.8 linker script. */
+MEMORY
+{
+    FLASH (rx)      : ORIGIN = 0x08000000, LENGTH = 128k
+    RAM (xrw)       : ORIGIN = 0x20000000, LENGTH = 32k
+}
+
+PROVIDE(_stext = ORIGIN(FLASH));
+PROVIDE(_stack_start = ORIGIN(RAM) + LENGTH(RAM));
+
+ENTRY(_start)
+
+SECTIONS
+{
+    .text.dummy (NOLOAD) :
+    {
+        . = ABSOLUTE(_stext);
+    } > FLASH
+
+    .text _stext :
+    {
+        KEEP(*(.init));
+        *(.text .text.*);
+    } > FLASH
+
+    .rodata : ALIGN(4)
+    {
+        *(.rodata .rodata.*);
+        . = ALIGN(4);
+    } > FLASH
+
+    .data : ALIGN(4)
+    {
+        *(.data .data.*);
+    } > RAM AT > FLASH
+
+    .bss (NOLOAD) :
+    {
+        *(.bss .bss.*);
+    } > RAM
+
+    .stack (NOLOAD) :
+    {
+        . = ABSOLUTE(_stack_start);
+    } > RAM
+}
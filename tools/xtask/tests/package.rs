@@ -0,0 +1,162 @@
+//! Packaging tests against the ELF files in `fixtures`, which are checked
+//! against the output of `llvm-objcopy -O binary`.
+
+use pinecil_core::image::{self, ImageHeader};
+use xtask::{
+    elf::{Elf, ParseError, Section},
+    package::{self, PackageError, DFU_SUFFIX_LEN},
+    size,
+};
+
+const BLINKY_ELF: &[u8] = include_bytes!("fixtures/blinky.elf");
+const BLINKY_BIN: &[u8] = include_bytes!("fixtures/blinky.bin");
+const BLINKY_APP_ELF: &[u8] = include_bytes!("fixtures/blinky-app.elf");
+const BLINKY_APP_BIN: &[u8] = include_bytes!("fixtures/blinky-app.bin");
+
+#[test]
+fn extract_matches_objcopy() {
+    let bin = package::extract_binary(&Elf::parse(BLINKY_ELF).unwrap()).unwrap();
+    assert_eq!(bin.base, image::FLASH_BASE);
+    assert_eq!(bin.data, BLINKY_BIN);
+
+    let bin = package::extract_binary(&Elf::parse(BLINKY_APP_ELF).unwrap()).unwrap();
+    assert_eq!(bin.base, image::APP_ENTRY);
+    assert_eq!(bin.data, BLINKY_APP_BIN);
+}
+
+#[test]
+fn data_is_placed_at_load_address() {
+    let elf = Elf::parse(BLINKY_ELF).unwrap();
+    let data = elf.sections.iter().find(|s| s.name == ".data").unwrap();
+    assert_eq!(data.addr, 0x2000_0000);
+    assert!(data.load_addr > image::FLASH_BASE);
+
+    let bin = package::extract_binary(&elf).unwrap();
+    let offset = (data.load_addr - bin.base) as usize;
+    assert_eq!(
+        &bin.data[offset..offset + 4],
+        &0x1234_5678_u32.to_le_bytes()
+    );
+}
+
+#[test]
+fn plain_image() {
+    assert_eq!(package::package(BLINKY_ELF, false).unwrap(), BLINKY_BIN);
+}
+
+#[test]
+fn bootloader_image_has_valid_header() {
+    let out = package::package(BLINKY_APP_ELF, true).unwrap();
+    assert_eq!(
+        out.len(),
+        image::HEADER_SIZE as usize + BLINKY_APP_BIN.len()
+    );
+
+    let header = ImageHeader::parse(&out).unwrap();
+    assert_eq!(header.size as usize, BLINKY_APP_BIN.len());
+    assert!(out[image::HEADER_LEN..image::HEADER_SIZE as usize]
+        .iter()
+        .all(|&b| b == 0xff));
+
+    let app = &out[image::HEADER_SIZE as usize..];
+    assert_eq!(app, BLINKY_APP_BIN);
+    header.verify(app).unwrap();
+}
+
+#[test]
+fn bootloader_image_rejects_wrong_link_address() {
+    assert_eq!(
+        package::package(BLINKY_ELF, true),
+        Err(PackageError::WrongBase(image::FLASH_BASE))
+    );
+}
+
+#[test]
+fn dfu_suffix() {
+    let mut out = BLINKY_BIN.to_vec();
+    package::append_dfu_suffix(&mut out, image::DFU_VID, image::DFU_PID);
+    assert_eq!(out.len(), BLINKY_BIN.len() + DFU_SUFFIX_LEN);
+
+    let suffix = &out[BLINKY_BIN.len()..];
+    assert_eq!(&suffix[0..2], &[0xff, 0xff]);
+    assert_eq!(&suffix[2..4], &image::DFU_PID.to_le_bytes());
+    assert_eq!(&suffix[4..6], &image::DFU_VID.to_le_bytes());
+    assert_eq!(&suffix[6..8], &[0x00, 0x01]);
+    assert_eq!(&suffix[8..11], b"UFD");
+    assert_eq!(suffix[11], 16);
+
+    // Computed with zlib's CRC-32 over everything before the CRC field, without
+    // the final inversion (same as `dfu-suffix`).
+    let crc = u32::from_le_bytes([suffix[12], suffix[13], suffix[14], suffix[15]]);
+    assert_eq!(crc, 0x2809_3e2a);
+}
+
+#[test]
+fn size_report() {
+    let report = size::size_report(&Elf::parse(BLINKY_ELF).unwrap());
+    let names: Vec<&str> = report.sections.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, [".text", ".rodata", ".data", ".bss", ".stack"]);
+    // .text + .rodata + .data
+    assert_eq!(report.flash, 0x28 + 0x10 + 4);
+    // .data + .bss
+    assert_eq!(report.ram, 4 + 64);
+}
+
+#[test]
+fn rejects_non_elf() {
+    assert_eq!(
+        package::package(BLINKY_BIN, false),
+        Err(PackageError::Elf(ParseError::NotElf))
+    );
+    assert_eq!(
+        Elf::parse(&BLINKY_ELF[..0x40]).err(),
+        Some(ParseError::Truncated)
+    );
+}
+
+#[test]
+fn rejects_malformed_elf() {
+    // Cut off right after the magic.
+    assert_eq!(
+        Elf::parse(&BLINKY_ELF[..4]).err(),
+        Some(ParseError::Truncated)
+    );
+
+    // A section with contents reaching past the end of the address space.
+    let u32_at = |i: usize| {
+        u32::from_le_bytes([
+            BLINKY_ELF[i],
+            BLINKY_ELF[i + 1],
+            BLINKY_ELF[i + 2],
+            BLINKY_ELF[i + 3],
+        ])
+    };
+    let u16_at = |i: usize| u16::from_le_bytes([BLINKY_ELF[i], BLINKY_ELF[i + 1]]) as usize;
+    let (shoff, shentsize, shnum) = (u32_at(0x20) as usize, u16_at(0x2e), u16_at(0x30));
+    let text = (0..shnum)
+        .map(|i| shoff + i * shentsize)
+        .find(|&sh| u32_at(sh + 4) == 1 && u32_at(sh + 8) & 2 != 0)
+        .unwrap();
+    let mut elf = BLINKY_ELF.to_vec();
+    elf[text + 20..text + 24].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(Elf::parse(&elf).err(), Some(ParseError::Truncated));
+}
+
+#[test]
+fn rejects_section_past_address_space() {
+    let data = [0; 32];
+    let elf = Elf {
+        entry: 0xffff_fff0,
+        sections: vec![Section {
+            name: ".text",
+            addr: 0xffff_fff0,
+            load_addr: 0xffff_fff0,
+            size: data.len() as u32,
+            data: Some(&data),
+        }],
+    };
+    assert_eq!(
+        package::extract_binary(&elf).err(),
+        Some(PackageError::AddressOverflow(0xffff_fff0))
+    );
+}